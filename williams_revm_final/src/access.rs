@@ -0,0 +1,62 @@
+// Read and write sets of executed transactions
+//
+// revm returns, with every transaction, the accounts and storage slots it
+// loaded. Everything loaded was read; an account was written when its
+// balance, nonce or code now differ from the state before the block, and a
+// slot when its value changed. (revm marks every call target as touched, so
+// being touched alone says nothing.)
+//
+// Two transactions conflict when one reads a location the other wrote. The
+// block's coinbase is left out: every transaction credits it, and executors
+// account for those credits separately.
+
+use std::collections::HashSet;
+
+use revm::primitives::{Account, Address, EvmState, U256};
+use revm::DatabaseRef;
+
+/// An account (`None`) or one of its storage slots
+pub type Location = (Address, Option<U256>);
+
+#[derive(Debug, Default)]
+pub struct AccessSet {
+    pub reads: Vec<Location>,
+    pub writes: Vec<Location>,
+}
+
+impl AccessSet {
+    /// What a transaction with state changes `state` read and wrote,
+    /// judging account writes against `base`
+    pub fn of<B: DatabaseRef>(state: &EvmState, base: &B, coinbase: Address) -> Self {
+        let mut access = Self::default();
+        for (address, account) in state.iter().filter(|(address, _)| **address != coinbase) {
+            access.reads.push((*address, None));
+            access.reads.extend(account.storage.keys().map(|slot| (*address, Some(*slot))));
+
+            if account.is_touched() && account_changed(base, *address, account) {
+                access.writes.push((*address, None));
+            }
+            access.writes.extend(account.changed_storage_slots().map(|(slot, _)| (*address, Some(*slot))));
+        }
+        access
+    }
+
+    /// Whether anything read here is in `written`
+    pub fn reads_any(&self, written: &HashSet<Location>) -> bool {
+        self.reads.iter().any(|location| written.contains(location))
+    }
+}
+
+fn account_changed<B: DatabaseRef>(base: &B, address: Address, account: &Account) -> bool {
+    if account.is_created() || account.is_selfdestructed() {
+        return true;
+    }
+    match base.basic_ref(address).ok().flatten() {
+        Some(before) => {
+            before.balance != account.info.balance
+                || before.nonce != account.info.nonce
+                || before.code_hash != account.info.code_hash
+        }
+        None => !account.info.is_empty(),
+    }
+}
@@ -0,0 +1,374 @@
+// Pre-decoded block cache
+//
+// Parsing JSON costs more than executing it, so `convert` decodes every
+// block once (BlockEnv, TxEnv with resolved senders, classification,
+// finalization and system-call inputs) and stores the result in a single
+// binary container:
+//
+//   header:  magic | version u32 | flags u32 | chain id u64 | count u64 | index offset u64
+//            | dataset manifest sha256 (32 bytes, zero without a manifest)
+//   records: bincode-encoded PreparedBlock, back to back
+//   index:   (block number u64, offset u64, length u64) per block
+//
+// A run maps the file into memory and decodes blocks straight from the
+// mapping, so repeated experiments pay neither file reads nor JSON parsing.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Result, Context, bail};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use revm::primitives::{BlockEnv, SpecId, TxEnv};
+
+use crate::finalize::FinalizeInputs;
+use crate::sender::SenderReport;
+use crate::system_calls::SystemCallInputs;
+
+const MAGIC: &[u8; 8] = b"WILLBLKC";
+
+/// Bumped whenever `PreparedBlock` changes shape
+const FORMAT_VERSION: u32 = 3;
+
+/// Senders were recovered and checked for every transaction
+const FLAG_VERIFIED_SENDERS: u32 = 1;
+
+const HEADER_SIZE: usize = 8 + 4 + 4 + 8 + 8 + 8 + 32;
+const INDEX_ENTRY_SIZE: usize = 24;
+
+/// A transaction ready for execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedTx {
+    /// Caller already resolved (JSON `from` or recovered signer)
+    #[serde(with = "cached_tx_env")]
+    pub tx_env: TxEnv,
+    pub deterministic: bool,
+}
+
+/// bincode cannot decode EIP-7702 authorizations in REVM's serde form
+/// (flattened signatures), so they are stored as plain fields beside the rest
+/// of the transaction
+mod cached_tx_env {
+    use serde::de::Deserializer;
+    use serde::ser::{Error, Serializer};
+    use serde::{Deserialize, Serialize};
+
+    use revm::primitives::{Address, Authorization, AuthorizationList, TxEnv, U256};
+    #[allow(deprecated)]
+    use revm::primitives::Signature;
+
+    #[derive(Serialize, Deserialize)]
+    struct CachedAuthorization {
+        chain_id: U256,
+        address: Address,
+        nonce: u64,
+        odd_y_parity: bool,
+        r: U256,
+        s: U256,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct CachedTxEnv {
+        tx_env: TxEnv,
+        authorizations: Option<Vec<CachedAuthorization>>,
+    }
+
+    pub fn serialize<S: Serializer>(tx_env: &TxEnv, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tx_env = tx_env.clone();
+        let authorizations = match tx_env.authorization_list.take() {
+            None => None,
+            Some(AuthorizationList::Signed(signed)) => Some(signed.iter()
+                .map(|auth| CachedAuthorization {
+                    chain_id: auth.chain_id,
+                    address: auth.address,
+                    nonce: auth.nonce,
+                    odd_y_parity: auth.signature().v().y_parity(),
+                    r: auth.signature().r(),
+                    s: auth.signature().s(),
+                })
+                .collect()),
+            Some(AuthorizationList::Recovered(_)) => {
+                return Err(S::Error::custom("recovered authorizations cannot be cached"));
+            }
+        };
+        CachedTxEnv { tx_env, authorizations }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TxEnv, D::Error> {
+        let CachedTxEnv { mut tx_env, authorizations } = CachedTxEnv::deserialize(deserializer)?;
+        tx_env.authorization_list = authorizations.map(|authorizations| {
+            let signed = authorizations.into_iter()
+                .map(|auth| {
+                    #[allow(deprecated)]
+                    let signature = Signature::new(auth.r, auth.s, auth.odd_y_parity.into());
+                    Authorization { chain_id: auth.chain_id, address: auth.address, nonce: auth.nonce }
+                        .into_signed(signature)
+                })
+                .collect::<Vec<_>>();
+            AuthorizationList::Signed(signed)
+        });
+        Ok(tx_env)
+    }
+}
+
+/// Everything execution needs from a block, with all parsing done
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedBlock {
+    pub number: u64,
+    pub block_env: BlockEnv,
+    pub spec_id: SpecId,
+    pub missing_header_fields: Vec<String>,
+    pub txs: Vec<PreparedTx>,
+    pub senders: SenderReport,
+    pub sender_recovery_us: u128,
+    pub finalize: FinalizeInputs,
+    pub system_calls: SystemCallInputs,
+    /// Measured each time the block is prepared, so never cached
+    #[serde(skip)]
+    pub timings: PrepareTimings,
+}
+
+/// Time spent turning a dataset entry into a `PreparedBlock`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PrepareTimings {
+    /// Reading the entry, or decoding it from the block cache
+    pub read_us: u128,
+    /// Parsing the block, and its transactions into EVM form
+    pub parse_us: u128,
+    pub classify_us: u128,
+}
+
+/// Location of one block inside the cache
+#[derive(Debug, Clone, Copy)]
+pub struct CacheEntry {
+    pub number: u64,
+    offset: usize,
+    len: usize,
+}
+
+/// Streams prepared blocks into a cache file
+pub struct BlockCacheWriter {
+    out: BufWriter<File>,
+    index: Vec<CacheEntry>,
+    offset: usize,
+    chain_id: u64,
+    flags: u32,
+    manifest: [u8; 32],
+}
+
+impl BlockCacheWriter {
+    /// `manifest` is the hash of the dataset manifest the blocks were verified against
+    pub fn create(path: &Path, chain_id: u64, verified_senders: bool, manifest: Option<&str>) -> Result<Self> {
+        let manifest = match manifest {
+            Some(hash) => hex::decode(hash).ok()
+                .and_then(|raw| <[u8; 32]>::try_from(raw).ok())
+                .context("Invalid manifest hash")?,
+            None => [0u8; 32],
+        };
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        // Placeholder, rewritten by `finish` once count and index are known
+        out.write_all(&[0u8; HEADER_SIZE])?;
+        Ok(Self {
+            out,
+            index: Vec::new(),
+            offset: HEADER_SIZE,
+            chain_id,
+            flags: if verified_senders { FLAG_VERIFIED_SENDERS } else { 0 },
+            manifest,
+        })
+    }
+
+    /// Append a block encoded with `encode`
+    pub fn append(&mut self, number: u64, encoded: &[u8]) -> Result<()> {
+        self.out.write_all(encoded)?;
+        self.index.push(CacheEntry { number, offset: self.offset, len: encoded.len() });
+        self.offset += encoded.len();
+        Ok(())
+    }
+
+    /// Write the index and header; returns the number of blocks
+    pub fn finish(mut self) -> Result<usize> {
+        let index_offset = self.offset as u64;
+        for entry in &self.index {
+            self.out.write_all(&entry.number.to_le_bytes())?;
+            self.out.write_all(&(entry.offset as u64).to_le_bytes())?;
+            self.out.write_all(&(entry.len as u64).to_le_bytes())?;
+        }
+
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(MAGIC)?;
+        self.out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        self.out.write_all(&self.flags.to_le_bytes())?;
+        self.out.write_all(&self.chain_id.to_le_bytes())?;
+        self.out.write_all(&(self.index.len() as u64).to_le_bytes())?;
+        self.out.write_all(&index_offset.to_le_bytes())?;
+        self.out.write_all(&self.manifest)?;
+        self.out.flush()?;
+        Ok(self.index.len())
+    }
+}
+
+/// Serialize a prepared block for `BlockCacheWriter::append`
+pub fn encode(block: &PreparedBlock) -> Result<Vec<u8>> {
+    bincode::serialize(block).context("Failed to encode prepared block")
+}
+
+/// A memory-mapped block cache
+pub struct BlockCache {
+    map: Mmap,
+    pub chain_id: u64,
+    pub verified_senders: bool,
+    /// Hash of the dataset manifest at convert time
+    pub manifest: Option<String>,
+    pub entries: Vec<CacheEntry>,
+}
+
+impl BlockCache {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        // SAFETY: the cache is written once by `convert` and only read here;
+        // modifying it during a run is unsupported
+        let map = unsafe { Mmap::map(&file)? };
+
+        if map.len() < HEADER_SIZE || &map[..8] != MAGIC {
+            bail!("{} is not a block cache", path.display());
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(map[at..at + 4].try_into().expect("4 bytes"));
+        let u64_at = |at: usize| u64::from_le_bytes(map[at..at + 8].try_into().expect("8 bytes"));
+
+        let version = u32_at(8);
+        if version != FORMAT_VERSION {
+            bail!("Block cache format {} is not supported (expected {}), run convert again", version, FORMAT_VERSION);
+        }
+        let flags = u32_at(12);
+        let chain_id = u64_at(16);
+        let count = u64_at(24) as usize;
+        let index_offset = u64_at(32) as usize;
+        let manifest = &map[40..72];
+        let manifest = manifest.iter().any(|&b| b != 0).then(|| hex::encode(manifest));
+
+        // Sizes come from the file, so a corrupt header must not overflow
+        let index_end = count.checked_mul(INDEX_ENTRY_SIZE).and_then(|len| len.checked_add(index_offset));
+        if index_end != Some(map.len()) {
+            bail!("Block cache {} is truncated", path.display());
+        }
+        let entries = (0..count)
+            .map(|i| {
+                let at = index_offset + i * INDEX_ENTRY_SIZE;
+                let entry = CacheEntry {
+                    number: u64_at(at),
+                    offset: u64_at(at + 8) as usize,
+                    len: u64_at(at + 16) as usize,
+                };
+                if entry.offset.checked_add(entry.len).is_none_or(|end| end > index_offset) {
+                    bail!("Block cache entry {} out of bounds", i);
+                }
+                Ok(entry)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            chain_id,
+            verified_senders: flags & FLAG_VERIFIED_SENDERS != 0,
+            manifest,
+            entries,
+            map,
+        })
+    }
+
+    /// Decode one block from the mapping
+    pub fn block(&self, entry: &CacheEntry) -> Result<PreparedBlock> {
+        bincode::deserialize(&self.map[entry.offset..entry.offset + entry.len])
+            .with_context(|| format!("Failed to decode cached block {}", entry.number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use revm::primitives::{address, Authorization, AuthorizationList, U256};
+    #[allow(deprecated)]
+    use revm::primitives::Signature;
+
+    #[test]
+    fn set_code_transactions_survive_the_cache() {
+        #[allow(deprecated)]
+        let signature = Signature::from_rs_and_parity(U256::from(5), U256::from(6), true).unwrap();
+        let authorization = Authorization {
+            chain_id: U256::from(1),
+            address: address!("00000000000000000000000000000000000000c0"),
+            nonce: 3,
+        };
+        let tx = PreparedTx {
+            tx_env: TxEnv {
+                authorization_list: Some(AuthorizationList::Signed(vec![authorization.into_signed(signature)])),
+                nonce: Some(7),
+                ..Default::default()
+            },
+            deterministic: false,
+        };
+
+        let cached: PreparedTx = bincode::deserialize(&bincode::serialize(&tx).unwrap()).unwrap();
+        assert_eq!(cached.tx_env, tx.tx_env);
+    }
+
+    /// A cache holding two opaque records, as raw bytes
+    fn cache_file(name: &str) -> (std::path::PathBuf, Vec<u8>) {
+        let path = std::env::temp_dir().join(format!("williams-cache-{}-{}.bin", name, std::process::id()));
+        let mut writer = BlockCacheWriter::create(&path, 1, false, None).unwrap();
+        writer.append(7, b"first").unwrap();
+        writer.append(8, b"second").unwrap();
+        assert_eq!(writer.finish().unwrap(), 2);
+        let contents = std::fs::read(&path).unwrap();
+        (path, contents)
+    }
+
+    fn open_error(path: &Path, contents: &[u8]) -> String {
+        std::fs::write(path, contents).unwrap();
+        let error = BlockCache::open(path).err().expect("corrupt cache opened");
+        format!("{:#}", error)
+    }
+
+    #[test]
+    fn index_is_read_back() {
+        let (path, _) = cache_file("valid");
+        let cache = BlockCache::open(&path).unwrap();
+        assert_eq!(cache.chain_id, 1);
+        assert_eq!(cache.manifest, None);
+        let entries: Vec<_> = cache.entries.iter().map(|e| (e.number, e.offset, e.len)).collect();
+        assert_eq!(entries, vec![(7, HEADER_SIZE, 5), (8, HEADER_SIZE + 5, 6)]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_caches_are_rejected() {
+        let (path, valid) = cache_file("corrupt");
+
+        assert!(open_error(&path, &valid[..HEADER_SIZE - 1]).contains("is not a block cache"));
+        assert!(open_error(&path, &valid[..valid.len() - 1]).contains("is truncated"));
+
+        // Counts and offsets large enough to overflow the size computations
+        let mut garbage = valid.clone();
+        garbage[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(open_error(&path, &garbage).contains("is truncated"));
+        let mut garbage = valid.clone();
+        garbage[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(open_error(&path, &garbage).contains("is truncated"));
+
+        let index = valid.len() - 2 * INDEX_ENTRY_SIZE;
+        let mut garbage = valid.clone();
+        garbage[index + 8..index + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(open_error(&path, &garbage).contains("entry 0 out of bounds"));
+        let mut garbage = valid;
+        garbage[index + INDEX_ENTRY_SIZE + 16..index + INDEX_ENTRY_SIZE + 24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(open_error(&path, &garbage).contains("entry 1 out of bounds"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
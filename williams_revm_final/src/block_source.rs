@@ -0,0 +1,437 @@
+// Block sources: the files a dataset can hold
+//
+// A dataset is either a `blocks/` directory or a single JSONL file. The
+// directory may contain:
+// - `*.json`: one JSON-RPC block (`eth_getBlockByNumber` with full txs),
+//   optionally wrapped in the response envelope. A response whose `result`
+//   is a hex string is a `debug_getRawBlock` answer and is decoded as RLP.
+// - `*.rlp`: one raw RLP block, as binary or 0x-prefixed hex text
+// - `*.era1`: an Era1 archive, expanded into one entry per block
+//
+// A JSONL file (`.jsonl`, optionally `.gz` or `.zst` compressed) holds one
+// JSON block per line and is streamed, so memory stays bounded no matter
+// how many blocks it contains.
+//
+// Every source is loaded into the JSON-RPC block shape, so the executor
+// and all checks see one format.
+//
+// File names carry no meaning: `discover` reads each block's number from
+// the block itself, orders a directory numerically, leaves out duplicate
+// numbers and blocks without one, and summarizes what it found. A JSONL
+// file is replayed in file order; lines out of order are only counted.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Result, Context, anyhow};
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::era1;
+use crate::json_hex::field_u64;
+use crate::raw_block::{decode_block, raw_bytes};
+
+/// Single-file dataset names looked up when there is no `blocks/` directory
+const JSONL_FILES: [&str; 3] = ["blocks.jsonl", "blocks.jsonl.gz", "blocks.jsonl.zst"];
+
+/// Where a block is stored
+#[derive(Debug, Clone)]
+pub enum BlockLocation {
+    Json(PathBuf),
+    Rlp(PathBuf),
+    /// Era1 archive and the offset of the block's Header record
+    Era1 { path: PathBuf, offset: u64 },
+    /// One line of a JSONL dataset, already read
+    Line(String),
+}
+
+/// One block of a dataset
+#[derive(Debug, Clone)]
+pub struct BlockRef {
+    /// File name, with `:<number>` for archive blocks or `:<line>` for JSONL
+    pub name: String,
+    /// Block number, once known (archive index or `discover`)
+    pub number: Option<u64>,
+    pub location: BlockLocation,
+}
+
+/// A dataset: where its blocks are and where its metadata lives
+pub struct Dataset {
+    /// Directory holding `chain.toml` and `blocks.cache`
+    pub root: PathBuf,
+    /// `blocks/` directory or JSONL file
+    pub blocks: PathBuf,
+    /// Directory blocks in numeric order, filled by `discover`
+    listing: Option<Vec<BlockRef>>,
+    /// JSONL line indexes `discover` left out
+    skipped_lines: HashSet<usize>,
+}
+
+/// What discovery found in a dataset
+#[derive(Debug, Clone, Default)]
+pub struct DatasetSummary {
+    /// Blocks that will be replayed
+    pub blocks: usize,
+    pub range: Option<(u64, u64)>,
+    /// Missing block numbers as inclusive ranges
+    pub gaps: Vec<(u64, u64)>,
+    /// Extra copies of a block number, left out
+    pub duplicates: Vec<(u64, String)>,
+    /// Blocks whose number could not be read, left out, with the reason
+    pub unnumbered: Vec<(String, String)>,
+    /// JSONL lines with a lower number than a line before them
+    pub out_of_order: usize,
+}
+
+impl DatasetSummary {
+    pub fn missing_blocks(&self) -> u64 {
+        self.gaps.iter().map(|(from, to)| to - from + 1).sum()
+    }
+
+    fn from_numbers(mut numbers: Vec<u64>) -> Self {
+        numbers.sort_unstable();
+        numbers.dedup();
+        let gaps = numbers.windows(2)
+            .filter(|pair| pair[1] > pair[0] + 1)
+            .map(|pair| (pair[0] + 1, pair[1] - 1))
+            .collect();
+        Self {
+            blocks: numbers.len(),
+            range: numbers.first().copied().zip(numbers.last().copied()),
+            gaps,
+            ..Default::default()
+        }
+    }
+}
+
+impl Dataset {
+    /// Resolve a data directory, or a JSONL file given directly
+    ///
+    /// A directory uses `blocks/` if present, else the first of
+    /// `blocks.jsonl[.gz|.zst]` that exists.
+    pub fn locate(data_dir: &str) -> Self {
+        let path = PathBuf::from(data_dir);
+        if path.is_file() {
+            let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            return Self { root, blocks: path, listing: None, skipped_lines: HashSet::new() };
+        }
+
+        let blocks = path.join("blocks");
+        let blocks = if blocks.is_dir() {
+            blocks
+        } else {
+            JSONL_FILES.iter()
+                .map(|file| path.join(file))
+                .find(|file| file.is_file())
+                .unwrap_or(blocks)
+        };
+        Self { root: path, blocks, listing: None, skipped_lines: HashSet::new() }
+    }
+
+    /// Read every block number, decide the replay order and summarize
+    ///
+    /// Directory blocks are probed in parallel; only the `number` field is
+    /// deserialized from JSON files. A JSONL file is read through once.
+    pub fn discover(&mut self) -> Result<DatasetSummary> {
+        if self.blocks.is_file() {
+            return self.discover_jsonl();
+        }
+
+        let blocks = list_blocks(&self.blocks)?;
+        let probed: Vec<(BlockRef, Result<u64>)> = blocks
+            .into_par_iter()
+            .map(|block| {
+                let number = match block.number {
+                    Some(number) => Ok(number),
+                    None => probe_number(&block),
+                };
+                (block, number)
+            })
+            .collect();
+
+        let mut unnumbered = Vec::new();
+        let mut numbered = Vec::with_capacity(probed.len());
+        for (mut block, number) in probed {
+            match number {
+                Ok(number) => {
+                    block.number = Some(number);
+                    numbered.push((number, block));
+                }
+                Err(e) => unnumbered.push((block.name, format!("{:#}", e))),
+            }
+        }
+        numbered.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+        let mut duplicates = Vec::new();
+        let mut listing: Vec<BlockRef> = Vec::with_capacity(numbered.len());
+        for (number, block) in numbered {
+            if listing.last().is_some_and(|last| last.number == Some(number)) {
+                duplicates.push((number, block.name));
+            } else {
+                listing.push(block);
+            }
+        }
+
+        let mut summary = DatasetSummary::from_numbers(listing.iter().filter_map(|b| b.number).collect());
+        summary.duplicates = duplicates;
+        summary.unnumbered = unnumbered;
+        self.listing = Some(listing);
+        Ok(summary)
+    }
+
+    fn discover_jsonl(&mut self) -> Result<DatasetSummary> {
+        let mut seen = HashSet::new();
+        let mut numbers = Vec::new();
+        let mut duplicates = Vec::new();
+        let mut unnumbered = Vec::new();
+        let mut out_of_order = 0;
+        let mut skipped_lines = HashSet::new();
+        let mut highest = None;
+
+        for (line_index, block) in stream_jsonl(&self.blocks, &HashSet::new())?.enumerate() {
+            let block = block?;
+            match probe_number(&block) {
+                Ok(number) if !seen.insert(number) => {
+                    skipped_lines.insert(line_index);
+                    duplicates.push((number, block.name));
+                }
+                Ok(number) => {
+                    if highest.is_some_and(|highest| number < highest) {
+                        out_of_order += 1;
+                    }
+                    highest = highest.max(Some(number));
+                    numbers.push(number);
+                }
+                Err(e) => {
+                    skipped_lines.insert(line_index);
+                    unnumbered.push((block.name, format!("{:#}", e)));
+                }
+            }
+        }
+
+        let mut summary = DatasetSummary::from_numbers(numbers);
+        summary.duplicates = duplicates;
+        summary.unnumbered = unnumbered;
+        summary.out_of_order = out_of_order;
+        self.skipped_lines = skipped_lines;
+        Ok(summary)
+    }
+
+    /// Files holding the dataset's blocks, in name order
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        if self.blocks.is_file() {
+            return Ok(vec![self.blocks.clone()]);
+        }
+        block_files(&self.blocks)
+    }
+
+    /// Blocks of the dataset in replay order
+    ///
+    /// Without a prior `discover`, a directory is replayed in file name
+    /// order and a JSONL file line by line.
+    pub fn open(&self) -> Result<BlockStream> {
+        if self.blocks.is_file() {
+            return stream_jsonl(&self.blocks, &self.skipped_lines);
+        }
+        let blocks = match &self.listing {
+            Some(listing) => listing.clone(),
+            None => list_blocks(&self.blocks)?,
+        };
+        Ok(BlockStream {
+            len: Some(blocks.len()),
+            inner: Box::new(blocks.into_iter().map(Ok)),
+        })
+    }
+}
+
+/// Blocks of a dataset, yielded in order
+pub struct BlockStream {
+    /// Block count, when known without reading the whole dataset
+    pub len: Option<usize>,
+    inner: Box<dyn Iterator<Item = Result<BlockRef>> + Send>,
+}
+
+impl Iterator for BlockStream {
+    type Item = Result<BlockRef>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+/// Block files of a directory (`.json`, `.rlp`, `.era1`) in name order
+fn block_files(blocks_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(blocks_dir)
+        .context("Failed to read blocks directory")?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| matches!(path.extension().and_then(|s| s.to_str()), Some("json" | "rlp" | "era1")))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Blocks of a directory, files in name order
+fn list_blocks(blocks_dir: &Path) -> Result<Vec<BlockRef>> {
+    let mut blocks = Vec::new();
+    for path in block_files(blocks_dir)? {
+        let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("?").to_string();
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("json") => blocks.push(BlockRef {
+                number: None,
+                name,
+                location: BlockLocation::Json(path),
+            }),
+            Some("rlp") => blocks.push(BlockRef {
+                number: None,
+                name,
+                location: BlockLocation::Rlp(path),
+            }),
+            Some("era1") => {
+                let index = era1::read_index(&path)
+                    .with_context(|| format!("Failed to read Era1 index of {}", name))?;
+                for (i, offset) in index.offsets.into_iter().enumerate() {
+                    let number = index.start + i as u64;
+                    blocks.push(BlockRef {
+                        name: format!("{}:{}", name, number),
+                        number: Some(number),
+                        location: BlockLocation::Era1 { path: path.clone(), offset },
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Stream the lines of a JSONL file, decompressing by extension
+///
+/// Lines are only read, not parsed; parsing happens in `load_block` so it
+/// can run in parallel. Non-empty lines are indexed from zero and those in
+/// `skip` are left out. The stream ends after the first read error.
+fn stream_jsonl(path: &Path, skip: &HashSet<usize>) -> Result<BlockStream> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let reader: Box<dyn Read + Send> = match path.extension().and_then(|s| s.to_str()) {
+        Some("gz") => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Some("zst") => Box::new(zstd::Decoder::new(file).context("Failed to open zstd stream")?),
+        _ => Box::new(file),
+    };
+
+    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("?").to_string();
+    let skip = skip.clone();
+    let mut failed = false;
+    let lines = BufReader::new(reader)
+        .lines()
+        .enumerate()
+        .map_while(move |(index, line)| {
+            if failed {
+                return None;
+            }
+            failed = line.is_err();
+            Some((index, line))
+        })
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .enumerate()
+        .filter(move |(block_index, _)| !skip.contains(block_index))
+        .map(move |(_, (index, line))| {
+            let line = line.with_context(|| format!("Failed to read {} line {}", name, index + 1))?;
+            Ok(BlockRef {
+                name: format!("{}:{}", name, index + 1),
+                number: None,
+                location: BlockLocation::Line(line),
+            })
+        });
+
+    Ok(BlockStream { len: None, inner: Box::new(lines) })
+}
+
+/// Load a block into the JSON-RPC block shape
+pub fn load_block(block: &BlockRef) -> Result<Value> {
+    load_block_timed(block).map(|(json, _)| json)
+}
+
+/// Time spent reading a dataset entry and parsing it into a block
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadTimings {
+    /// Era1 entries are read, decompressed and decoded in one step, all
+    /// counted as reading; JSONL lines are already read by the stream
+    pub read_us: u128,
+    pub parse_us: u128,
+}
+
+/// `load_block`, timing reading and parsing separately
+pub fn load_block_timed(block: &BlockRef) -> Result<(Value, LoadTimings)> {
+    let mut timings = LoadTimings::default();
+    let start = Instant::now();
+    let loaded = match &block.location {
+        BlockLocation::Json(path) => fs::read_to_string(path).map_err(anyhow::Error::from).and_then(|text| {
+            timings.read_us = start.elapsed().as_micros();
+            parse_block_json(&text)
+        }),
+        BlockLocation::Rlp(path) => fs::read(path).map_err(anyhow::Error::from).and_then(|raw| {
+            timings.read_us = start.elapsed().as_micros();
+            decode_block(&raw_bytes(raw)?)
+        }),
+        BlockLocation::Era1 { path, offset } => era1::read_block(path, *offset).inspect(|_| {
+            timings.read_us = start.elapsed().as_micros();
+        }),
+        BlockLocation::Line(line) => parse_block_json(line),
+    };
+    let json = loaded.with_context(|| format!("Failed to load {}", block.name))?;
+    timings.parse_us = start.elapsed().as_micros() - timings.read_us;
+    Ok((json, timings))
+}
+
+/// Parse a JSON block, unwrapping the JSON-RPC response envelope if present
+fn parse_block_json(text: &str) -> Result<Value> {
+    let json: Value = serde_json::from_str(text)?;
+    match json {
+        Value::Object(mut response) if response.contains_key("result") => {
+            match response.remove("result").unwrap_or_default() {
+                Value::String(raw) => decode_block(&raw_bytes(raw.into_bytes())?),
+                block => Ok(block),
+            }
+        }
+        block => Ok(block),
+    }
+}
+
+/// Block number of a dataset entry, reading as little as possible
+fn probe_number(block: &BlockRef) -> Result<u64> {
+    match &block.location {
+        BlockLocation::Json(path) => probe_json_number(&fs::read_to_string(path)?),
+        BlockLocation::Line(line) => probe_json_number(line),
+        _ => field_u64(&load_block(block)?, "number").ok_or_else(|| anyhow!("Block has no number")),
+    }
+}
+
+/// `number` of a JSON block or response, without building the whole tree
+fn probe_json_number(text: &str) -> Result<u64> {
+    #[derive(Deserialize)]
+    struct Probe<'a> {
+        number: Option<String>,
+        #[serde(borrow)]
+        result: Option<&'a RawValue>,
+    }
+
+    let probe: Probe = serde_json::from_str(text)?;
+    match probe.result {
+        // A raw block inside a response has to be decoded
+        Some(result) if result.get().starts_with('"') => {
+            field_u64(&parse_block_json(text)?, "number").ok_or_else(|| anyhow!("Block has no number"))
+        }
+        Some(result) => probe_json_number(result.get()),
+        None => {
+            let number = probe.number.ok_or_else(|| anyhow!("Block has no number"))?;
+            u64::from_str_radix(number.trim_start_matches("0x"), 16)
+                .with_context(|| format!("Invalid block number {}", number))
+        }
+    }
+}
@@ -0,0 +1,381 @@
+// Chain configuration for replaying non-mainnet EVM chains
+//
+// Chain parameters used to be implicit in `CfgEnvWithHandlerCfg` defaults
+// (chain id 1, latest spec). A `ChainConfig` makes them explicit so the same
+// binary can replay mainnet, Sepolia, Holesky or a private chain dataset.
+//
+// Config files are JSON or TOML (picked by extension). When a dataset
+// directory contains `chain.toml` or `chain.json` it is used automatically,
+// otherwise the built-in mainnet configuration applies.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Result, Context, bail};
+use serde::{Deserialize, Serialize};
+
+use revm::{
+    primitives::{Address, BlockEnv, CfgEnv, CfgEnvWithHandlerCfg, SpecId},
+    precompile::PrecompileSpecId,
+    ContextPrecompiles, Database, Evm,
+};
+
+/// File names looked up in a dataset directory, in priority order
+const CHAIN_CONFIG_FILES: [&str; 2] = ["chain.toml", "chain.json"];
+
+/// The chain config file `discover` uses for a dataset directory, if any
+pub fn config_file(data_dir: &Path) -> Option<PathBuf> {
+    CHAIN_CONFIG_FILES.iter()
+        .map(|file| data_dir.join(file))
+        .find(|path| path.exists())
+}
+
+/// Chain parameters needed to execute blocks of one EVM chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// Human readable chain name (only used for reporting)
+    #[serde(default = "default_chain_name")]
+    pub name: String,
+    /// EIP-155 chain id
+    pub chain_id: u64,
+    /// Fork activation schedule
+    pub forks: Vec<ForkActivation>,
+    /// EIP-1559 base fee parameters
+    #[serde(default)]
+    pub base_fee: BaseFeeParams,
+    /// EIP-4844 blob parameters keyed by the fork that introduces them
+    #[serde(default = "default_blob_schedule")]
+    pub blob_schedule: BTreeMap<String, BlobParams>,
+    /// Restricts the precompile set to these addresses (fork default when absent)
+    #[serde(default)]
+    pub precompiles: Option<Vec<Address>>,
+}
+
+/// A single fork activation, either by block number or by timestamp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForkActivation {
+    /// Fork name, e.g. "london", "shanghai", "cancun"
+    pub fork: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+/// EIP-1559 base fee parameters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BaseFeeParams {
+    pub max_change_denominator: u64,
+    pub elasticity_multiplier: u64,
+    /// Base fee of the first London block
+    pub initial_base_fee: u64,
+}
+
+impl Default for BaseFeeParams {
+    fn default() -> Self {
+        Self {
+            max_change_denominator: 8,
+            elasticity_multiplier: 2,
+            initial_base_fee: 1_000_000_000,
+        }
+    }
+}
+
+impl BaseFeeParams {
+    /// EIP-1559 base fee of a child block, derived from its parent
+    pub fn next_base_fee(&self, parent_gas_used: u64, parent_gas_limit: u64, parent_base_fee: u64) -> u64 {
+        let gas_target = parent_gas_limit / self.elasticity_multiplier;
+        if gas_target == 0 || parent_gas_used == gas_target {
+            return parent_base_fee;
+        }
+
+        let base_fee = parent_base_fee as u128;
+        let denominator = self.max_change_denominator as u128;
+        if parent_gas_used > gas_target {
+            let delta = (parent_gas_used - gas_target) as u128;
+            let increase = (base_fee * delta / gas_target as u128 / denominator).max(1);
+            (base_fee + increase) as u64
+        } else {
+            let delta = (gas_target - parent_gas_used) as u128;
+            let decrease = base_fee * delta / gas_target as u128 / denominator;
+            (base_fee - decrease) as u64
+        }
+    }
+}
+
+/// EIP-4844 blob parameters
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BlobParams {
+    pub target_blobs_per_block: u64,
+    pub max_blobs_per_block: u64,
+    pub update_fraction: u64,
+}
+
+fn default_chain_name() -> String {
+    "custom".to_string()
+}
+
+fn default_blob_schedule() -> BTreeMap<String, BlobParams> {
+    let mut schedule = BTreeMap::new();
+    schedule.insert("cancun".to_string(), BlobParams {
+        target_blobs_per_block: 3,
+        max_blobs_per_block: 6,
+        update_fraction: 3_338_477,
+    });
+    schedule.insert("prague".to_string(), BlobParams {
+        target_blobs_per_block: 6,
+        max_blobs_per_block: 9,
+        update_fraction: 5_007_716,
+    });
+    schedule
+}
+
+/// Map a fork name from a config file to the REVM spec id
+pub fn parse_fork_name(name: &str) -> Result<SpecId> {
+    let spec = match name.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+        "frontier" => SpecId::FRONTIER,
+        "homestead" => SpecId::HOMESTEAD,
+        "dao" | "dao_fork" => SpecId::DAO_FORK,
+        "tangerine" | "tangerine_whistle" | "eip150" => SpecId::TANGERINE,
+        "spurious_dragon" | "spurious" | "eip158" => SpecId::SPURIOUS_DRAGON,
+        "byzantium" => SpecId::BYZANTIUM,
+        "constantinople" => SpecId::CONSTANTINOPLE,
+        "petersburg" => SpecId::PETERSBURG,
+        "istanbul" => SpecId::ISTANBUL,
+        "muir_glacier" => SpecId::MUIR_GLACIER,
+        "berlin" => SpecId::BERLIN,
+        "london" => SpecId::LONDON,
+        "arrow_glacier" => SpecId::ARROW_GLACIER,
+        "gray_glacier" => SpecId::GRAY_GLACIER,
+        "merge" | "paris" => SpecId::MERGE,
+        "shanghai" => SpecId::SHANGHAI,
+        "cancun" => SpecId::CANCUN,
+        "prague" => SpecId::PRAGUE,
+        _ => bail!("Unknown fork name: {}", name),
+    };
+    Ok(spec)
+}
+
+impl ChainConfig {
+    /// Built-in Ethereum mainnet configuration
+    pub fn mainnet() -> Self {
+        let by_block = |fork: &str, block: u64| ForkActivation {
+            fork: fork.to_string(),
+            block: Some(block),
+            timestamp: None,
+        };
+        let by_time = |fork: &str, timestamp: u64| ForkActivation {
+            fork: fork.to_string(),
+            block: None,
+            timestamp: Some(timestamp),
+        };
+
+        Self {
+            name: "mainnet".to_string(),
+            chain_id: 1,
+            forks: vec![
+                by_block("frontier", 0),
+                by_block("homestead", 1_150_000),
+                by_block("dao_fork", 1_920_000),
+                by_block("tangerine", 2_463_000),
+                by_block("spurious_dragon", 2_675_000),
+                by_block("byzantium", 4_370_000),
+                by_block("petersburg", 7_280_000),
+                by_block("istanbul", 9_069_000),
+                by_block("muir_glacier", 9_200_000),
+                by_block("berlin", 12_244_000),
+                by_block("london", 12_965_000),
+                by_block("arrow_glacier", 13_773_000),
+                by_block("gray_glacier", 15_050_000),
+                by_block("merge", 15_537_394),
+                by_time("shanghai", 1_681_338_455),
+                by_time("cancun", 1_710_338_135),
+                by_time("prague", 1_746_612_311),
+            ],
+            base_fee: BaseFeeParams::default(),
+            blob_schedule: default_blob_schedule(),
+            precompiles: None,
+        }
+    }
+
+    /// Load a chain config from a `.toml` or `.json` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read chain config {}", path.display()))?;
+
+        let config: ChainConfig = match path.extension().and_then(|s| s.to_str()) {
+            Some("toml") => toml::from_str(&contents)
+                .with_context(|| format!("Invalid TOML chain config {}", path.display()))?,
+            Some("json") => serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid JSON chain config {}", path.display()))?,
+            _ => bail!("Chain config must be a .toml or .json file: {}", path.display()),
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Use `chain.toml`/`chain.json` from the dataset directory, or mainnet
+    pub fn discover(data_dir: &Path) -> Result<Self> {
+        match config_file(data_dir) {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::mainnet()),
+        }
+    }
+
+    /// Check fork and blob schedule names once, so lookups cannot fail later
+    fn validate(&self) -> Result<()> {
+        if self.forks.is_empty() {
+            bail!("Chain config '{}' has no forks", self.name);
+        }
+        for activation in &self.forks {
+            parse_fork_name(&activation.fork)?;
+            if activation.block.is_none() && activation.timestamp.is_none() {
+                bail!("Fork '{}' needs a block or timestamp activation", activation.fork);
+            }
+        }
+        for fork in self.blob_schedule.keys() {
+            parse_fork_name(fork)?;
+        }
+        if self.base_fee.max_change_denominator == 0 || self.base_fee.elasticity_multiplier == 0 {
+            bail!("Base fee parameters must be non-zero");
+        }
+        Ok(())
+    }
+
+    /// Active spec for a block, the latest fork whose activation has passed
+    pub fn spec_at(&self, number: u64, timestamp: u64) -> SpecId {
+        self.forks
+            .iter()
+            .filter(|f| {
+                f.block.is_some_and(|b| number >= b)
+                    || f.timestamp.is_some_and(|t| timestamp >= t)
+            })
+            .filter_map(|f| parse_fork_name(&f.fork).ok())
+            .max()
+            .unwrap_or(SpecId::FRONTIER)
+    }
+
+    /// Active spec for an already populated block environment
+    pub fn spec_for_block(&self, block_env: &BlockEnv) -> SpecId {
+        self.spec_at(block_env.number.saturating_to(), block_env.timestamp.saturating_to())
+    }
+
+    /// Blob parameters in effect for a spec (None before Cancun)
+    pub fn blob_params(&self, spec: SpecId) -> Option<BlobParams> {
+        self.blob_schedule
+            .iter()
+            .filter_map(|(fork, params)| Some((parse_fork_name(fork).ok()?, *params)))
+            .filter(|(fork_spec, _)| *fork_spec <= spec)
+            .max_by_key(|(fork_spec, _)| *fork_spec)
+            .map(|(_, params)| params)
+    }
+
+    /// EVM configuration for a spec on this chain
+    pub fn cfg_env(&self, spec: SpecId) -> CfgEnvWithHandlerCfg {
+        let mut cfg_env = CfgEnv::default();
+        cfg_env.chain_id = self.chain_id;
+        CfgEnvWithHandlerCfg::new_with_spec_id(cfg_env, spec)
+    }
+
+    /// Install the configured precompile set on a built EVM
+    ///
+    /// The set can only narrow what the fork provides; REVM has no
+    /// implementation for precompiles outside the fork's own set.
+    pub fn apply_precompiles<EXT, DB: Database>(&self, evm: &mut Evm<'_, EXT, DB>) {
+        let Some(addresses) = &self.precompiles else {
+            return;
+        };
+        let allowed: HashSet<Address> = addresses.iter().copied().collect();
+        let spec = evm.handler.cfg.spec_id;
+
+        evm.handler.pre_execution.load_precompiles = Arc::new(move || {
+            let mut precompiles = ContextPrecompiles::new(PrecompileSpecId::from_spec_id(spec));
+            precompiles.to_mut().retain(|addr, _| allowed.contains(addr));
+            precompiles
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(name: &str) -> ChainConfig {
+        ChainConfig::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("chains").join(name)).unwrap()
+    }
+
+    /// Every fork is active from its activation on and not one block or second before
+    fn assert_boundaries(chain: &ChainConfig) {
+        let last_block_fork = chain.forks.iter().filter_map(|f| f.block).max().unwrap();
+        for activation in &chain.forks {
+            let spec = parse_fork_name(&activation.fork).unwrap();
+            let (before, at) = match (activation.block, activation.timestamp) {
+                (Some(block), _) => (block.checked_sub(1).map(|b| chain.spec_at(b, 0)), chain.spec_at(block, 0)),
+                (None, Some(time)) => (
+                    Some(chain.spec_at(last_block_fork, time - 1)),
+                    chain.spec_at(last_block_fork, time),
+                ),
+                (None, None) => unreachable!(),
+            };
+            assert!(at >= spec, "{}: {:?} at activation", activation.fork, at);
+            if let Some(before) = before {
+                assert!(before < spec, "{}: {:?} before activation", activation.fork, before);
+            }
+        }
+    }
+
+    #[test]
+    fn mainnet_fork_boundaries() {
+        let chain = ChainConfig::mainnet();
+        assert_boundaries(&chain);
+        assert_eq!(chain.spec_at(0, 0), SpecId::FRONTIER);
+        assert_eq!(chain.spec_at(1_149_999, 0), SpecId::FRONTIER);
+        assert_eq!(chain.spec_at(1_150_000, 0), SpecId::HOMESTEAD);
+        assert_eq!(chain.spec_at(4_370_000, 0), SpecId::BYZANTIUM);
+        assert_eq!(chain.spec_at(7_280_000, 0), SpecId::PETERSBURG);
+        assert_eq!(chain.spec_at(12_964_999, 0), SpecId::BERLIN);
+        assert_eq!(chain.spec_at(12_965_000, 0), SpecId::LONDON);
+        assert_eq!(chain.spec_at(15_537_393, 1_663_224_162), SpecId::GRAY_GLACIER);
+        assert_eq!(chain.spec_at(15_537_394, 1_663_224_179), SpecId::MERGE);
+        assert_eq!(chain.spec_at(17_034_869, 1_681_338_454), SpecId::MERGE);
+        assert_eq!(chain.spec_at(17_034_870, 1_681_338_455), SpecId::SHANGHAI);
+        assert_eq!(chain.spec_at(19_426_587, 1_710_338_135), SpecId::CANCUN);
+        assert_eq!(chain.spec_at(22_431_084, 1_746_612_311), SpecId::PRAGUE);
+    }
+
+    #[test]
+    fn sepolia_fork_boundaries() {
+        let chain = example("sepolia.toml");
+        assert_eq!(chain.chain_id, 11_155_111);
+        assert_boundaries(&chain);
+        assert_eq!(chain.spec_at(0, 0), SpecId::LONDON);
+        assert_eq!(chain.spec_at(1_450_408, 0), SpecId::LONDON);
+        assert_eq!(chain.spec_at(1_450_409, 0), SpecId::MERGE);
+        assert_eq!(chain.spec_at(2_990_907, 1_677_557_087), SpecId::MERGE);
+        assert_eq!(chain.spec_at(2_990_908, 1_677_557_088), SpecId::SHANGHAI);
+        assert_eq!(chain.spec_at(5_187_023, 1_706_655_072), SpecId::CANCUN);
+        assert_eq!(chain.spec_at(7_836_331, 1_741_159_776), SpecId::PRAGUE);
+    }
+
+    #[test]
+    fn holesky_fork_boundaries() {
+        let chain = example("holesky.toml");
+        assert_eq!(chain.chain_id, 17_000);
+        assert_boundaries(&chain);
+        assert_eq!(chain.spec_at(0, 1_695_902_400), SpecId::MERGE);
+        assert_eq!(chain.spec_at(6_698, 1_696_000_704), SpecId::SHANGHAI);
+        assert_eq!(chain.spec_at(894_733, 1_707_305_664), SpecId::CANCUN);
+        assert_eq!(chain.spec_at(3_710_976, 1_740_434_112), SpecId::PRAGUE);
+    }
+
+    #[test]
+    fn blob_params_follow_the_fork() {
+        let chain = ChainConfig::mainnet();
+        assert!(chain.blob_params(SpecId::SHANGHAI).is_none());
+        assert_eq!(chain.blob_params(SpecId::CANCUN).unwrap().update_fraction, 3_338_477);
+        assert_eq!(chain.blob_params(SpecId::PRAGUE).unwrap().update_fraction, 5_007_716);
+    }
+}
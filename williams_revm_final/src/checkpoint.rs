@@ -0,0 +1,328 @@
+// Progress checkpoints of long runs
+//
+// A run over a large dataset saves its progress every `--checkpoint-every`
+// blocks, so one that dies part way through continues with `--resume`
+// instead of starting over. A checkpoint holds
+//
+// - the position in the block stream: how many dataset entries were taken
+//   and the last block completed. Every block executes on its own prestate,
+//   so no state is carried from block to block and the stream position is
+//   all the state backend needs to pick up again;
+// - the partial results, appended to a JSONL file so each checkpoint only
+//   writes the blocks completed since the previous one;
+// - what the executor learned: the gas profile is saved with every
+//   checkpoint, and the policy is fed the conflict rates of the completed
+//   blocks again on resume;
+// - the thread counts already finished, for multi-configuration runs.
+//
+// The checkpoint file is replaced atomically once the results are appended,
+// and results beyond the count it records are dropped on resume, so a run
+// killed while checkpointing resumes from the previous checkpoint. A resumed
+// run ends with the same blocks, in block order, and the same per-block
+// outcomes as an uninterrupted one. Timings are another matter: the bytecode
+// analysis cache starts empty again, so the blocks executed after a resume
+// are not comparable in time with those of an uninterrupted run.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::PathBuf;
+
+use anyhow::{Result, Context, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use williams_executor::ingest::ReadAheadStats;
+
+use crate::cli::RunOptions;
+use crate::BlockResult;
+
+/// Progress of the current run, and the results it covers
+const CHECKPOINT_FILE: &str = "williams_checkpoint.json";
+const CHECKPOINT_RESULTS_FILE: &str = "williams_checkpoint_results.jsonl";
+
+/// What a run was started with; a checkpoint only resumes the same run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RunIdentity {
+    data_dir: String,
+    /// Manifest hash, when the dataset has a manifest
+    manifest: Option<String>,
+    executor: String,
+    threads: Vec<usize>,
+    selection: String,
+    use_cache: bool,
+    use_prestate: bool,
+    verify_senders: bool,
+}
+
+impl RunIdentity {
+    fn of(options: &RunOptions, manifest: Option<&str>) -> Self {
+        Self {
+            data_dir: options.data_dir.clone(),
+            manifest: manifest.map(str::to_string),
+            executor: options.executor.clone(),
+            threads: options.threads.clone(),
+            selection: options.selection.to_string(),
+            use_cache: options.use_cache,
+            use_prestate: options.use_prestate,
+            verify_senders: options.verify_senders,
+        }
+    }
+}
+
+/// How far the run of one thread count got
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Progress {
+    threads: usize,
+    /// Dataset entries taken from the block stream, selected or not
+    position: usize,
+    last_block: Option<u64>,
+    /// Leading lines of the results file that belong to this checkpoint
+    results: usize,
+    /// Blocks that failed, in block order
+    failures: Vec<Failure>,
+    elapsed_us: u128,
+    ingestion: ReadAheadStats,
+}
+
+/// A block that failed, by its place among the completed blocks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Failure {
+    index: usize,
+    error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Checkpoint {
+    run: RunIdentity,
+    /// Thread counts whose runs completed
+    finished: Vec<usize>,
+    progress: Option<Progress>,
+}
+
+/// Where the interrupted run of a thread count left off
+pub struct Resumed {
+    pub position: usize,
+    pub last_block: Option<u64>,
+    /// Blocks completed, in block order
+    pub outcomes: Vec<Result<BlockResult>>,
+    pub elapsed_us: u128,
+    pub ingestion: ReadAheadStats,
+}
+
+/// Checkpoints of one `run`, kept in the output directory
+pub struct Checkpoints {
+    every: usize,
+    path: PathBuf,
+    results_path: PathBuf,
+    checkpoint: Checkpoint,
+    /// Outcomes of the current thread count already checkpointed
+    seen: usize,
+}
+
+impl Checkpoints {
+    /// Pick up the checkpoint of an earlier run with `--resume`, or start over
+    pub fn open(options: &RunOptions, manifest: Option<&str>) -> Result<Self> {
+        let run = RunIdentity::of(options, manifest);
+        let path = options.output_path(CHECKPOINT_FILE);
+        let results_path = options.output_path(CHECKPOINT_RESULTS_FILE);
+
+        let checkpoint = if options.resume && path.exists() {
+            let raw = fs::read(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let checkpoint: Checkpoint = serde_json::from_slice(&raw)
+                .with_context(|| format!("Invalid checkpoint {}", path.display()))?;
+            if checkpoint.run != run {
+                bail!("Checkpoint {} belongs to a different run (dataset, executor, threads or blocks differ); \
+                    run without --resume to start over", path.display());
+            }
+            checkpoint
+        } else {
+            if options.resume {
+                println!("No checkpoint at {}, starting from the first block", path.display());
+            } else if path.exists() {
+                println!("Note: replacing the checkpoint of an earlier run (pass --resume to continue it)");
+            }
+            Checkpoint { run, finished: Vec::new(), progress: None }
+        };
+        println!("Progress checkpoints:      every {} blocks in {}", options.checkpoint_every, path.display());
+        Ok(Self { every: options.checkpoint_every, path, results_path, checkpoint, seen: 0 })
+    }
+
+    /// Whether the run of a thread count completed before the restart
+    pub fn finished(&self, threads: usize) -> bool {
+        self.checkpoint.finished.contains(&threads)
+    }
+
+    /// Where the run of `threads` was interrupted, if it was
+    pub fn resume(&mut self, threads: usize) -> Result<Option<Resumed>> {
+        let Some(progress) = self.checkpoint.progress.clone().filter(|progress| progress.threads == threads) else {
+            return Ok(None);
+        };
+
+        let file = File::open(&self.results_path)
+            .with_context(|| format!("Failed to open {}", self.results_path.display()))?;
+        let mut reader = BufReader::new(file);
+        let total = progress.results + progress.failures.len();
+        let mut outcomes = Vec::with_capacity(total);
+        let mut failures = progress.failures.iter().peekable();
+        let mut results = 0;
+        let mut length = 0;
+        let mut line = String::new();
+        while outcomes.len() < total {
+            if let Some(failure) = failures.next_if(|failure| failure.index == outcomes.len()) {
+                outcomes.push(Err(anyhow!("{}", failure.error)));
+                continue;
+            }
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                bail!("{} ends after {} of {} checkpointed results",
+                    self.results_path.display(), results, progress.results
+                );
+            }
+            length += read as u64;
+            results += 1;
+            let result = serde_json::from_str(&line)
+                .with_context(|| format!("{}:{}: invalid result", self.results_path.display(), results))?;
+            outcomes.push(Ok(result));
+        }
+        // Results appended after the checkpoint was written are run again
+        OpenOptions::new().write(true).open(&self.results_path)?.set_len(length)?;
+
+        self.seen = outcomes.len();
+        Ok(Some(Resumed {
+            position: progress.position,
+            last_block: progress.last_block,
+            outcomes,
+            elapsed_us: progress.elapsed_us,
+            ingestion: progress.ingestion,
+        }))
+    }
+
+    /// Whether to checkpoint once `done` blocks are complete
+    pub fn due(&self, done: usize) -> bool {
+        done.is_multiple_of(self.every)
+    }
+
+    /// Record the outcomes of `threads` so far, `position` entries into the block stream
+    pub fn save(
+        &mut self,
+        threads: usize,
+        outcomes: &[Result<BlockResult>],
+        position: usize,
+        elapsed_us: u128,
+        ingestion: ReadAheadStats,
+    ) -> Result<()> {
+        let mut progress = self.checkpoint.progress.take()
+            .filter(|progress| progress.threads == threads)
+            .unwrap_or(Progress { threads, ..Default::default() });
+
+        let file = if progress.results == 0 {
+            File::create(&self.results_path)
+        } else {
+            OpenOptions::new().append(true).open(&self.results_path)
+        };
+        let file = file.with_context(|| format!("Failed to write {}", self.results_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        for (index, outcome) in outcomes.iter().enumerate().skip(self.seen) {
+            match outcome {
+                Ok(result) => {
+                    serde_json::to_writer(&mut writer, result)?;
+                    writer.write_all(b"\n")?;
+                    progress.results += 1;
+                    progress.last_block = Some(result.block_number);
+                }
+                Err(error) => progress.failures.push(Failure { index, error: format!("{:#}", error) }),
+            }
+        }
+        writer.into_inner()?.sync_data()?;
+
+        self.seen = outcomes.len();
+        progress.position = position;
+        progress.elapsed_us = elapsed_us;
+        progress.ingestion = ingestion;
+        self.checkpoint.progress = Some(progress);
+        self.write()
+    }
+
+    /// Mark the run of `threads` complete
+    pub fn finish(&mut self, threads: usize) -> Result<()> {
+        self.checkpoint.finished.push(threads);
+        self.checkpoint.progress = None;
+        self.seen = 0;
+        self.write()
+    }
+
+    /// The whole run completed: nothing left to resume
+    pub fn remove(self) -> Result<()> {
+        for path in [&self.path, &self.results_path] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the checkpoint file in one step, so it is never half written
+    fn write(&self) -> Result<()> {
+        let partial = self.path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_vec_pretty(&self.checkpoint)?)
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        fs::rename(&partial, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64) -> Result<BlockResult> {
+        Ok(BlockResult { block_number: number, tx_count: 1, ..Default::default() })
+    }
+
+    fn numbers(outcomes: &[Result<BlockResult>]) -> Vec<Option<u64>> {
+        outcomes.iter().map(|outcome| outcome.as_ref().ok().map(|result| result.block_number)).collect()
+    }
+
+    #[test]
+    fn resume_restores_outcomes_in_block_order() {
+        let dir = std::env::temp_dir().join(format!("williams-checkpoint-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let options = RunOptions { output_dir: dir.clone(), checkpoint_every: 2, ..Default::default() };
+
+        let mut outcomes = vec![block(10), Err(anyhow!("block 11 failed")), block(12), block(13)];
+        let mut checkpoints = Checkpoints::open(&options, None).unwrap();
+        checkpoints.save(4, &outcomes, 4, 100, ReadAheadStats::default()).unwrap();
+
+        // Killed while writing the next checkpoint: a result and half a line
+        // made it to the results file, the checkpoint itself did not
+        let checkpointed = fs::metadata(&checkpoints.results_path).unwrap().len();
+        outcomes.push(block(14));
+        let mut file = OpenOptions::new().append(true).open(&checkpoints.results_path).unwrap();
+        serde_json::to_writer(&mut file, outcomes[4].as_ref().unwrap()).unwrap();
+        file.write_all(b"\n{\"block_number\":").unwrap();
+        drop(file);
+
+        let resume_options = RunOptions { resume: true, ..options };
+        let mut checkpoints = Checkpoints::open(&resume_options, None).unwrap();
+        let resumed = checkpoints.resume(4).unwrap().unwrap();
+        assert_eq!(resumed.position, 4);
+        assert_eq!(resumed.last_block, Some(13));
+        assert_eq!(numbers(&resumed.outcomes), vec![Some(10), None, Some(12), Some(13)]);
+        assert_eq!(fs::metadata(&checkpoints.results_path).unwrap().len(), checkpointed);
+
+        // The resumed run continues the same files
+        let mut outcomes = resumed.outcomes;
+        outcomes.extend([Err(anyhow!("block 14 failed")), block(15)]);
+        checkpoints.save(4, &outcomes, 6, 200, ReadAheadStats::default()).unwrap();
+        let resumed = Checkpoints::open(&resume_options, None).unwrap().resume(4).unwrap().unwrap();
+        assert_eq!(numbers(&resumed.outcomes), vec![Some(10), None, Some(12), Some(13), None, Some(15)]);
+        assert_eq!(format!("{}", resumed.outcomes[1].as_ref().unwrap_err()), "block 11 failed");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+// Transaction classification without execution
+//
+// `classify` decodes the selected blocks and reports how the classifier
+// splits their transactions into deterministic and non-deterministic ones,
+// per block and in total, next to what kind of transaction each is: plain
+// value transfer, contract call or contract creation. Nothing is executed,
+// so it also works on datasets without prestate traces.
+//
+// The selectors most often classified non-deterministic are listed too; they
+// are the candidates for new classification rules.
+
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use revm::primitives::{FixedBytes, TransactTo};
+
+use williams_executor::block_cache::PreparedBlock;
+
+use crate::cli::{ReportFormat, RunOptions};
+use crate::{prepared_blocks, RunInputs};
+
+/// Per-block classification, written to `williams_classification.*`
+const CLASSIFICATION_FILE: &str = "williams_classification";
+
+#[derive(Debug, Default, Serialize)]
+struct BlockClasses {
+    block: u64,
+    txs: usize,
+    deterministic: usize,
+    non_deterministic: usize,
+    /// Calls without input
+    transfers: usize,
+    calls: usize,
+    creations: usize,
+}
+
+impl BlockClasses {
+    fn of(block: &PreparedBlock, selectors: &mut HashMap<FixedBytes<4>, usize>) -> Self {
+        let mut classes = Self { block: block.number, txs: block.txs.len(), ..Default::default() };
+        for tx in &block.txs {
+            if tx.deterministic {
+                classes.deterministic += 1;
+            } else {
+                classes.non_deterministic += 1;
+            }
+            match tx.tx_env.transact_to {
+                TransactTo::Create => classes.creations += 1,
+                TransactTo::Call(_) if tx.tx_env.data.is_empty() => classes.transfers += 1,
+                TransactTo::Call(_) => classes.calls += 1,
+            }
+            if let Some(selector) = tx.tx_env.data.get(..4).filter(|_| !tx.deterministic) {
+                *selectors.entry(FixedBytes::from_slice(selector)).or_insert(0) += 1;
+            }
+        }
+        classes
+    }
+
+    fn add(&mut self, other: &BlockClasses) {
+        self.txs += other.txs;
+        self.deterministic += other.deterministic;
+        self.non_deterministic += other.non_deterministic;
+        self.transfers += other.transfers;
+        self.calls += other.calls;
+        self.creations += other.creations;
+    }
+}
+
+pub fn run(inputs: &RunInputs, options: &RunOptions) -> Result<()> {
+    const MAX_LISTED: usize = 10;
+
+    let blocks = prepared_blocks(inputs, options)?;
+    println!("Classifying transactions...");
+
+    let mut rows = Vec::new();
+    let mut selectors = HashMap::new();
+    let mut failures = Vec::new();
+    for block in blocks {
+        match block {
+            Ok(block) => rows.push(BlockClasses::of(&block, &mut selectors)),
+            Err(e) => failures.push(e),
+        }
+    }
+
+    let mut total = BlockClasses::default();
+    for row in &rows {
+        total.add(row);
+    }
+    let share = |count: usize| count as f64 / total.txs.max(1) as f64 * 100.0;
+
+    println!("Blocks classified:         {}", rows.len());
+    println!("Transactions:              {}", total.txs);
+    println!("  Deterministic:           {} ({:.1}%)", total.deterministic, share(total.deterministic));
+    println!("  Non-deterministic:       {} ({:.1}%)", total.non_deterministic, share(total.non_deterministic));
+    println!("  Value transfers:         {} ({:.1}%)", total.transfers, share(total.transfers));
+    println!("  Contract calls:          {} ({:.1}%)", total.calls, share(total.calls));
+    println!("  Contract creations:      {} ({:.1}%)", total.creations, share(total.creations));
+
+    let mut selectors: Vec<_> = selectors.into_iter().collect();
+    selectors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    if !selectors.is_empty() {
+        println!("  Top non-deterministic selectors:");
+        for (selector, count) in selectors.iter().take(MAX_LISTED) {
+            println!("    {}  {} txs", selector, count);
+        }
+    }
+    if !failures.is_empty() {
+        println!("Failed blocks:             {} (not classified)", failures.len());
+        for error in failures.iter().take(MAX_LISTED) {
+            println!("    {:#}", error);
+        }
+    }
+    println!();
+
+    let path = options.output_path(&format!("{}.{}", CLASSIFICATION_FILE, options.format.extension()));
+    let output = match options.format {
+        ReportFormat::Json => serde_json::to_string_pretty(&rows)?,
+        ReportFormat::Text => {
+            let mut output = String::from("block\ttxs\tdeterministic\tnon_deterministic\ttransfers\tcalls\tcreations\n");
+            for row in &rows {
+                output.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    row.block, row.txs, row.deterministic, row.non_deterministic,
+                    row.transfers, row.calls, row.creations
+                ));
+            }
+            output
+        }
+    };
+    fs::write(&path, output)?;
+    println!("Classification saved to {}", path.display());
+
+    Ok(())
+}
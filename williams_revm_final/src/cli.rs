@@ -0,0 +1,443 @@
+// Command line and run configuration
+//
+//   williams-benchmark [command] [data_dir] [threads] [options]
+//
+// The command defaults to `run`, so `williams-benchmark <data_dir> 16` keeps
+// working. Every execution option can also come from a TOML file given with
+// `--config`; options on the command line override the file. Runs write the
+// parameters they used to `williams_run_config.toml` in the output directory,
+// so `--config` on that file repeats the run exactly.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Result, Context, bail};
+use serde::{Deserialize, Serialize};
+
+use williams_executor::download::DownloadConfig;
+use williams_executor::ingest::ReadAheadConfig;
+
+use crate::selection::{BlockList, BlockSelection};
+
+/// Names accepted by `--executor` and `--baseline`
+pub const EXECUTORS: [&str; 4] = ["williams", "sequential", "naive", "optimistic"];
+
+/// Parameters of a run, written next to its results
+pub const RUN_CONFIG_FILE: &str = "williams_run_config.toml";
+
+/// Thread counts run when none are given
+const DEFAULT_THREADS: [usize; 3] = [4, 8, 16];
+
+/// Blocks between progress checkpoints when `--checkpoint-every` is not given
+const DEFAULT_CHECKPOINT_EVERY: usize = 1000;
+
+pub const USAGE: &str = "\
+Usage: williams-benchmark [command] [data_dir] [threads] [options]
+
+Commands:
+  run          Execute the dataset and measure (default)
+  verify       Check manifest, header chain and transaction integrity only
+  classify     Classify every transaction without executing
+  convert      Decode the dataset once into the binary block cache
+  compare      Execute with --baseline and --executor and compare time and results
+  trace        Execute and record each transaction's outcome and accesses
+  download     Fetch blocks, receipts and prestate traces from a JSON-RPC node
+  manifest     Write the dataset manifest (file hashes, block range, tx counts)
+  evm-bench    Measure EVM construction against reusing one EVM per worker
+  help         Show this message
+
+Options:
+  --config <file>        Read options from a TOML file (command line wins)
+  --data-dir <dir>       Dataset directory or JSONL file (default ./data_bdf)
+  --threads <n,...>      Thread counts to run, e.g. 12 or 4,8,16 (default 4,8,16)
+  --blocks <range>       Blocks to include: N, N..M (inclusive), N.. or ..M
+  --every <n>            Only every Nth block number, counted from the range start
+  --sample <fraction>    Only a random fraction of blocks, e.g. 0.01
+  --seed <n>             Seed of --sample (default 1)
+  --block-list <file>    Only the block numbers listed in a file
+  --executor <name>      williams, sequential, naive or optimistic (default williams)
+  --baseline <name>      Executor `compare` measures against (default sequential)
+  --output-dir <dir>     Where result files are written (default .)
+  --format <text|json>   Format of result files (default text)
+  --verify-senders       Recover every sender and cross-check it against `from`
+  --verify-integrity     Check tx hashes and transactions roots before running
+  --no-header-check      Skip header chain validation
+  --no-cache             Ignore the block cache
+  --readers <n>          Threads loading blocks ahead of execution (default 2)
+  --queue-depth <n>      Blocks loaded ahead of execution, at most (default 16)
+  --checkpoint-every <n> Save run progress every n blocks, 0 to never (default 1000)
+  --resume               Continue an interrupted run from its last checkpoint
+  --no-prestate          Execute against empty state
+  --source <text>        Where the dataset came from (manifest)
+  --iterations <n>       Transactions per variant (evm-bench, default 100000)
+  --rpc <url>            Archive node to download from (download)
+  --start <n>            First block to download (download, default 18000000)
+  --count <n>            Blocks to download (download, default 100000)
+  --workers <n>          Concurrent requests (download, default 20)
+  --retries <n>          Retries per request (download, default 5)
+  --no-receipts          Do not download receipts (download)
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Execute the dataset and measure
+    Run,
+    /// Run the dataset checks and fail if any finds a problem
+    Verify,
+    /// Classify transactions without executing them
+    Classify,
+    /// Decode the dataset once into the binary block cache
+    Convert,
+    /// Execute with two executors and compare time and results
+    Compare,
+    /// Execute and record what every transaction did
+    Trace,
+    /// Fetch blocks, receipts and prestate traces from a JSON-RPC node
+    Download,
+    /// Write the dataset manifest (file hashes, block range, tx counts)
+    Manifest,
+    /// Measure EVM construction against reusing one EVM per worker
+    EvmBench,
+    Help,
+}
+
+impl Command {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word {
+            "run" => Command::Run,
+            "verify" => Command::Verify,
+            "classify" => Command::Classify,
+            "convert" => Command::Convert,
+            "compare" => Command::Compare,
+            "trace" => Command::Trace,
+            "download" => Command::Download,
+            "manifest" => Command::Manifest,
+            "evm-bench" => Command::EvmBench,
+            "help" => Command::Help,
+            _ => return None,
+        })
+    }
+}
+
+/// Format of the files a command writes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Tab-separated tables
+    Text,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ReportFormat::Text => "txt",
+            ReportFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => bail!("expected text or json"),
+        }
+    }
+}
+
+/// Execution options as read from and written to `--config` files
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RunConfig {
+    data_dir: Option<String>,
+    threads: Option<Vec<usize>>,
+    blocks: Option<String>,
+    every: Option<u64>,
+    sample: Option<f64>,
+    seed: Option<u64>,
+    block_list: Option<PathBuf>,
+    executor: Option<String>,
+    baseline: Option<String>,
+    output_dir: Option<PathBuf>,
+    format: Option<ReportFormat>,
+    verify_senders: Option<bool>,
+    verify_integrity: Option<bool>,
+    check_headers: Option<bool>,
+    use_cache: Option<bool>,
+    use_prestate: Option<bool>,
+    readers: Option<usize>,
+    queue_depth: Option<usize>,
+    checkpoint_every: Option<usize>,
+}
+
+pub struct RunOptions {
+    pub command: Command,
+    pub data_dir: String,
+    /// Thread counts to run with, in order
+    pub threads: Vec<usize>,
+    /// Blocks the command works on
+    pub selection: BlockSelection,
+    /// Block executor to run with, one of `EXECUTORS`
+    pub executor: String,
+    /// Executor `compare` measures `executor` against
+    pub baseline: String,
+    pub output_dir: PathBuf,
+    pub format: ReportFormat,
+    /// Recover every sender and cross-check it against `from`
+    pub verify_senders: bool,
+    /// Check tx hashes and transactions roots before executing anything
+    pub verify_integrity: bool,
+    /// Validate the header chain (hashes, linkage, gas, base fee) before running
+    pub check_headers: bool,
+    /// Execute from the block cache when the data directory has one
+    pub use_cache: bool,
+    /// Execute against the dataset's prestate traces when it has them
+    pub use_prestate: bool,
+    /// Reader threads and queue depth of block loading
+    pub read_ahead: ReadAheadConfig,
+    /// Blocks between progress checkpoints of `run`, 0 for none
+    pub checkpoint_every: usize,
+    /// Continue from the checkpoint of an interrupted run
+    pub resume: bool,
+    pub download: DownloadConfig,
+    /// Where the dataset came from, recorded in its manifest
+    pub source: Option<String>,
+    /// Transactions per variant in `evm-bench`
+    pub iterations: usize,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            command: Command::Run,
+            data_dir: "./data_bdf".to_string(),
+            threads: DEFAULT_THREADS.to_vec(),
+            selection: BlockSelection::default(),
+            executor: "williams".to_string(),
+            baseline: "sequential".to_string(),
+            output_dir: PathBuf::from("."),
+            format: ReportFormat::Text,
+            verify_senders: false,
+            verify_integrity: false,
+            check_headers: true,
+            use_cache: true,
+            use_prestate: true,
+            read_ahead: ReadAheadConfig::default(),
+            checkpoint_every: DEFAULT_CHECKPOINT_EVERY,
+            resume: false,
+            download: DownloadConfig::default(),
+            source: None,
+            iterations: 100_000,
+        }
+    }
+}
+
+impl RunOptions {
+    pub fn from_args() -> Result<Self> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+
+        // The config file is the starting point, whatever its position
+        let mut options = Self::default();
+        if let Some(index) = args.iter().position(|arg| arg == "--config") {
+            let path = args.get(index + 1).context("--config needs a value")?;
+            options.apply_config(RunConfig::load(Path::new(path))?)?;
+        }
+
+        let mut positional = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    args.next();
+                }
+                "--help" | "-h" => options.command = Command::Help,
+                "--data-dir" => options.data_dir = option_value(&mut args, &arg)?,
+                "--threads" => options.threads = parse_threads(&option_value::<String>(&mut args, &arg)?)?,
+                "--blocks" => options.selection.range = option_value(&mut args, &arg)?,
+                "--every" => options.selection.every = Some(option_value(&mut args, &arg)?),
+                "--sample" => options.selection.sample = Some(option_value(&mut args, &arg)?),
+                "--seed" => options.selection.seed = option_value(&mut args, &arg)?,
+                "--block-list" => {
+                    let path: PathBuf = option_value(&mut args, &arg)?;
+                    options.selection.list = Some(BlockList::load(&path)?);
+                }
+                "--executor" => options.executor = option_value(&mut args, &arg)?,
+                "--baseline" => options.baseline = option_value(&mut args, &arg)?,
+                "--output-dir" => options.output_dir = option_value(&mut args, &arg)?,
+                "--format" => options.format = option_value(&mut args, &arg)?,
+                "--verify-senders" => options.verify_senders = true,
+                "--verify-integrity" => options.verify_integrity = true,
+                "--no-header-check" => options.check_headers = false,
+                "--no-cache" => options.use_cache = false,
+                "--readers" => options.read_ahead.readers = option_value(&mut args, &arg)?,
+                "--queue-depth" => options.read_ahead.depth = option_value(&mut args, &arg)?,
+                "--checkpoint-every" => options.checkpoint_every = option_value(&mut args, &arg)?,
+                "--resume" => options.resume = true,
+                "--rpc" => options.download.rpc_url = option_value(&mut args, &arg)?,
+                "--start" => options.download.start = option_value(&mut args, &arg)?,
+                "--count" => options.download.count = option_value(&mut args, &arg)?,
+                "--workers" => options.download.workers = option_value(&mut args, &arg)?,
+                "--retries" => options.download.retries = option_value(&mut args, &arg)?,
+                "--no-receipts" => options.download.receipts = false,
+                "--no-prestate" => {
+                    options.download.prestate = false;
+                    options.use_prestate = false;
+                }
+                "--source" => options.source = Some(option_value(&mut args, &arg)?),
+                "--iterations" => options.iterations = option_value(&mut args, &arg)?,
+                flag if flag.starts_with("--") => bail!("Unknown option: {} (see --help)", flag),
+                _ => positional.push(arg),
+            }
+        }
+
+        // A leading word that is no command is the data directory of `run`
+        let mut positional = positional.into_iter().peekable();
+        if let Some(command) = positional.peek().and_then(|word| Command::parse(word)) {
+            positional.next();
+            if options.command != Command::Help {
+                options.command = command;
+            }
+        }
+        if let Some(data_dir) = positional.next() {
+            options.data_dir = data_dir;
+        }
+        if let Some(threads) = positional.next() {
+            options.threads = parse_threads(&threads)?;
+        }
+        if let Some(extra) = positional.next() {
+            bail!("Unexpected argument: {} (see --help)", extra);
+        }
+
+        options.selection.check()?;
+        if options.read_ahead.readers == 0 || options.read_ahead.depth == 0 {
+            bail!("--readers and --queue-depth must be positive");
+        }
+        if options.resume && options.checkpoint_every == 0 {
+            bail!("--resume needs checkpoints; drop --checkpoint-every 0");
+        }
+        if options.resume && options.command != Command::Run {
+            bail!("--resume only applies to run");
+        }
+        for name in [&options.executor, &options.baseline] {
+            if !EXECUTORS.contains(&name.as_str()) {
+                bail!("Unknown executor {} (expected one of: {})", name, EXECUTORS.join(", "));
+            }
+        }
+        Ok(options)
+    }
+
+    fn apply_config(&mut self, config: RunConfig) -> Result<()> {
+        if let Some(data_dir) = config.data_dir {
+            self.data_dir = data_dir;
+        }
+        if let Some(threads) = config.threads {
+            self.threads = check_threads(threads)?;
+        }
+        if let Some(blocks) = config.blocks {
+            self.selection.range = blocks.parse()?;
+        }
+        if let Some(path) = config.block_list {
+            self.selection.list = Some(BlockList::load(&path)?);
+        }
+        self.selection.every = config.every.or(self.selection.every);
+        self.selection.sample = config.sample.or(self.selection.sample);
+        self.selection.seed = config.seed.unwrap_or(self.selection.seed);
+        if let Some(executor) = config.executor {
+            self.executor = executor;
+        }
+        if let Some(baseline) = config.baseline {
+            self.baseline = baseline;
+        }
+        if let Some(output_dir) = config.output_dir {
+            self.output_dir = output_dir;
+        }
+        self.format = config.format.unwrap_or(self.format);
+        self.verify_senders = config.verify_senders.unwrap_or(self.verify_senders);
+        self.verify_integrity = config.verify_integrity.unwrap_or(self.verify_integrity);
+        self.check_headers = config.check_headers.unwrap_or(self.check_headers);
+        self.use_cache = config.use_cache.unwrap_or(self.use_cache);
+        self.use_prestate = config.use_prestate.unwrap_or(self.use_prestate);
+        self.read_ahead.readers = config.readers.unwrap_or(self.read_ahead.readers);
+        self.read_ahead.depth = config.queue_depth.unwrap_or(self.read_ahead.depth);
+        self.checkpoint_every = config.checkpoint_every.unwrap_or(self.checkpoint_every);
+        Ok(())
+    }
+
+    /// Thread budget of commands that run once rather than per thread count
+    pub fn max_threads(&self) -> usize {
+        self.threads.iter().copied().max().unwrap_or(1)
+    }
+
+    /// Path of a result file inside the output directory
+    pub fn output_path(&self, file: &str) -> PathBuf {
+        self.output_dir.join(file)
+    }
+
+    /// Write the options this run uses as a `--config` file
+    pub fn write_config(&self) -> Result<PathBuf> {
+        let config = RunConfig {
+            data_dir: Some(self.data_dir.clone()),
+            threads: Some(self.threads.clone()),
+            blocks: (!self.selection.range.is_all()).then(|| self.selection.range.to_string()),
+            every: self.selection.every,
+            sample: self.selection.sample,
+            seed: self.selection.sample.map(|_| self.selection.seed),
+            block_list: self.selection.list.as_ref().map(|list| list.path.clone()),
+            executor: Some(self.executor.clone()),
+            baseline: Some(self.baseline.clone()),
+            output_dir: Some(self.output_dir.clone()),
+            format: Some(self.format),
+            verify_senders: Some(self.verify_senders),
+            verify_integrity: Some(self.verify_integrity),
+            check_headers: Some(self.check_headers),
+            use_cache: Some(self.use_cache),
+            use_prestate: Some(self.use_prestate),
+            readers: Some(self.read_ahead.readers),
+            queue_depth: Some(self.read_ahead.depth),
+            checkpoint_every: Some(self.checkpoint_every),
+        };
+        let path = self.output_path(RUN_CONFIG_FILE);
+        fs::write(&path, toml::to_string_pretty(&config)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+impl RunConfig {
+    fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Invalid TOML config {}", path.display()))
+    }
+}
+
+/// Parse the value following an option
+fn option_value<T: FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<T>
+where
+    T::Err: fmt::Display,
+{
+    let value = args.next().with_context(|| format!("{} needs a value", flag))?;
+    value.parse().map_err(|e| anyhow::anyhow!("Invalid value for {}: {} ({})", flag, value, e))
+}
+
+/// A thread count or a comma-separated list of them
+fn parse_threads(list: &str) -> Result<Vec<usize>> {
+    let threads = list.split(',')
+        .map(|count| count.trim().parse().with_context(|| format!("Invalid thread count: {}", count)))
+        .collect::<Result<Vec<usize>>>()?;
+    check_threads(threads)
+}
+
+fn check_threads(threads: Vec<usize>) -> Result<Vec<usize>> {
+    if threads.is_empty() || threads.contains(&0) {
+        bail!("Thread counts must be positive integers");
+    }
+    Ok(threads)
+}
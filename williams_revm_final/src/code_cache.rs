@@ -0,0 +1,106 @@
+// Shared bytecode analysis cache
+//
+// Before running legacy bytecode revm pads it and builds its jump destination
+// table. Code read from a state backend arrives raw, so without help every
+// transaction into a contract repeats that analysis in its own EVM. The
+// `CodeCache` keeps analyzed bytecode keyed by code hash for the whole run:
+// the state layers hand revm code that is already analyzed, and a contract is
+// analyzed the first time any worker in any block loads it, and never again.
+//
+// The map is split into shards, each behind its own lock, so workers loading
+// different contracts do not wait on each other. A miss analyzes under the
+// shard's write lock, so concurrent loads of the same new contract still
+// analyze it once.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+use revm::interpreter::analysis::to_analysed;
+use revm::primitives::{Bytecode, B256};
+
+/// Independently locked parts of the cache
+const SHARD_COUNT: usize = 16;
+
+/// Analyzed bytecode by code hash, shared by all workers and blocks
+pub struct CodeCache {
+    shards: Vec<RwLock<HashMap<B256, Bytecode>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    bytes: AtomicUsize,
+}
+
+/// Cache activity over a run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CodeCacheStats {
+    /// Loads served with code analyzed earlier
+    pub hits: u64,
+    /// Loads that analyzed the code, one per distinct contract
+    pub misses: u64,
+    /// Padded bytecode plus jump tables
+    pub bytes: usize,
+}
+
+impl CodeCacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64 * 100.0
+    }
+}
+
+impl CodeCache {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..SHARD_COUNT).map(|_| RwLock::new(HashMap::new())).collect(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// The analyzed form of `code`, analyzing it only if no worker has yet
+    pub fn analyzed(&self, code_hash: B256, code: Bytecode) -> Bytecode {
+        if code.is_empty() {
+            return code;
+        }
+        let shard = &self.shards[code_hash[0] as usize % SHARD_COUNT];
+
+        if let Some(cached) = shard.read().unwrap_or_else(|e| e.into_inner()).get(&code_hash) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return cached.clone();
+        }
+
+        let mut shard = shard.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = shard.get(&code_hash) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return cached.clone();
+        }
+        let analyzed = to_analysed(code);
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(footprint(&analyzed), Ordering::Relaxed);
+        shard.insert(code_hash, analyzed.clone());
+        analyzed
+    }
+
+    pub fn stats(&self) -> CodeCacheStats {
+        CodeCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for CodeCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Heap bytes held for one analyzed contract
+fn footprint(code: &Bytecode) -> usize {
+    code.bytes_slice().len() + code.legacy_jump_table().map_or(0, |table| table.as_slice().len())
+}
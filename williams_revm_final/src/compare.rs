@@ -0,0 +1,148 @@
+// Executor comparison
+//
+// `compare` executes every selected block twice, with the baseline executor
+// (`--baseline`, sequential by default) and with the executor under test
+// (`--executor`), each on its own copy of the block's prestate. It reports
+// both times per thread count, and whether both executors produced the same
+// result for every transaction: status, gas used, output and logs. A faster
+// executor that changes results is wrong rather than fast, so every
+// mismatching transaction is counted and the first ones are listed.
+
+use std::fs;
+
+use anyhow::{Result, Context};
+use serde::Serialize;
+
+use williams_executor::executor::BlockExecution;
+use williams_executor::schedule::GAS_PROFILE_FILE;
+
+use crate::cli::{ReportFormat, RunOptions};
+use crate::trace::outcome_status;
+use crate::{build_executor, execute_on_prestate, prepared_blocks, RunInputs};
+
+/// Per-block comparison, written to `williams_comparison.*`
+const COMPARISON_FILE: &str = "williams_comparison";
+
+#[derive(Debug, Serialize)]
+struct BlockComparison {
+    threads: usize,
+    block: u64,
+    txs: usize,
+    baseline_us: u128,
+    executor_us: u128,
+    /// Transactions whose results differ between the executors
+    mismatches: usize,
+}
+
+/// A transaction the two executors disagree on
+struct Mismatch {
+    block: u64,
+    index: usize,
+    baseline: (&'static str, u64),
+    executor: (&'static str, u64),
+}
+
+pub fn run(inputs: &RunInputs, options: &RunOptions) -> Result<()> {
+    let mut rows = Vec::new();
+    for &thread_count in &options.threads {
+        rows.extend(compare_with_threads(inputs, options, thread_count)?);
+    }
+
+    let path = options.output_path(&format!("{}.{}", COMPARISON_FILE, options.format.extension()));
+    let output = match options.format {
+        ReportFormat::Json => serde_json::to_string_pretty(&rows)?,
+        ReportFormat::Text => {
+            let mut output = format!("threads\tblock\ttxs\t{}_us\t{}_us\tmismatches\n", options.baseline, options.executor);
+            for row in &rows {
+                output.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\n",
+                    row.threads, row.block, row.txs, row.baseline_us, row.executor_us, row.mismatches
+                ));
+            }
+            output
+        }
+    };
+    fs::write(&path, output)?;
+    println!("Comparison saved to {}", path.display());
+
+    let mismatches: usize = rows.iter().map(|row| row.mismatches).sum();
+    if mismatches > 0 {
+        println!("✗ {} transactions differ between {} and {}", mismatches, options.baseline, options.executor);
+    } else {
+        println!("✓ {} matches {} on every transaction", options.executor, options.baseline);
+    }
+    Ok(())
+}
+
+fn compare_with_threads(inputs: &RunInputs, options: &RunOptions, thread_count: usize) -> Result<Vec<BlockComparison>> {
+    const MAX_LISTED: usize = 10;
+
+    let blocks = prepared_blocks(inputs, options)?;
+    let profile_path = options.output_path(GAS_PROFILE_FILE);
+    let mut baseline = build_executor(&options.baseline, inputs.chain, thread_count, &profile_path)?;
+    let mut executor = build_executor(&options.executor, inputs.chain, thread_count, &profile_path)?;
+    println!();
+    println!("Comparing {} against {} with {} threads...", executor.name(), baseline.name(), thread_count);
+
+    let mut rows = Vec::new();
+    let mut mismatches = Vec::new();
+    for block in blocks {
+        let block = block?;
+        let (expected, _) = execute_on_prestate(&block, inputs.state, &mut baseline)
+            .with_context(|| format!("Block {} ({})", block.number, baseline.name()))?;
+        let (actual, _) = execute_on_prestate(&block, inputs.state, &mut executor)
+            .with_context(|| format!("Block {} ({})", block.number, executor.name()))?;
+
+        let differing = differing_outcomes(block.number, &expected, &actual);
+        rows.push(BlockComparison {
+            threads: thread_count,
+            block: block.number,
+            txs: block.txs.len(),
+            baseline_us: expected.timings.total_us(),
+            executor_us: actual.timings.total_us(),
+            mismatches: differing.len(),
+        });
+        mismatches.extend(differing);
+    }
+
+    let baseline_us: u128 = rows.iter().map(|row| row.baseline_us).sum();
+    let executor_us: u128 = rows.iter().map(|row| row.executor_us).sum();
+    let txs: usize = rows.iter().map(|row| row.txs).sum();
+    println!("  Blocks compared:         {} ({} txs)", rows.len(), txs);
+    println!("  {:<25}{:.2}ms", format!("{}:", baseline.name()), baseline_us as f64 / 1000.0);
+    println!("  {:<25}{:.2}ms", format!("{}:", executor.name()), executor_us as f64 / 1000.0);
+    println!("  Speedup:                 {:.2}x", baseline_us as f64 / executor_us.max(1) as f64);
+    println!("  Differing txs:           {} in {} blocks", mismatches.len(),
+        rows.iter().filter(|row| row.mismatches > 0).count()
+    );
+    for mismatch in mismatches.iter().take(MAX_LISTED) {
+        println!("    block {} tx {}: {} {} gas with {}, {} {} gas with {}",
+            mismatch.block, mismatch.index,
+            mismatch.baseline.0, mismatch.baseline.1, baseline.name(),
+            mismatch.executor.0, mismatch.executor.1, executor.name()
+        );
+    }
+    println!();
+
+    if let Some(profile) = executor.gas_profile() {
+        profile.save(&profile_path)?;
+    }
+    Ok(rows)
+}
+
+/// Transactions whose result differs, ignoring state changes, which differ
+/// legitimately in what was loaded
+fn differing_outcomes(block: u64, expected: &BlockExecution, actual: &BlockExecution) -> Vec<Mismatch> {
+    expected.outcomes.iter()
+        .zip(&actual.outcomes)
+        .enumerate()
+        .filter(|(_, (expected, actual))| {
+            expected.as_ref().map(|outcome| &outcome.result) != actual.as_ref().map(|outcome| &outcome.result)
+        })
+        .map(|(index, (expected, actual))| Mismatch {
+            block,
+            index,
+            baseline: outcome_status(expected.as_ref()),
+            executor: outcome_status(actual.as_ref()),
+        })
+        .collect()
+}
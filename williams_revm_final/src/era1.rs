@@ -0,0 +1,266 @@
+// Era1 archive reader
+//
+// An Era1 file is an e2store container holding up to 8192 consecutive
+// pre-merge blocks. Each record is `type (u16 LE) | length (u32 LE) |
+// reserved (2 zero bytes) | data`. A file is laid out as
+//
+//   Version | (Header | Body | Receipts | TotalDifficulty)* | Accumulator | BlockIndex
+//
+// Header, body and receipts are snappy-framed RLP; the total difficulty is
+// a little-endian u256. The receipts are decoded and attached to the block
+// as `receipts`, each linked to its transaction by `transactionHash`. The trailing BlockIndex gives the starting block
+// number and, per block, the offset of its Header record relative to the
+// start of the index record, so any block can be read without a scan.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use anyhow::{Result, Context, bail};
+use serde_json::json;
+use serde_json::Value;
+
+use revm::primitives::U256;
+
+use crate::raw_block::{decode_block, decode_receipts, Rlp};
+use crate::tx_rlp::RlpList;
+
+const TYPE_VERSION: u16 = 0x3265;
+const TYPE_COMPRESSED_HEADER: u16 = 0x03;
+const TYPE_COMPRESSED_BODY: u16 = 0x04;
+const TYPE_COMPRESSED_RECEIPTS: u16 = 0x05;
+const TYPE_TOTAL_DIFFICULTY: u16 = 0x06;
+const TYPE_BLOCK_INDEX: u16 = 0x3266;
+
+/// Size of an e2store record header
+const RECORD_HEADER_SIZE: u64 = 8;
+
+/// Blocks of one Era1 file: first block number and each block's file offset
+pub struct Era1Index {
+    pub start: u64,
+    pub offsets: Vec<u64>,
+}
+
+/// Read the block index at the end of an Era1 file
+pub fn read_index(path: &Path) -> Result<Era1Index> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let (ty, _) = read_record_header(&mut file)?;
+    if ty != TYPE_VERSION {
+        bail!("Not an Era1 file (first record type {:#06x})", ty);
+    }
+
+    // Index record: header | start | offset * count | count
+    let count = read_u64_at(&mut file, len.checked_sub(8).context("Era1 file too short")?)?;
+    let index_start = count.checked_mul(8)
+        .and_then(|offsets| len.checked_sub(RECORD_HEADER_SIZE + 16 + offsets))
+        .context("Era1 block index out of bounds")?;
+
+    file.seek(SeekFrom::Start(index_start))?;
+    let (ty, _) = read_record_header(&mut file)?;
+    if ty != TYPE_BLOCK_INDEX {
+        bail!("Era1 file does not end with a block index");
+    }
+    let start = read_u64_at(&mut file, index_start + RECORD_HEADER_SIZE)?;
+
+    let mut raw = vec![0u8; count as usize * 8];
+    file.read_exact(&mut raw)?;
+    let offsets = raw.chunks_exact(8)
+        .map(|chunk| {
+            let relative = i64::from_le_bytes(chunk.try_into().expect("8-byte chunk"));
+            index_start.checked_add_signed(relative).context("Era1 block offset out of bounds")
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Era1Index { start, offsets })
+}
+
+/// Read the block whose Header record starts at `offset`
+///
+/// The receipts must match the transaction count; they are attached as
+/// `receipts` and the total difficulty as `totalDifficulty`, as JSON-RPC does.
+pub fn read_block(path: &Path, offset: u64) -> Result<Value> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let header = read_compressed(&mut file, TYPE_COMPRESSED_HEADER)?;
+    let body = read_compressed(&mut file, TYPE_COMPRESSED_BODY)?;
+    let receipts = read_compressed(&mut file, TYPE_COMPRESSED_RECEIPTS)?;
+    let total_difficulty = read_record(&mut file, TYPE_TOTAL_DIFFICULTY)?;
+
+    // Body is [transactions, ommers(, withdrawals)]; the block is the header
+    // followed by the same items
+    let mut block = RlpList::default();
+    block.push_raw(&header);
+    for item in Rlp::decode(&body).context("Invalid Era1 body")?.items()? {
+        block.push_raw(item.raw);
+    }
+    let mut block = decode_block(&block.finish())?;
+
+    let mut receipts = decode_receipts(&receipts).context("Invalid Era1 receipts")?;
+    let txs = block["transactions"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if receipts.len() != txs.len() {
+        bail!("Era1 entry has {} receipts for {} transactions", receipts.len(), txs.len());
+    }
+    for (receipt, tx) in receipts.iter_mut().zip(txs) {
+        receipt["transactionHash"] = tx["hash"].clone();
+    }
+    block["receipts"] = Value::Array(receipts);
+
+    let total_difficulty = U256::try_from_le_slice(&total_difficulty)
+        .context("Invalid Era1 total difficulty")?;
+    block["totalDifficulty"] = json!(format!("{:#x}", total_difficulty));
+
+    Ok(block)
+}
+
+fn read_record_header(file: &mut File) -> Result<(u16, u32)> {
+    let mut header = [0u8; RECORD_HEADER_SIZE as usize];
+    file.read_exact(&mut header).context("Truncated e2store record")?;
+    if header[6..] != [0, 0] {
+        bail!("Invalid e2store record header");
+    }
+    let ty = u16::from_le_bytes([header[0], header[1]]);
+    let len = u32::from_le_bytes([header[2], header[3], header[4], header[5]]);
+    Ok((ty, len))
+}
+
+fn read_record(file: &mut File, expected: u16) -> Result<Vec<u8>> {
+    let (ty, len) = read_record_header(file)?;
+    if ty != expected {
+        bail!("Expected e2store record {:#06x}, found {:#06x}", expected, ty);
+    }
+    let mut data = vec![0u8; len as usize];
+    file.read_exact(&mut data).context("Truncated e2store record")?;
+    Ok(data)
+}
+
+fn read_compressed(file: &mut File, expected: u16) -> Result<Vec<u8>> {
+    let compressed = read_record(file, expected)?;
+    let mut data = Vec::new();
+    snap::read::FrameDecoder::new(compressed.as_slice())
+        .read_to_end(&mut data)
+        .context("Invalid snappy data in Era1 record")?;
+    Ok(data)
+}
+
+fn read_u64_at(file: &mut File, offset: u64) -> Result<u64> {
+    let mut raw = [0u8; 8];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut raw)?;
+    Ok(u64::from_le_bytes(raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::io::Write;
+
+    use crate::raw_block::tests::{genesis_header, list_item, receipt, GENESIS_HASH};
+    use crate::tx_rlp::encode_signed;
+    use crate::tx_rlp::tests::first_mainnet_transaction;
+
+    const TYPE_ACCUMULATOR: u16 = 0x07;
+
+    fn record(out: &mut Vec<u8>, ty: u16, data: &[u8]) {
+        out.extend_from_slice(&ty.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(data);
+    }
+
+    fn compressed(data: &[u8]) -> Vec<u8> {
+        let mut encoder = snap::write::FrameEncoder::new(Vec::new());
+        encoder.write_all(data).unwrap();
+        encoder.into_inner().unwrap()
+    }
+
+    /// An Era1 file holding the genesis header with `txs`, and `receipts` as its encoded receipt list
+    fn genesis_era1(txs: &[Vec<u8>], receipts: &[u8]) -> Vec<u8> {
+        let mut file = Vec::new();
+        record(&mut file, TYPE_VERSION, &[]);
+
+        let entry = file.len();
+        let mut tx_list = RlpList::default();
+        for tx in txs {
+            tx_list.push_raw(&list_item(tx));
+        }
+        let mut body = RlpList::default();
+        body.push_raw(&tx_list.finish());
+        body.push_raw(&RlpList::default().finish());
+        record(&mut file, TYPE_COMPRESSED_HEADER, &compressed(&genesis_header()));
+        record(&mut file, TYPE_COMPRESSED_BODY, &compressed(&body.finish()));
+        record(&mut file, TYPE_COMPRESSED_RECEIPTS, &compressed(receipts));
+        record(&mut file, TYPE_TOTAL_DIFFICULTY, &U256::from(0x4_0000_0000u64).to_le_bytes::<32>());
+        record(&mut file, TYPE_ACCUMULATOR, &[0u8; 32]);
+
+        let index = file.len();
+        let mut data = Vec::new();
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&(entry as i64 - index as i64).to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        record(&mut file, TYPE_BLOCK_INDEX, &data);
+        file
+    }
+
+    fn write(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("williams-era1-{}-{}.era1", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn genesis_entry_is_indexed_and_decoded() {
+        let path = write("genesis", &genesis_era1(&[], &RlpList::default().finish()));
+        let index = read_index(&path).unwrap();
+        assert_eq!(index.start, 0);
+        assert_eq!(index.offsets, vec![8]);
+
+        let block = read_block(&path, index.offsets[0]).unwrap();
+        assert_eq!(block["hash"], json!(GENESIS_HASH));
+        assert_eq!(block["totalDifficulty"], json!("0x400000000"));
+        assert_eq!(block["transactions"], json!([]));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn receipts_are_attached_to_their_transactions() {
+        let tx = encode_signed(&first_mainnet_transaction()).unwrap();
+        let mut receipts = RlpList::default();
+        receipts.push_raw(&receipt(0, &[0x55; 32], 21_000, &[]));
+        let path = write("attached", &genesis_era1(&[tx], &receipts.finish()));
+
+        let block = read_block(&path, read_index(&path).unwrap().offsets[0]).unwrap();
+        let receipts = block["receipts"].as_array().unwrap();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0]["transactionHash"], block["transactions"][0]["hash"]);
+        assert_eq!(
+            receipts[0]["transactionHash"],
+            json!("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060")
+        );
+        assert_eq!(receipts[0]["gasUsed"], json!("0x5208"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn receipts_must_match_the_transactions() {
+        let mut receipts = RlpList::default();
+        receipts.push_raw(&receipt(0, &[1], 21_000, &[]));
+        let path = write("receipts", &genesis_era1(&[], &receipts.finish()));
+        let error = read_block(&path, read_index(&path).unwrap().offsets[0]).unwrap_err();
+        assert!(error.to_string().contains("1 receipts for 0 transactions"), "{}", error);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let mut file = genesis_era1(&[], &RlpList::default().finish());
+        file[0] = 0x66;
+        let path = write("version", &file);
+        assert!(read_index(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+// EVM construction microbenchmark
+//
+// `evm-bench` measures what building an EVM for every transaction costs
+// compared with reusing one EVM per worker, on the same plain value transfer
+// executed over the same copy-on-write layers the executor uses:
+//
+// - build per tx: configuration, builder, precompiles and block env clone
+// - reused:       swap in the transaction and a fresh database layer only
+// - build only:   construction without executing, the cost being saved
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+
+use revm::db::{CacheDB, EmptyDB};
+use revm::primitives::{AccountInfo, Address, BlockEnv, TransactTo, TxEnv, U256};
+
+use williams_executor::chain_config::ChainConfig;
+use williams_executor::executor::{build_block_evm, execute_transaction};
+use williams_executor::overlay::OverlayDB;
+
+/// Untimed runs of each variant before measuring
+const WARMUP_ITERATIONS: usize = 1_000;
+
+pub fn run(chain: &ChainConfig, iterations: usize) -> Result<()> {
+    let sender = Address::with_last_byte(0x01);
+    let block_env = BlockEnv {
+        number: U256::from(20_300_000),
+        timestamp: U256::from(1_720_000_000),
+        gas_limit: U256::from(30_000_000),
+        basefee: U256::from(10_000_000_000u64),
+        coinbase: Address::with_last_byte(0xcb),
+        ..Default::default()
+    };
+    let spec_id = chain.spec_for_block(&block_env);
+    let tx_env = TxEnv {
+        caller: sender,
+        transact_to: TransactTo::Call(Address::with_last_byte(0x02)),
+        value: U256::from(1),
+        gas_limit: 21_000,
+        gas_price: block_env.basefee,
+        nonce: None,
+        ..Default::default()
+    };
+
+    let mut state = CacheDB::new(EmptyDB::default());
+    state.insert_account_info(sender, AccountInfo::from_balance(U256::from(u128::MAX)));
+    let base = Arc::new(state);
+
+    println!("EVM construction microbenchmark");
+    println!("  Chain / spec:            {} / {:?}", chain.name, spec_id);
+    println!("  Transactions:            {} value transfers per variant", iterations);
+    println!();
+
+    let build_per_tx = |count: usize| -> Result<()> {
+        for _ in 0..count {
+            let mut evm = build_block_evm(OverlayDB::new(base.clone()), &block_env, spec_id, chain);
+            execute_transaction(&mut evm, &tx_env).context("Benchmark transfer failed")?;
+        }
+        Ok(())
+    };
+    let reused = |count: usize| -> Result<()> {
+        let mut evm = build_block_evm(OverlayDB::new(base.clone()), &block_env, spec_id, chain);
+        for _ in 0..count {
+            *evm.db_mut() = OverlayDB::new(base.clone());
+            execute_transaction(&mut evm, &tx_env).context("Benchmark transfer failed")?;
+        }
+        Ok(())
+    };
+    let build_only = |count: usize| -> Result<()> {
+        for _ in 0..count {
+            let evm = build_block_evm(OverlayDB::new(base.clone()), &block_env, spec_id, chain);
+            std::hint::black_box(evm);
+        }
+        Ok(())
+    };
+
+    let build_per_tx_ns = measure(build_per_tx, iterations)?;
+    let reused_ns = measure(reused, iterations)?;
+    let build_only_ns = measure(build_only, iterations)?;
+    let saving_ns = build_per_tx_ns - reused_ns;
+
+    println!("  Build EVM per tx:        {:.0} ns/tx", build_per_tx_ns);
+    println!("  Reuse EVM per worker:    {:.0} ns/tx", reused_ns);
+    println!("  Construction alone:      {:.0} ns/build", build_only_ns);
+    println!("  Saving:                  {:.0} ns/tx ({:.1}%)",
+        saving_ns, saving_ns / build_per_tx_ns * 100.0
+    );
+    Ok(())
+}
+
+/// Average nanoseconds per iteration, after a warm-up
+fn measure(variant: impl Fn(usize) -> Result<()>, iterations: usize) -> Result<f64> {
+    variant(WARMUP_ITERATIONS)?;
+    let start = Instant::now();
+    variant(iterations)?;
+    Ok(start.elapsed().as_nanos() as f64 / iterations.max(1) as f64)
+}
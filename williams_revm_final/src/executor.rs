@@ -0,0 +1,612 @@
+// Block executors
+//
+// A `BlockExecutor` runs one `PreparedBlock` on top of a state database the
+// caller supplies (the state before the block) and reports what each
+// transaction did and how long each stage took. All executors share the
+// stages around the transactions:
+//
+// 1. pre-block system calls (EIP-4788 beacon root, EIP-2935 block hash)
+// 2. the block's transactions, where the strategies differ
+// 3. withdrawals and block rewards, then post-block system calls (EIP-7002,
+//    EIP-7251)
+//
+// Strategies:
+//
+// - `SequentialExecutor`: one EVM, transactions in block order, each seeing
+//   the ones before it
+// - `NaiveParallelExecutor`: every transaction in parallel against the
+//   pre-block state, split evenly by count
+// - `WilliamsExecutor`: the hybrid: parallel prefetch, deterministic and
+//   non-deterministic classes, gas-weighted scheduling and a per-block choice
+//   between sequential and parallel execution
+// - `OptimisticExecutor`: every transaction in parallel, then validated in
+//   block order; one that read what an earlier transaction wrote is executed
+//   again on the committed state, so results match sequential execution
+//
+// The naive and Williams strategies execute each transaction on a throwaway
+// layer over the pre-block state; their state changes are then committed in
+// block order into one layer, so finalization and the post-block system
+// calls see the state after every transaction, whatever the strategy.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use revm::primitives::{Address, BlockEnv, EvmState, ResultAndState, SpecId, TxEnv};
+use revm::{Database, DatabaseCommit, DatabaseRef, Evm};
+
+use crate::access::{AccessSet, Location};
+use crate::block_cache::{PreparedBlock, PreparedTx};
+use crate::chain_config::ChainConfig;
+use crate::code_cache::{CodeCache, CodeCacheStats};
+use crate::finalize::{finalize_block, FinalizeSummary};
+use crate::overlay::{OverlayDB, StateChanges};
+use crate::policy::{observed_conflicts, Decision, ParallelismPolicy, Strategy};
+use crate::prefetch::{AccessPlan, PrefetchSummary, SharedStateCache};
+use crate::schedule::{GasProfile, Schedule, ScheduleSummary};
+use crate::system_calls::{apply_post_block_system_calls, apply_pre_block_system_calls, SystemCallSummary};
+
+/// Executes blocks on top of state databases of type `DB`
+pub trait BlockExecutor<DB> {
+    /// Short name used in reports
+    fn name(&self) -> &'static str;
+
+    /// Execute `block` on top of `db`, the state before the block; `plan`
+    /// names what the block is known to touch, for executors that prefetch
+    fn execute_with_plan(&mut self, block: &PreparedBlock, db: DB, plan: &AccessPlan) -> Result<BlockExecution>;
+
+    /// Execute `block` on top of `db`, predicting what it touches from its
+    /// transactions
+    fn execute(&mut self, block: &PreparedBlock, db: DB) -> Result<BlockExecution> {
+        self.execute_with_plan(block, db, &AccessPlan::for_block(block))
+    }
+
+    /// Bytecode analysis cache activity so far, for executors that keep one
+    fn code_cache_stats(&self) -> Option<CodeCacheStats> {
+        None
+    }
+
+    /// Gas measured per contract and selector, for executors that learn it
+    fn gas_profile(&self) -> Option<&GasProfile> {
+        None
+    }
+
+    /// Take in the conflict rates blocks had before a restart, oldest first,
+    /// for executors that learn from them
+    fn replay_conflicts(&mut self, _conflict_rates: &[f64]) {}
+}
+
+/// What executing a block did, and how long each stage took
+#[derive(Debug, Default)]
+pub struct BlockExecution {
+    /// Result and state changes per transaction in block order, `None` for
+    /// invalid transactions
+    pub outcomes: Vec<Option<ResultAndState>>,
+    pub timings: StageTimings,
+    pub prefetch: PrefetchSummary,
+    pub schedule: ScheduleSummary,
+    /// Strategy chosen for the block, by executors that choose one
+    pub decision: Option<Decision>,
+    /// Fraction of executed transactions that read what an earlier one wrote
+    pub conflict_rate: f64,
+    /// Transactions executed again after failing validation
+    pub reexecuted: usize,
+    pub finalize: FinalizeSummary,
+    pub system_calls: SystemCallSummary,
+    /// State after the block relative to the state before it: system calls,
+    /// transactions, withdrawals and rewards, to check against the header's
+    /// state root
+    pub state: StateChanges,
+}
+
+/// Microseconds per stage; the sum of the first four is the block's
+/// execution time
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct StageTimings {
+    pub prefetch_us: u128,
+    pub transactions_us: u128,
+    pub finalize_us: u128,
+    pub system_calls_us: u128,
+    /// Part of `transactions_us`: committing transactions' state changes into
+    /// the block's state in block order
+    pub commit_us: u128,
+    /// Building thread pools on first use, outside the execution time
+    pub pool_us: u128,
+}
+
+impl StageTimings {
+    pub fn total_us(&self) -> u128 {
+        self.prefetch_us + self.transactions_us + self.finalize_us + self.system_calls_us
+    }
+}
+
+/// One EVM, transactions in block order, each committed before the next
+pub struct SequentialExecutor {
+    chain: ChainConfig,
+}
+
+impl SequentialExecutor {
+    pub fn new(chain: ChainConfig) -> Self {
+        Self { chain }
+    }
+}
+
+impl<DB> BlockExecutor<DB> for SequentialExecutor
+where
+    DB: DatabaseRef + Send + Sync,
+    DB::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "sequential"
+    }
+
+    fn execute_with_plan(&mut self, block: &PreparedBlock, db: DB, _plan: &AccessPlan) -> Result<BlockExecution> {
+        let chain = &self.chain;
+        execute_stages(block, chain, db, |base| {
+            let txs: Vec<&PreparedTx> = block.txs.iter().collect();
+            Ok(execute_sequential(&txs, base, &block.block_env, block.spec_id, chain))
+        })
+    }
+}
+
+/// Every transaction in parallel against the pre-block state, split by count
+pub struct NaiveParallelExecutor {
+    chain: ChainConfig,
+    pool: ThreadPool,
+}
+
+impl NaiveParallelExecutor {
+    pub fn new(chain: ChainConfig, thread_count: usize) -> Result<Self> {
+        Ok(Self { chain, pool: build_pool(thread_count)? })
+    }
+}
+
+impl<DB> BlockExecutor<DB> for NaiveParallelExecutor
+where
+    DB: DatabaseRef + Send + Sync,
+    DB::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "naive"
+    }
+
+    fn execute_with_plan(&mut self, block: &PreparedBlock, db: DB, _plan: &AccessPlan) -> Result<BlockExecution> {
+        let (chain, pool) = (&self.chain, &self.pool);
+        execute_stages(block, chain, db, |base| {
+            let outcomes = execute_isolated(&block.txs, pool, base, &block.block_env, block.spec_id, chain);
+            Ok(merge_isolated(outcomes, base, block.block_env.coinbase))
+        })
+    }
+}
+
+/// The Williams hybrid strategy
+///
+/// The code cache, gas profile and policy carry over from block to block.
+pub struct WilliamsExecutor {
+    chain: ChainConfig,
+    thread_count: usize,
+    code_cache: CodeCache,
+    profile: GasProfile,
+    policy: ParallelismPolicy,
+    pools: Pools,
+}
+
+impl WilliamsExecutor {
+    pub fn new(chain: ChainConfig, thread_count: usize) -> Self {
+        Self {
+            chain,
+            thread_count,
+            code_cache: CodeCache::new(),
+            profile: GasProfile::default(),
+            policy: ParallelismPolicy::new(thread_count),
+            pools: Pools::default(),
+        }
+    }
+
+    /// Start from gas measured by earlier runs
+    pub fn with_gas_profile(mut self, profile: GasProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+}
+
+impl<DB> BlockExecutor<DB> for WilliamsExecutor
+where
+    DB: DatabaseRef + Send + Sync,
+    DB::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "williams"
+    }
+
+    fn execute_with_plan(&mut self, block: &PreparedBlock, db: DB, plan: &AccessPlan) -> Result<BlockExecution> {
+        let block_env = &block.block_env;
+        let spec_id = block.spec_id;
+        let chain = &self.chain;
+
+        // OPTIMIZATION 1: Prefetch the block's accounts, code and predicted slots
+        // in parallel into one immutable layer that every worker reads through
+        let pool_start = Instant::now();
+        let prefetch_pool = self.pools.get(self.thread_count)?;
+        let mut pool_us = pool_start.elapsed().as_micros();
+        let prefetch_start = Instant::now();
+        let shared_state = prefetch_pool.install(|| SharedStateCache::prefetch(db, plan, &self.code_cache))?;
+        let prefetch = shared_state.summary(prefetch_start.elapsed().as_micros());
+
+        // Pick sequential, limited or full parallel execution for this block
+        let decision = self.policy.choose(block);
+        let pool_start = Instant::now();
+        let pool = match decision.strategy {
+            Strategy::Sequential => None,
+            Strategy::Limited(width) | Strategy::Parallel(width) => Some(self.pools.get(width)?),
+        };
+        pool_us += pool_start.elapsed().as_micros();
+
+        let profile = &self.profile;
+        let mut schedule = ScheduleSummary::default();
+        let mut execution = execute_stages(block, chain, shared_state, |base| {
+            let Some(pool) = pool else {
+                // Too small or too entangled to repay thread pools: one EVM,
+                // transactions committed in block order
+                let txs: Vec<&PreparedTx> = block.txs.iter().collect();
+                return Ok(execute_sequential(&txs, base, block_env, spec_id, chain));
+            };
+
+            // Classify transactions
+            let (deterministic_txs, nondeterministic_txs): (Vec<&PreparedTx>, Vec<&PreparedTx>) =
+                block.txs.iter().partition(|tx| tx.deterministic);
+
+            // Williams Strategy 1: Deterministic transactions with PARALLEL execution
+            // Execute ALL deterministic transactions (simple transfers, known patterns)
+            // These are INDEPENDENT - no conflicts, perfect for parallelization!
+            let (det_outcomes, det_schedule) =
+                execute_scheduled(&deterministic_txs, pool, base, block_env, spec_id, chain, profile);
+
+            // Williams Strategy 2: Non-deterministic transactions with REAL parallel execution
+            // Gas-weighted chunks keep every thread busy until the block is done
+            let (nondet_outcomes, nondet_schedule) =
+                execute_scheduled(&nondeterministic_txs, pool, base, block_env, spec_id, chain, profile);
+
+            schedule = det_schedule;
+            schedule.combine(&nondet_schedule);
+
+            // Back into block order
+            let mut det_outcomes = det_outcomes.into_iter();
+            let mut nondet_outcomes = nondet_outcomes.into_iter();
+            let outcomes = block.txs.iter()
+                .map(|tx| if tx.deterministic { det_outcomes.next() } else { nondet_outcomes.next() }.flatten())
+                .collect();
+            Ok(merge_isolated(outcomes, base, block_env.coinbase))
+        })?;
+
+        // Learn from what the transactions actually did, for later blocks and runs
+        for (tx, outcome) in block.txs.iter().zip(&execution.outcomes) {
+            if let Some(outcome) = outcome {
+                self.profile.record(&tx.tx_env, outcome.result.gas_used());
+            }
+        }
+        self.policy.observe(execution.conflict_rate);
+
+        execution.timings.prefetch_us = prefetch.prefetch_us;
+        execution.timings.pool_us = pool_us;
+        execution.prefetch = prefetch;
+        execution.schedule = schedule;
+        execution.decision = Some(decision);
+        Ok(execution)
+    }
+
+    fn code_cache_stats(&self) -> Option<CodeCacheStats> {
+        Some(self.code_cache.stats())
+    }
+
+    fn gas_profile(&self) -> Option<&GasProfile> {
+        Some(&self.profile)
+    }
+
+    fn replay_conflicts(&mut self, conflict_rates: &[f64]) {
+        for &rate in conflict_rates {
+            self.policy.observe(rate);
+        }
+    }
+}
+
+/// Execute in parallel, validate in order, re-execute what was invalidated
+pub struct OptimisticExecutor {
+    chain: ChainConfig,
+    pool: ThreadPool,
+}
+
+impl OptimisticExecutor {
+    pub fn new(chain: ChainConfig, thread_count: usize) -> Result<Self> {
+        Ok(Self { chain, pool: build_pool(thread_count)? })
+    }
+}
+
+impl<DB> BlockExecutor<DB> for OptimisticExecutor
+where
+    DB: DatabaseRef + Send + Sync,
+    DB::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "optimistic"
+    }
+
+    fn execute_with_plan(&mut self, block: &PreparedBlock, db: DB, _plan: &AccessPlan) -> Result<BlockExecution> {
+        let (chain, pool) = (&self.chain, &self.pool);
+        let block_env = &block.block_env;
+        let coinbase = block_env.coinbase;
+        let mut reexecuted = 0;
+
+        let mut execution = execute_stages(block, chain, db, |base| {
+            // Speculate: every transaction against the pre-block state
+            let speculative = execute_isolated(&block.txs, pool, base, block_env, block.spec_id, chain);
+
+            // Validate in block order on one EVM over the committed state
+            let coinbase_before = base.basic_ref(coinbase).ok().flatten().unwrap_or_default().balance;
+            let mut evm = build_block_evm(OverlayDB::new(base.clone()), block_env, block.spec_id, chain);
+            let mut written: HashSet<Location> = HashSet::new();
+            let mut outcomes = Vec::with_capacity(block.txs.len());
+            let mut commit_us = 0;
+
+            for (tx, outcome) in block.txs.iter().zip(speculative) {
+                let speculated = outcome.filter(|outcome| {
+                    !AccessSet::of(&outcome.state, base.as_ref(), coinbase).reads_any(&written)
+                });
+                let outcome = match speculated {
+                    Some(mut outcome) => {
+                        // The fee was credited to the pre-block coinbase balance;
+                        // move it onto the committed one
+                        if let Some(account) = outcome.state.get_mut(&coinbase) {
+                            let fee = account.info.balance.saturating_sub(coinbase_before);
+                            let committed = evm.db().basic_ref(coinbase).ok().flatten().unwrap_or_default().balance;
+                            account.info.balance = committed.saturating_add(fee);
+                        }
+                        Some(outcome)
+                    }
+                    None => {
+                        reexecuted += 1;
+                        execute_transaction(&mut evm, &tx.tx_env)
+                    }
+                };
+                if let Some(outcome) = &outcome {
+                    written.extend(AccessSet::of(&outcome.state, base.as_ref(), coinbase).writes);
+                    let commit_start = Instant::now();
+                    evm.db_mut().commit(outcome.state.clone());
+                    commit_us += commit_start.elapsed().as_micros();
+                }
+                outcomes.push(outcome);
+            }
+            let (state, _) = evm.into_db_and_env_with_handler_cfg();
+            Ok(Transactions { outcomes, state, commit_us })
+        })?;
+
+        execution.reexecuted = reexecuted;
+        Ok(execution)
+    }
+}
+
+/// What a block's transactions did: outcomes in block order, and the state
+/// after all of them as one layer over the pre-block state
+struct Transactions<B> {
+    outcomes: Vec<Option<ResultAndState>>,
+    state: OverlayDB<B>,
+    commit_us: u128,
+}
+
+/// Run the stages every executor shares around `transactions`, which get
+/// the frozen pre-block state
+fn execute_stages<B, F>(block: &PreparedBlock, chain: &ChainConfig, state: B, transactions: F) -> Result<BlockExecution>
+where
+    B: DatabaseRef + Send + Sync,
+    B::Error: Debug,
+    F: FnOnce(&Arc<OverlayDB<B>>) -> Result<Transactions<OverlayDB<B>>>,
+{
+    let block_env = &block.block_env;
+    let spec_id = block.spec_id;
+    let mut timings = StageTimings::default();
+
+    // REAL EXECUTION: Block state as copy-on-write layers over the given state
+    let mut block_db = OverlayDB::new(Arc::new(state));
+
+    // Pre-block system calls (EIP-4788 beacon root, EIP-2935 block hash)
+    let system_start = Instant::now();
+    let mut system_calls = apply_pre_block_system_calls(&mut block_db, &block.system_calls, block_env, spec_id, chain)?;
+    timings.system_calls_us = system_start.elapsed().as_micros();
+
+    // Freeze the pre-block state; transactions get layers on top of it
+    // instead of copies
+    let block_base = Arc::new(block_db);
+    let transactions_start = Instant::now();
+    let Transactions { outcomes, state: mut block_db, commit_us } = transactions(&block_base)?;
+    timings.transactions_us = transactions_start.elapsed().as_micros();
+    timings.commit_us = commit_us;
+
+    // Post-block state transitions on top of the transactions: withdrawals,
+    // block and ommer rewards
+    let finalize_start = Instant::now();
+    let finalize = finalize_block(&mut block_db, &block.finalize, block_env, spec_id)?;
+    timings.finalize_us = finalize_start.elapsed().as_micros();
+
+    // Post-block system calls (EIP-7002 / EIP-7251 requests)
+    let system_start = Instant::now();
+    apply_post_block_system_calls(&mut block_db, block_env, spec_id, chain, &mut system_calls)?;
+    timings.system_calls_us += system_start.elapsed().as_micros();
+
+    // After the clock stopped: how much the transactions depended on each other
+    let states: Vec<Option<&EvmState>> = outcomes.iter()
+        .map(|outcome| outcome.as_ref().map(|outcome| &outcome.state))
+        .collect();
+    let conflict_rate = observed_conflicts(&states, block_base.as_ref(), block_env.coinbase);
+    let mut state = block_base.changes();
+    state.extend(block_db.into_changes());
+
+    Ok(BlockExecution {
+        outcomes,
+        timings,
+        conflict_rate,
+        finalize,
+        system_calls,
+        state,
+        ..Default::default()
+    })
+}
+
+/// Thread pools by width, built on first use and kept across blocks
+#[derive(Default)]
+struct Pools(HashMap<usize, ThreadPool>);
+
+impl Pools {
+    fn get(&mut self, width: usize) -> Result<&ThreadPool> {
+        Ok(match self.0.entry(width) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(build_pool(width)?),
+        })
+    }
+}
+
+fn build_pool(thread_count: usize) -> Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(thread_count)
+        .build()
+        .with_context(|| format!("Failed to create a pool of {} threads", thread_count))
+}
+
+/// Build an EVM for one block: chain, fork and block environment are set
+/// once, transactions and databases are swapped in per transaction
+pub fn build_block_evm<'a, DB: Database + 'a>(
+    db: DB,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+    chain: &ChainConfig,
+) -> Evm<'a, (), DB> {
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_block_env(block_env.clone())
+        .with_cfg_env_with_handler_cfg(chain.cfg_env(spec_id))
+        .build();
+    chain.apply_precompiles(&mut evm);
+    evm
+}
+
+/// Execute a single transaction using REVM on a block's EVM; returns its
+/// result and state changes, or `None` if the transaction was invalid
+pub fn execute_transaction<DB: Database>(evm: &mut Evm<'_, (), DB>, tx_env: &TxEnv) -> Option<ResultAndState> {
+    *evm.tx_mut() = tx_env.clone();
+    evm.transact().ok()
+}
+
+/// Every transaction on its own layer over `block_base`, split evenly over
+/// the pool; one EVM per worker
+fn execute_isolated<B: DatabaseRef + Send + Sync>(
+    txs: &[PreparedTx],
+    pool: &ThreadPool,
+    block_base: &Arc<B>,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+    chain: &ChainConfig,
+) -> Vec<Option<ResultAndState>> {
+    pool.install(|| {
+        txs.par_iter()
+            .map_init(
+                || build_block_evm(OverlayDB::new(block_base.clone()), block_env, spec_id, chain),
+                |evm, tx| {
+                    // Each transaction gets its own layer, discarded afterwards
+                    *evm.db_mut() = OverlayDB::new(block_base.clone());
+                    execute_transaction(evm, &tx.tx_env)
+                },
+            )
+            .collect()
+    })
+}
+
+/// Run one class of a block's transactions on the pool, gas-weighted: each
+/// worker builds one EVM and takes chunks, most expensive first, until none
+/// are left. Returns each transaction's outcome (`None` if invalid).
+fn execute_scheduled<B: DatabaseRef + Send + Sync>(
+    txs: &[&PreparedTx],
+    pool: &ThreadPool,
+    block_base: &Arc<B>,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+    chain: &ChainConfig,
+    profile: &GasProfile,
+) -> (Vec<Option<ResultAndState>>, ScheduleSummary) {
+    if txs.is_empty() {
+        return (Vec::new(), ScheduleSummary::default());
+    }
+    let schedule = Schedule::plan(txs, profile, pool.current_num_threads());
+
+    let executed = pool.broadcast(|_| {
+        let mut evm = build_block_evm(OverlayDB::new(block_base.clone()), block_env, spec_id, chain);
+        let mut executed = Vec::new();
+        while let Some(chunk) = schedule.next_chunk() {
+            for &index in chunk {
+                // Each transaction gets its own layer, discarded afterwards
+                *evm.db_mut() = OverlayDB::new(block_base.clone());
+                executed.push((index, execute_transaction(&mut evm, &txs[index].tx_env)));
+            }
+        }
+        executed
+    });
+
+    let mut outcomes: Vec<Option<ResultAndState>> = (0..txs.len()).map(|_| None).collect();
+    for (index, outcome) in executed.into_iter().flatten() {
+        outcomes[index] = outcome;
+    }
+    (outcomes, schedule.summary().clone())
+}
+
+/// Execute transactions in order on one EVM, each seeing the ones before it
+fn execute_sequential<B: DatabaseRef>(
+    txs: &[&PreparedTx],
+    block_base: &Arc<B>,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+    chain: &ChainConfig,
+) -> Transactions<B> {
+    let mut evm = build_block_evm(OverlayDB::new(block_base.clone()), block_env, spec_id, chain);
+    let mut commit_us = 0;
+    let outcomes = txs.iter()
+        .map(|tx| {
+            let outcome = execute_transaction(&mut evm, &tx.tx_env);
+            if let Some(outcome) = &outcome {
+                let commit_start = Instant::now();
+                evm.db_mut().commit(outcome.state.clone());
+                commit_us += commit_start.elapsed().as_micros();
+            }
+            outcome
+        })
+        .collect();
+    let (state, _) = evm.into_db_and_env_with_handler_cfg();
+    Transactions { outcomes, state, commit_us }
+}
+
+/// Commit outcomes of transactions executed in isolation into one layer over
+/// `block_base`, in block order. Each was executed on the pre-block state, so
+/// fees credited to the coinbase are moved onto its committed balance.
+fn merge_isolated<B: DatabaseRef>(
+    outcomes: Vec<Option<ResultAndState>>,
+    block_base: &Arc<B>,
+    coinbase: Address,
+) -> Transactions<B> {
+    let commit_start = Instant::now();
+    let coinbase_before = block_base.basic_ref(coinbase).ok().flatten().unwrap_or_default().balance;
+    let mut state = OverlayDB::new(block_base.clone());
+    for outcome in outcomes.iter().flatten() {
+        let mut changes = outcome.state.clone();
+        if let Some(account) = changes.get_mut(&coinbase) {
+            let fee = account.info.balance.saturating_sub(coinbase_before);
+            let committed = state.basic_ref(coinbase).ok().flatten().unwrap_or_default().balance;
+            account.info.balance = committed.saturating_add(fee);
+        }
+        state.commit(changes);
+    }
+    Transactions { outcomes, state, commit_us: commit_start.elapsed().as_micros() }
+}
@@ -0,0 +1,304 @@
+// Post-block state transitions
+//
+// After the last transaction a block still changes state:
+// - Shanghai+: beacon-chain withdrawals are credited (amounts are in Gwei)
+// - Pre-merge: the miner earns the block reward plus 1/32 of it per ommer,
+//   and each ommer's miner earns (8 + ommer_number - number) / 8 of it
+//
+// Balance increments are committed through `DatabaseCommit`, so any state
+// backend REVM can execute against can also be finalized.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use revm::{
+    primitives::{Account, AccountStatus, Address, BlockEnv, SpecId, U256},
+    Database, DatabaseCommit,
+};
+
+use crate::json_hex::{field_address, field_u64};
+
+/// Wei per Gwei, withdrawal amounts are denominated in Gwei
+const GWEI_TO_WEI: u64 = 1_000_000_000;
+
+/// What finalization applied to a block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FinalizeSummary {
+    pub withdrawals: usize,
+    pub withdrawn_wei: U256,
+    pub block_reward_wei: U256,
+    pub ommer_reward_wei: U256,
+    /// Ommers listed in `uncles` whose headers were not in the JSON
+    pub unpaid_ommers: usize,
+}
+
+/// Base block reward for a pre-merge fork (zero from the Merge on)
+pub fn base_block_reward(spec_id: SpecId) -> U256 {
+    const ETHER: u128 = 1_000_000_000_000_000_000;
+    let reward = if spec_id >= SpecId::MERGE {
+        0
+    } else if spec_id >= SpecId::CONSTANTINOPLE {
+        2 * ETHER
+    } else if spec_id >= SpecId::BYZANTIUM {
+        3 * ETHER
+    } else {
+        5 * ETHER
+    };
+    U256::from(reward)
+}
+
+/// What finalization needs from the block, decoded once
+///
+/// Ommer rewards need the ommer headers (miner and number), which
+/// `eth_getBlockByNumber` does not return; they are read from an optional
+/// `uncleHeaders` array. Ommers without a header are counted as unpaid.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FinalizeInputs {
+    /// Number of ommers listed in `uncles`
+    pub ommer_count: usize,
+    /// Miner and number of each ommer whose header is known
+    pub ommers: Vec<(Address, u64)>,
+    /// Recipient and amount in Gwei of each withdrawal
+    pub withdrawals: Vec<(Address, u64)>,
+}
+
+impl FinalizeInputs {
+    /// Read ommers (pre-merge) and withdrawals (Shanghai+) from block JSON
+    pub fn from_block(block: &Value, spec_id: SpecId) -> Result<Self> {
+        let mut inputs = Self::default();
+
+        if spec_id < SpecId::MERGE {
+            inputs.ommer_count = block.get("uncles")
+                .and_then(|u| u.as_array())
+                .map_or(0, |u| u.len());
+            let ommer_headers = block.get("uncleHeaders")
+                .and_then(|u| u.as_array())
+                .map(|u| u.as_slice())
+                .unwrap_or_default();
+            inputs.ommers = ommer_headers.iter()
+                .filter_map(|ommer| Some((field_address(ommer, "miner")?, field_u64(ommer, "number")?)))
+                .collect();
+        }
+
+        if spec_id >= SpecId::SHANGHAI {
+            let withdrawals = block.get("withdrawals")
+                .and_then(|w| w.as_array())
+                .map(|w| w.as_slice())
+                .unwrap_or_default();
+
+            for withdrawal in withdrawals {
+                let address = field_address(withdrawal, "address")
+                    .ok_or_else(|| anyhow!("Withdrawal without a valid address"))?;
+                let amount_gwei = field_u64(withdrawal, "amount")
+                    .ok_or_else(|| anyhow!("Withdrawal without a valid amount"))?;
+                inputs.withdrawals.push((address, amount_gwei));
+            }
+        }
+
+        Ok(inputs)
+    }
+}
+
+/// Apply withdrawals and block/ommer rewards after the last transaction
+pub fn finalize_block<DB>(
+    db: &mut DB,
+    inputs: &FinalizeInputs,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+) -> Result<FinalizeSummary>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: std::fmt::Debug,
+{
+    let mut summary = FinalizeSummary::default();
+    let mut increments: HashMap<Address, U256> = HashMap::new();
+
+    // Block and ommer rewards (pre-merge only)
+    let base_reward = base_block_reward(spec_id);
+    if base_reward > U256::ZERO {
+        let block_number: u64 = block_env.number.saturating_to();
+
+        for &(miner, number) in &inputs.ommers {
+            let depth = block_number.saturating_sub(number);
+            if depth == 0 || depth > 6 {
+                continue;
+            }
+            let reward = base_reward * U256::from(8 - depth) / U256::from(8);
+            *increments.entry(miner).or_default() += reward;
+            summary.ommer_reward_wei += reward;
+        }
+        summary.unpaid_ommers = inputs.ommer_count.saturating_sub(inputs.ommers.len());
+
+        let miner_reward = base_reward + base_reward / U256::from(32) * U256::from(inputs.ommer_count);
+        *increments.entry(block_env.coinbase).or_default() += miner_reward;
+        summary.block_reward_wei = miner_reward;
+    }
+
+    // Beacon-chain withdrawals (Shanghai+)
+    if spec_id >= SpecId::SHANGHAI {
+        for &(address, amount_gwei) in &inputs.withdrawals {
+            let amount = U256::from(amount_gwei) * U256::from(GWEI_TO_WEI);
+
+            summary.withdrawals += 1;
+            summary.withdrawn_wei += amount;
+            if amount > U256::ZERO {
+                *increments.entry(address).or_default() += amount;
+            }
+        }
+    }
+
+    apply_balance_increments(db, increments)?;
+    Ok(summary)
+}
+
+/// Credit balances and commit them as touched accounts
+pub fn apply_balance_increments<DB>(db: &mut DB, increments: HashMap<Address, U256>) -> Result<()>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: std::fmt::Debug,
+{
+    if increments.is_empty() {
+        return Ok(());
+    }
+
+    let mut changes = HashMap::with_capacity(increments.len());
+    for (address, amount) in increments {
+        let mut info = db.basic(address)
+            .map_err(|e| anyhow!("Failed to load account {}: {:?}", address, e))?
+            .unwrap_or_default();
+        info.balance = info.balance.saturating_add(amount);
+
+        let mut account = Account::from(info);
+        account.status = AccountStatus::Touched;
+        changes.insert(address, account);
+    }
+
+    db.commit(changes.into_iter().collect());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use revm::db::{CacheDB, EmptyDB};
+    use revm::primitives::{address, AccountInfo};
+    use serde_json::json;
+
+    use crate::chain_config::ChainConfig;
+
+    const ETHER: u64 = 1_000_000_000_000_000_000;
+    const MINER: Address = address!("00000000000000000000000000000000000000c0");
+
+    /// `numerator / denominator` ether in wei
+    fn ether(numerator: u64, denominator: u64) -> U256 {
+        U256::from(ETHER) * U256::from(numerator) / U256::from(denominator)
+    }
+
+    fn block_env(number: u64) -> BlockEnv {
+        BlockEnv { number: U256::from(number), coinbase: MINER, ..Default::default() }
+    }
+
+    fn balance(db: &mut CacheDB<EmptyDB>, address: Address) -> U256 {
+        db.basic(address).unwrap().map_or(U256::ZERO, |info| info.balance)
+    }
+
+    #[test]
+    fn block_reward_per_fork() {
+        let chain = ChainConfig::mainnet();
+        let reward_at = |number| base_block_reward(chain.spec_at(number, 0));
+        assert_eq!(reward_at(1), ether(5, 1));
+        assert_eq!(reward_at(4_369_999), ether(5, 1));
+        assert_eq!(reward_at(4_370_000), ether(3, 1));
+        assert_eq!(reward_at(7_279_999), ether(3, 1));
+        assert_eq!(reward_at(7_280_000), ether(2, 1));
+        assert_eq!(reward_at(15_537_393), ether(2, 1));
+        assert_eq!(reward_at(15_537_394), U256::ZERO);
+        assert_eq!(base_block_reward(SpecId::CONSTANTINOPLE), ether(2, 1));
+    }
+
+    #[test]
+    fn mainnet_block_one_pays_five_ether() {
+        // Block 1 has no ommers; its miner starts with nothing in genesis
+        let miner = address!("05a56e2d52c817161883f50c441c3228cfe54d9f");
+        let env = BlockEnv { number: U256::from(1), coinbase: miner, ..Default::default() };
+        let mut db = CacheDB::new(EmptyDB::default());
+        let summary = finalize_block(&mut db, &FinalizeInputs::default(), &env, SpecId::FRONTIER).unwrap();
+        assert_eq!(balance(&mut db, miner), ether(5, 1));
+        assert_eq!(summary.block_reward_wei, ether(5, 1));
+    }
+
+    #[test]
+    fn ommer_and_nephew_rewards() {
+        let (near, far, stale) = (
+            address!("00000000000000000000000000000000000000d1"),
+            address!("00000000000000000000000000000000000000d2"),
+            address!("00000000000000000000000000000000000000d7"),
+        );
+        let block = json!({
+            "uncles": ["0x01", "0x02", "0x03", "0x04"],
+            "uncleHeaders": [
+                { "miner": near.to_string(), "number": "0x63" },
+                { "miner": far.to_string(), "number": "0x62" },
+                { "miner": stale.to_string(), "number": "0x5d" },
+            ],
+        });
+        let inputs = FinalizeInputs::from_block(&block, SpecId::BYZANTIUM).unwrap();
+        assert_eq!(inputs.ommer_count, 4);
+        assert_eq!(inputs.ommers.len(), 3);
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(MINER, AccountInfo { balance: U256::from(1), ..Default::default() });
+        let summary = finalize_block(&mut db, &inputs, &block_env(100), SpecId::BYZANTIUM).unwrap();
+
+        // 3 ETH at Byzantium: depth 1 earns 7/8, depth 2 earns 6/8, depth 7 nothing;
+        // the miner earns 1/32 per listed ommer, paid or not
+        assert_eq!(balance(&mut db, near), ether(3 * 7, 8));
+        assert_eq!(balance(&mut db, far), ether(3 * 6, 8));
+        assert_eq!(balance(&mut db, stale), U256::ZERO);
+        assert_eq!(balance(&mut db, MINER), U256::from(1) + ether(3 * 36, 32));
+        assert_eq!(summary.ommer_reward_wei, ether(3 * 13, 8));
+        assert_eq!(summary.unpaid_ommers, 1);
+    }
+
+    #[test]
+    fn no_rewards_after_the_merge() {
+        let block = json!({ "uncles": ["0x01"], "uncleHeaders": [{ "miner": MINER.to_string(), "number": "0x1" }] });
+        let inputs = FinalizeInputs::from_block(&block, SpecId::MERGE).unwrap();
+        assert_eq!(inputs.ommer_count, 0);
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        let summary = finalize_block(&mut db, &inputs, &block_env(15_537_394), SpecId::MERGE).unwrap();
+        assert_eq!(summary.block_reward_wei, U256::ZERO);
+        assert!(db.accounts.is_empty());
+    }
+
+    #[test]
+    fn withdrawals_are_credited_in_wei() {
+        let validator = address!("00000000000000000000000000000000000000e1");
+        let block = json!({
+            "withdrawals": [
+                { "index": "0x0", "validatorIndex": "0x1", "address": validator.to_string(), "amount": "0x1" },
+                { "index": "0x1", "validatorIndex": "0x1", "address": validator.to_string(), "amount": "0x773594000" },
+                { "index": "0x2", "validatorIndex": "0x2", "address": MINER.to_string(), "amount": "0x0" },
+            ],
+        });
+        let inputs = FinalizeInputs::from_block(&block, SpecId::SHANGHAI).unwrap();
+        let mut db = CacheDB::new(EmptyDB::default());
+        let summary = finalize_block(&mut db, &inputs, &block_env(17_034_870), SpecId::SHANGHAI).unwrap();
+
+        // 1 Gwei plus 32 ETH worth of Gwei; a zero withdrawal touches nothing
+        let expected = U256::from(GWEI_TO_WEI) + ether(32, 1);
+        assert_eq!(balance(&mut db, validator), expected);
+        assert_eq!(summary.withdrawals, 3);
+        assert_eq!(summary.withdrawn_wei, expected);
+        assert!(!db.accounts.contains_key(&MINER));
+
+        // Before Shanghai withdrawals are not read
+        let inputs = FinalizeInputs::from_block(&block, SpecId::MERGE).unwrap();
+        assert!(inputs.withdrawals.is_empty());
+    }
+}
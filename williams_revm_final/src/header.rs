@@ -0,0 +1,160 @@
+// Block header handling: BlockEnv population and header field validation
+//
+// Which header fields exist depends on the fork: `difficulty` is replaced by
+// `mixHash` (prevrandao) at the Merge, Cancun adds the blob gas fields and
+// the parent beacon block root, and so on. `setup_block_env` maps all fields
+// REVM consumes, `validate_header` reports which ones the JSON is missing.
+// `header_hash` recomputes the block hash from the RLP-encoded header.
+
+use anyhow::{Result, Context};
+use serde_json::Value;
+
+use revm::primitives::{
+    BlockEnv, SpecId, BlobExcessGasAndPrice, B256, U256,
+    fake_exponential, keccak256, MIN_BLOB_GASPRICE,
+};
+
+use crate::chain_config::ChainConfig;
+use crate::json_hex::{field_address, field_b256, field_bytes, field_u256, field_u64};
+use crate::tx_rlp::RlpList;
+
+/// Fields present in every header since Frontier
+const BASE_HEADER_FIELDS: [&str; 15] = [
+    "parentHash", "sha3Uncles", "miner", "stateRoot", "transactionsRoot",
+    "receiptsRoot", "logsBloom", "difficulty", "number", "gasLimit",
+    "gasUsed", "timestamp", "extraData", "mixHash", "nonce",
+];
+
+/// Fields added by later forks, with the fork that introduced them
+const FORK_HEADER_FIELDS: [(SpecId, &str); 6] = [
+    (SpecId::LONDON, "baseFeePerGas"),
+    (SpecId::SHANGHAI, "withdrawalsRoot"),
+    (SpecId::CANCUN, "blobGasUsed"),
+    (SpecId::CANCUN, "excessBlobGas"),
+    (SpecId::CANCUN, "parentBeaconBlockRoot"),
+    (SpecId::PRAGUE, "requestsHash"),
+];
+
+/// Header fields required by `spec` that are absent from the block JSON
+pub fn validate_header(block: &Value, spec_id: SpecId) -> Vec<&'static str> {
+    let required = BASE_HEADER_FIELDS.iter().copied().chain(
+        FORK_HEADER_FIELDS
+            .iter()
+            .filter(|(fork, _)| spec_id >= *fork)
+            .map(|(_, field)| *field),
+    );
+
+    required
+        .filter(|field| block.get(*field).is_none_or(|v| v.is_null()))
+        .collect()
+}
+
+/// Setup block environment following the chain's fork schedule
+///
+/// Missing fork-specific fields fall back to zero so execution can proceed;
+/// `validate_header` is what surfaces them.
+pub fn setup_block_env(block: &Value, chain: &ChainConfig) -> Result<BlockEnv> {
+    let mut block_env = BlockEnv::default();
+
+    if let Some(number) = field_u64(block, "number") {
+        block_env.number = U256::from(number);
+    }
+    if let Some(timestamp) = field_u64(block, "timestamp") {
+        block_env.timestamp = U256::from(timestamp);
+    }
+    if let Some(gas_limit) = field_u64(block, "gasLimit") {
+        block_env.gas_limit = U256::from(gas_limit);
+    }
+    if let Some(coinbase) = field_address(block, "miner") {
+        block_env.coinbase = coinbase;
+    }
+
+    let spec_id = chain.spec_for_block(&block_env);
+
+    // Base fee only exists from London on; the first London block of a
+    // chain may omit it, in which case the configured initial value applies
+    if spec_id >= SpecId::LONDON {
+        block_env.basefee = field_u256(block, "baseFeePerGas")
+            .unwrap_or(U256::from(chain.base_fee.initial_base_fee));
+    }
+
+    // After the Merge DIFFICULTY reads PREVRANDAO, which lives in mixHash
+    if spec_id >= SpecId::MERGE {
+        block_env.difficulty = U256::ZERO;
+        block_env.prevrandao = Some(field_b256(block, "mixHash").unwrap_or(B256::ZERO));
+    } else {
+        block_env.difficulty = field_u256(block, "difficulty").unwrap_or_default();
+        block_env.prevrandao = None;
+    }
+
+    // Blob gas price uses the blob parameters of the active fork
+    if spec_id >= SpecId::CANCUN {
+        let excess_blob_gas = field_u64(block, "excessBlobGas").unwrap_or(0);
+        block_env.blob_excess_gas_and_price = Some(match chain.blob_params(spec_id) {
+            Some(params) => BlobExcessGasAndPrice {
+                excess_blob_gas,
+                blob_gasprice: fake_exponential(
+                    MIN_BLOB_GASPRICE,
+                    excess_blob_gas,
+                    params.update_fraction,
+                ),
+            },
+            None => BlobExcessGasAndPrice::new(excess_blob_gas),
+        });
+    }
+
+    Ok(block_env)
+}
+
+/// Block hash: keccak256 of the RLP-encoded header
+pub fn header_hash(block: &Value) -> Result<B256> {
+    Ok(keccak256(encode_header(block)?))
+}
+
+/// RLP-encode a header from its JSON-RPC fields
+///
+/// Fork fields are appended in consensus order for as long as they are
+/// present, which matches how every fork extended the header.
+pub fn encode_header(block: &Value) -> Result<Vec<u8>> {
+    let b256 = |key: &str| field_b256(block, key).with_context(|| format!("Header has no {}", key));
+    let quantity = |key: &str| field_u256(block, key).with_context(|| format!("Header has no {}", key));
+    let bytes = |key: &str| field_bytes(block, key).with_context(|| format!("Header has no {}", key));
+
+    let mut fields = RlpList::default();
+    fields.push(&b256("parentHash")?);
+    fields.push(&b256("sha3Uncles")?);
+    fields.push(&field_address(block, "miner").context("Header has no miner")?);
+    fields.push(&b256("stateRoot")?);
+    fields.push(&b256("transactionsRoot")?);
+    fields.push(&b256("receiptsRoot")?);
+    fields.push(&bytes("logsBloom")?);
+    fields.push(&quantity("difficulty")?);
+    fields.push(&quantity("number")?);
+    fields.push(&quantity("gasLimit")?);
+    fields.push(&quantity("gasUsed")?);
+    fields.push(&quantity("timestamp")?);
+    fields.push(&bytes("extraData")?);
+    fields.push(&b256("mixHash")?);
+    fields.push(&bytes("nonce")?);
+
+    if let Some(base_fee) = field_u256(block, "baseFeePerGas") {
+        fields.push(&base_fee);
+        if let Some(withdrawals_root) = field_b256(block, "withdrawalsRoot") {
+            fields.push(&withdrawals_root);
+            if let (Some(blob_gas_used), Some(excess_blob_gas), Some(beacon_root)) = (
+                field_u64(block, "blobGasUsed"),
+                field_u64(block, "excessBlobGas"),
+                field_b256(block, "parentBeaconBlockRoot"),
+            ) {
+                fields.push(&blob_gas_used);
+                fields.push(&excess_blob_gas);
+                fields.push(&beacon_root);
+                if let Some(requests_hash) = field_b256(block, "requestsHash") {
+                    fields.push(&requests_hash);
+                }
+            }
+        }
+    }
+
+    Ok(fields.finish())
+}
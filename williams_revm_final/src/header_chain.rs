@@ -0,0 +1,371 @@
+// Header chain validation across a block dataset
+//
+// A benchmark over a directory of blocks only means something if the blocks
+// form one chain. Each header is re-hashed from its RLP encoding, then
+// consecutive headers are checked for parentHash linkage, increasing
+// timestamps, gas limit bounds and the EIP-1559 base fee derived from the
+// parent. Gaps, duplicates and forks (competing blocks at one height) are
+// reported before anything is measured.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use revm::primitives::{SpecId, B256};
+
+use crate::chain_config::ChainConfig;
+use crate::header::header_hash;
+use crate::json_hex::{field_b256, field_u64};
+
+/// Gas limit may move by less than parent_gas_limit / 1024 per block
+const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+
+/// Smallest gas limit a header may declare
+const MIN_GAS_LIMIT: u64 = 5000;
+
+/// The header fields chain validation looks at
+#[derive(Debug, Clone)]
+pub struct HeaderInfo {
+    /// Where the block came from (file name)
+    pub source: String,
+    pub number: u64,
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub base_fee: Option<u64>,
+    pub parent_hash: Option<B256>,
+    /// `hash` as stored in the JSON
+    pub claimed_hash: Option<B256>,
+    /// Hash recomputed from the header fields
+    pub computed_hash: Result<B256, String>,
+}
+
+impl HeaderInfo {
+    /// Extract what chain validation needs; None if there is no block number
+    pub fn from_block(block: &Value, source: String) -> Option<Self> {
+        Some(Self {
+            source,
+            number: field_u64(block, "number")?,
+            timestamp: field_u64(block, "timestamp").unwrap_or_default(),
+            gas_limit: field_u64(block, "gasLimit").unwrap_or_default(),
+            gas_used: field_u64(block, "gasUsed").unwrap_or_default(),
+            base_fee: field_u64(block, "baseFeePerGas"),
+            parent_hash: field_b256(block, "parentHash"),
+            claimed_hash: field_b256(block, "hash"),
+            computed_hash: header_hash(block).map_err(|e| e.to_string()),
+        })
+    }
+
+    /// Best known hash: recomputed if possible, else the JSON one
+    fn hash(&self) -> Option<B256> {
+        self.computed_hash.as_ref().ok().copied().or(self.claimed_hash)
+    }
+}
+
+/// Findings of header chain validation
+#[derive(Debug, Clone, Default)]
+pub struct ChainReport {
+    pub blocks: usize,
+    pub range: Option<(u64, u64)>,
+    /// Missing block numbers as inclusive ranges
+    pub gaps: Vec<(u64, u64)>,
+    /// Same block (same hash) present more than once
+    pub duplicates: Vec<u64>,
+    /// Different blocks at the same height
+    pub forks: Vec<u64>,
+    pub problems: Vec<String>,
+}
+
+impl ChainReport {
+    pub fn missing_blocks(&self) -> u64 {
+        self.gaps.iter().map(|(from, to)| to - from + 1).sum()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.gaps.is_empty() && self.duplicates.is_empty() && self.forks.is_empty() && self.problems.is_empty()
+    }
+}
+
+/// Validate the headers of a dataset as one chain
+pub fn validate_header_chain(headers: Vec<HeaderInfo>, chain: &ChainConfig) -> ChainReport {
+    let mut report = ChainReport {
+        blocks: headers.len(),
+        ..Default::default()
+    };
+
+    for header in &headers {
+        match (&header.computed_hash, header.claimed_hash) {
+            (Ok(computed), Some(claimed)) if *computed != claimed => report.problems.push(format!(
+                "block {} ({}): hash {} but header hashes to {}",
+                header.number, header.source, claimed, computed
+            )),
+            (Err(e), _) => report.problems.push(format!(
+                "block {} ({}): cannot hash header ({})", header.number, header.source, e
+            )),
+            _ => {}
+        }
+    }
+
+    // Group by height: one entry is normal, identical entries are duplicates,
+    // differing entries are forks
+    let mut by_number: BTreeMap<u64, Vec<HeaderInfo>> = BTreeMap::new();
+    for header in headers {
+        by_number.entry(header.number).or_default().push(header);
+    }
+    for (number, entries) in &by_number {
+        if entries.len() < 2 {
+            continue;
+        }
+        let first = entries[0].hash();
+        if entries.iter().all(|e| e.hash() == first) {
+            report.duplicates.push(*number);
+        } else {
+            report.forks.push(*number);
+        }
+    }
+
+    report.range = by_number.keys().next().copied().zip(by_number.keys().next_back().copied());
+
+    let mut previous: Option<&HeaderInfo> = None;
+    for (number, entries) in &by_number {
+        let header = &entries[0];
+        if let Some(parent) = previous {
+            if *number > parent.number + 1 {
+                report.gaps.push((parent.number + 1, number - 1));
+            } else {
+                check_child(parent, header, chain, &mut report.problems);
+            }
+        }
+        previous = Some(header);
+    }
+
+    report
+}
+
+/// Consensus checks between a header and its direct parent
+fn check_child(parent: &HeaderInfo, child: &HeaderInfo, chain: &ChainConfig, problems: &mut Vec<String>) {
+    let number = child.number;
+
+    if let (Some(parent_hash), Some(expected)) = (child.parent_hash, parent.hash()) {
+        if parent_hash != expected {
+            problems.push(format!(
+                "block {}: parentHash {} does not link to block {} ({})",
+                number, parent_hash, parent.number, expected
+            ));
+        }
+    }
+
+    if child.timestamp <= parent.timestamp {
+        problems.push(format!(
+            "block {}: timestamp {} not after parent's {}",
+            number, child.timestamp, parent.timestamp
+        ));
+    }
+
+    let parent_spec = chain.spec_at(parent.number, parent.timestamp);
+    let child_spec = chain.spec_at(child.number, child.timestamp);
+    let london_transition = child_spec >= SpecId::LONDON && parent_spec < SpecId::LONDON;
+
+    // At the London transition the parent's limit is scaled by the elasticity
+    let parent_gas_limit = if london_transition {
+        parent.gas_limit * chain.base_fee.elasticity_multiplier
+    } else {
+        parent.gas_limit
+    };
+    let max_delta = parent_gas_limit / GAS_LIMIT_BOUND_DIVISOR;
+    if child.gas_limit.abs_diff(parent_gas_limit) >= max_delta || child.gas_limit < MIN_GAS_LIMIT {
+        problems.push(format!(
+            "block {}: gas limit {} out of bounds (parent {})",
+            number, child.gas_limit, parent_gas_limit
+        ));
+    }
+
+    if child_spec >= SpecId::LONDON {
+        let expected = if london_transition {
+            Some(chain.base_fee.initial_base_fee)
+        } else {
+            parent.base_fee.map(|base_fee| {
+                chain.base_fee.next_base_fee(parent.gas_used, parent.gas_limit, base_fee)
+            })
+        };
+        if let (Some(expected), Some(actual)) = (expected, child.base_fee) {
+            if expected != actual {
+                problems.push(format!(
+                    "block {}: base fee {} but parent implies {}", number, actual, expected
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use revm::primitives::keccak256;
+
+    use crate::raw_block::decode_block;
+    use crate::raw_block::tests::{block, genesis_header, GENESIS_HASH};
+
+    /// A header with a made-up hash that is unique per number and variant
+    fn header(number: u64, variant: u8, parent: Option<&HeaderInfo>) -> HeaderInfo {
+        let mut seed = number.to_be_bytes().to_vec();
+        seed.push(variant);
+        HeaderInfo {
+            source: format!("{}.json", number),
+            number,
+            timestamp: parent.map_or(1_700_000_000, |p| p.timestamp + 12),
+            gas_limit: parent.map_or(30_000_000, |p| p.gas_limit),
+            gas_used: 15_000_000,
+            base_fee: parent.map_or(Some(10_000_000_000), |p| p.base_fee),
+            parent_hash: parent.and_then(HeaderInfo::hash),
+            claimed_hash: None,
+            computed_hash: Ok(keccak256(&seed)),
+        }
+    }
+
+    /// A valid post-London chain whose blocks each use exactly the gas target
+    fn chain_of(first: u64, len: u64) -> Vec<HeaderInfo> {
+        let mut headers = vec![header(first, 0, None)];
+        for number in first + 1..first + len {
+            let parent = headers.last().unwrap();
+            headers.push(header(number, 0, Some(parent)));
+        }
+        headers
+    }
+
+    fn validate(headers: Vec<HeaderInfo>) -> ChainReport {
+        validate_header_chain(headers, &ChainConfig::mainnet())
+    }
+
+    #[test]
+    fn london_transition_on_mainnet() {
+        // Blocks 12,965,000 and the base fee of 12,965,001 are mainnet values;
+        // the other fields only need to lie within bounds
+        let mut berlin = header(12_964_999, 0, None);
+        berlin.timestamp = 1_628_166_810;
+        berlin.gas_limit = 15_000_000;
+        berlin.base_fee = None;
+        let mut london = header(12_965_000, 0, Some(&berlin));
+        london.timestamp = 1_628_166_822;
+        london.gas_limit = 30_029_122;
+        london.gas_used = 30_025_257;
+        london.base_fee = Some(1_000_000_000);
+        let mut next = header(12_965_001, 0, Some(&london));
+        next.base_fee = Some(1_124_967_822);
+
+        let report = validate(vec![berlin.clone(), london.clone(), next.clone()]);
+        assert!(report.is_clean(), "{:?}", report);
+        assert_eq!(report.range, Some((12_964_999, 12_965_001)));
+
+        // The first London block must start at the initial base fee
+        london.base_fee = Some(1_000_000_001);
+        let report = validate(vec![berlin.clone(), london.clone(), next.clone()]);
+        assert_eq!(report.problems.len(), 2, "{:?}", report.problems);
+        assert!(report.problems[0].starts_with("block 12965000: base fee"));
+
+        // Past the transition the parent's limit is not doubled, so the same jump is out of bounds
+        let (mut parent, mut child) = (berlin, london);
+        parent.number += 1;
+        child.number += 1;
+        let mut problems = Vec::new();
+        check_child(&parent, &child, &ChainConfig::mainnet(), &mut problems);
+        assert!(problems.iter().any(|p| p.ends_with("gas limit 30029122 out of bounds (parent 15000000)")), "{:?}", problems);
+    }
+
+    #[test]
+    fn base_fee_follows_gas_used() {
+        let params = ChainConfig::mainnet().base_fee;
+        assert_eq!(params.next_base_fee(15_000_000, 30_000_000, 8_000_000_000), 8_000_000_000);
+        assert_eq!(params.next_base_fee(30_000_000, 30_000_000, 8_000_000_000), 9_000_000_000);
+        assert_eq!(params.next_base_fee(0, 30_000_000, 8_000_000_000), 7_000_000_000);
+        // An increase is at least one wei
+        assert_eq!(params.next_base_fee(15_000_001, 30_000_000, 7), 8);
+
+        let mut headers = chain_of(20_000_000, 3);
+        headers[1].gas_used = 30_000_000;
+        headers[2].base_fee = Some(11_250_000_000);
+        assert!(validate(headers.clone()).is_clean());
+        headers[2].base_fee = Some(10_000_000_000);
+        let report = validate(headers);
+        assert_eq!(report.problems, vec!["block 20000002: base fee 10000000000 but parent implies 11250000000"]);
+    }
+
+    #[test]
+    fn gas_limit_moves_less_than_one_1024th() {
+        let mut headers = chain_of(20_000_000, 2);
+        headers[1].gas_limit = 30_000_000 + 30_000_000 / 1024 - 1;
+        assert!(validate(headers.clone()).is_clean());
+        headers[1].gas_limit = 30_000_000 + 30_000_000 / 1024;
+        assert_eq!(validate(headers.clone()).problems.len(), 1);
+        headers[1].gas_limit = 30_000_000 - 30_000_000 / 1024;
+        assert_eq!(validate(headers).problems.len(), 1);
+
+        let mut headers = chain_of(20_000_000, 2);
+        headers[0].gas_limit = MIN_GAS_LIMIT;
+        headers[0].gas_used = MIN_GAS_LIMIT / 2;
+        headers[1].gas_limit = MIN_GAS_LIMIT - 1;
+        let report = validate(headers);
+        assert_eq!(report.problems, vec!["block 20000001: gas limit 4999 out of bounds (parent 5000)"]);
+    }
+
+    #[test]
+    fn gap_is_reported_once() {
+        let headers = chain_of(100, 6);
+        let report = validate(vec![headers[0].clone(), headers[1].clone(), headers[4].clone(), headers[5].clone()]);
+        assert_eq!(report.gaps, vec![(102, 103)]);
+        assert_eq!(report.missing_blocks(), 2);
+        assert!(report.problems.is_empty() && report.duplicates.is_empty() && report.forks.is_empty());
+    }
+
+    #[test]
+    fn duplicate_block() {
+        let mut headers = chain_of(100, 3);
+        headers.push(headers[1].clone());
+        let report = validate(headers);
+        assert_eq!(report.duplicates, vec![101]);
+        assert!(report.forks.is_empty() && report.problems.is_empty());
+    }
+
+    #[test]
+    fn competing_blocks_are_a_fork() {
+        let mut headers = chain_of(100, 2);
+        let uncle = header(101, 1, Some(&headers[0]));
+        headers.push(uncle);
+        let report = validate(headers);
+        assert_eq!(report.forks, vec![101]);
+        assert!(report.duplicates.is_empty() && report.problems.is_empty());
+    }
+
+    #[test]
+    fn parent_hash_must_link() {
+        let mut headers = chain_of(100, 3);
+        headers[2].parent_hash = Some(B256::ZERO);
+        let report = validate(headers);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].starts_with("block 102: parentHash"), "{:?}", report.problems);
+    }
+
+    #[test]
+    fn timestamp_must_increase() {
+        let mut headers = chain_of(100, 2);
+        headers[1].timestamp = headers[0].timestamp;
+        let report = validate(headers);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].contains("timestamp"), "{:?}", report.problems);
+    }
+
+    #[test]
+    fn header_hash_is_checked() {
+        let genesis = decode_block(&block(&genesis_header(), &[], &[])).unwrap();
+        let info = HeaderInfo::from_block(&genesis, "0.rlp".to_string()).unwrap();
+        assert_eq!(info.computed_hash, Ok(GENESIS_HASH));
+        assert!(validate(vec![info.clone()]).is_clean());
+
+        let mut tampered = info;
+        tampered.claimed_hash = Some(B256::ZERO);
+        let report = validate(vec![tampered]);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].contains("but header hashes to"), "{:?}", report.problems);
+    }
+}
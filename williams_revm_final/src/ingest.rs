@@ -0,0 +1,314 @@
+// Streaming block ingestion
+//
+// Loading a block (reading its file, parsing JSON, recovering senders) costs
+// more than executing it. A `ReadAhead` moves that work onto reader threads
+// that run ahead of the consumer: each reader takes the next item from the
+// source, loads it and parks the result, and the consumer receives results in
+// source order. Readers never get more than `depth` items ahead of the
+// consumer, so memory stays bounded however large the dataset is.
+//
+// Whenever the consumer asks for the next block and it is not loaded yet, it
+// waits; that wait is the I/O stall, the part of loading the readers did not
+// hide. It is measured separately so disk and parsing speed stay out of the
+// execution figures.
+//
+// A load that panics does not leave the consumer waiting for a result that
+// never comes: the panic is parked in its place and resumed on the consumer
+// when it reaches that item.
+
+use std::collections::BTreeMap;
+use std::iter::Fuse;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// How far and with how many threads to read ahead
+#[derive(Debug, Clone, Copy)]
+pub struct ReadAheadConfig {
+    pub readers: usize,
+    /// Items loaded or being loaded ahead of the consumer, at most
+    pub depth: usize,
+}
+
+impl Default for ReadAheadConfig {
+    fn default() -> Self {
+        Self { readers: 2, depth: 16 }
+    }
+}
+
+/// What reading ahead achieved
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ReadAheadStats {
+    pub items: usize,
+    /// Time readers spent loading, summed over readers
+    pub load_us: u128,
+    /// Time the consumer waited for the next item, and how often it had to
+    pub stall_us: u128,
+    pub stalls: usize,
+    /// Items already loaded when the consumer asked, summed over requests
+    pub ready_sum: usize,
+}
+
+impl ReadAheadStats {
+    /// Fold in the stats of another queue, e.g. of a run before a restart
+    pub fn add(&mut self, other: &ReadAheadStats) {
+        self.items += other.items;
+        self.load_us += other.load_us;
+        self.stall_us += other.stall_us;
+        self.stalls += other.stalls;
+        self.ready_sum += other.ready_sum;
+    }
+
+    /// Items ready on average when the consumer asked for the next one
+    pub fn average_ready(&self) -> f64 {
+        self.ready_sum as f64 / self.items.max(1) as f64
+    }
+}
+
+struct State<T> {
+    /// Sequence numbers handed to readers, and results the consumer took
+    reserved: usize,
+    consumed: usize,
+    /// Loaded results by sequence number, or the panic of a failed load
+    parked: BTreeMap<usize, thread::Result<T>>,
+    /// Number of items in the source, once a reader found its end
+    total: Option<usize>,
+    /// The consumer is gone; readers stop
+    closed: bool,
+    load_us: u128,
+}
+
+struct Shared<I: Iterator, T> {
+    /// The source and the sequence number of its next item
+    source: Mutex<(usize, Fuse<I>)>,
+    state: Mutex<State<T>>,
+    /// Signalled when a result is parked or the source ends
+    ready: Condvar,
+    /// Signalled when the consumer takes a result or goes away
+    space: Condvar,
+    depth: usize,
+}
+
+impl<I: Iterator, T> Shared<I, T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Results of `load` over `source`, in source order, loaded ahead on reader threads
+pub struct ReadAhead<I: Iterator, T> {
+    shared: Arc<Shared<I, T>>,
+    readers: Vec<JoinHandle<()>>,
+    next: usize,
+    stats: ReadAheadStats,
+}
+
+impl<I, T> ReadAhead<I, T>
+where
+    I: Iterator + Send + 'static,
+    T: Send + 'static,
+{
+    pub fn spawn<L>(source: I, config: ReadAheadConfig, load: L) -> Self
+    where
+        L: Fn(I::Item) -> T + Send + Sync + 'static,
+    {
+        let shared = Arc::new(Shared {
+            source: Mutex::new((0, source.fuse())),
+            state: Mutex::new(State {
+                reserved: 0,
+                consumed: 0,
+                parked: BTreeMap::new(),
+                total: None,
+                closed: false,
+                load_us: 0,
+            }),
+            ready: Condvar::new(),
+            space: Condvar::new(),
+            depth: config.depth.max(1),
+        });
+        let load = Arc::new(load);
+        let readers = (0..config.readers.max(1))
+            .map(|_| {
+                let (shared, load) = (Arc::clone(&shared), Arc::clone(&load));
+                thread::spawn(move || read(&shared, &*load))
+            })
+            .collect();
+        Self { shared, readers, next: 0, stats: ReadAheadStats::default() }
+    }
+}
+
+impl<I: Iterator, T> ReadAhead<I, T> {
+    pub fn stats(&self) -> ReadAheadStats {
+        ReadAheadStats { load_us: self.shared.lock().load_us, ..self.stats }
+    }
+}
+
+/// Reader thread: reserve a slot in the window, take an item, load it, park it
+fn read<I: Iterator, T>(shared: &Shared<I, T>, load: &(dyn Fn(I::Item) -> T + Sync)) {
+    loop {
+        {
+            let mut state = shared.lock();
+            while !state.closed && state.total.is_none() && state.reserved - state.consumed >= shared.depth {
+                state = shared.space.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+            if state.closed || state.total.is_some() {
+                return;
+            }
+            state.reserved += 1;
+        }
+
+        let taken = {
+            let mut source = shared.source.lock().unwrap_or_else(|e| e.into_inner());
+            let sequence = source.0;
+            match source.1.next() {
+                Some(item) => {
+                    source.0 += 1;
+                    Ok((sequence, item))
+                }
+                None => Err(sequence),
+            }
+        };
+
+        match taken {
+            Ok((sequence, item)) => {
+                let start = Instant::now();
+                let result = panic::catch_unwind(AssertUnwindSafe(|| load(item)));
+                let mut state = shared.lock();
+                state.load_us += start.elapsed().as_micros();
+                state.parked.insert(sequence, result);
+                shared.ready.notify_all();
+            }
+            Err(total) => {
+                let mut state = shared.lock();
+                state.reserved -= 1;
+                state.total = Some(total);
+                shared.ready.notify_all();
+                shared.space.notify_all();
+                return;
+            }
+        }
+    }
+}
+
+impl<I: Iterator, T> Iterator for ReadAhead<I, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let shared = &self.shared;
+        let mut state = shared.lock();
+        let mut waiting_since = None;
+        loop {
+            if let Some(result) = state.parked.remove(&self.next) {
+                self.stats.ready_sum += state.parked.len() + 1;
+                state.consumed += 1;
+                shared.space.notify_all();
+                drop(state);
+
+                let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+                if let Some(since) = waiting_since {
+                    self.stats.stall_us += Instant::now().duration_since(since).as_micros();
+                    self.stats.stalls += 1;
+                }
+                self.stats.items += 1;
+                self.next += 1;
+                return Some(result);
+            }
+            if state.total.is_some_and(|total| self.next >= total) {
+                return None;
+            }
+            waiting_since.get_or_insert_with(Instant::now);
+            state = shared.ready.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl<I: Iterator, T> Drop for ReadAhead<I, T> {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.space.notify_all();
+        for reader in self.readers.drain(..) {
+            let _ = reader.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn config(readers: usize, depth: usize) -> ReadAheadConfig {
+        ReadAheadConfig { readers, depth }
+    }
+
+    #[test]
+    fn delivers_in_source_order() {
+        // Later items load faster, so readers finish out of order
+        let queue = ReadAhead::spawn(0..64u64, config(4, 8), |i| {
+            thread::sleep(Duration::from_micros((64 - i) * 20));
+            i
+        });
+        assert_eq!(queue.collect::<Vec<_>>(), (0..64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stays_within_depth() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&started);
+        let mut queue = ReadAhead::spawn(0..40, config(4, 5), move |i| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            i
+        });
+        for consumed in 1..=40 {
+            assert!(queue.next().is_some());
+            // Give the readers time to fill the window as far as they may
+            thread::sleep(Duration::from_millis(2));
+            let ahead = started.load(Ordering::SeqCst) - consumed;
+            assert!(ahead <= 5, "{} items loaded ahead of the consumer", ahead);
+        }
+        assert_eq!(queue.stats().items, 40);
+    }
+
+    #[test]
+    fn ends_with_the_source() {
+        let mut empty = ReadAhead::spawn(std::iter::empty::<u32>(), config(3, 4), |i| i);
+        assert_eq!(empty.next(), None);
+
+        let mut queue = ReadAhead::spawn(0..3, config(3, 4), |i| i * 2);
+        assert_eq!(queue.by_ref().collect::<Vec<_>>(), vec![0, 2, 4]);
+        assert_eq!(queue.next(), None);
+    }
+
+    #[test]
+    fn dropping_the_consumer_stops_the_readers() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&started);
+        let mut queue = ReadAhead::spawn(0.., config(4, 6), move |i: u64| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            i
+        });
+        assert_eq!(queue.next(), Some(0));
+        assert_eq!(queue.next(), Some(1));
+        // Joins the readers; an endless source would hang here if they kept going
+        drop(queue);
+        assert!(started.load(Ordering::SeqCst) <= 2 + 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "load failed")]
+    fn panicking_load_reaches_the_consumer() {
+        let queue = ReadAhead::spawn(0..10, config(2, 4), |i| {
+            if i == 3 {
+                panic!("load failed");
+            }
+            i
+        });
+        let loaded: Vec<_> = queue.collect();
+        unreachable!("consumer received {:?} past a failed load", loaded);
+    }
+}
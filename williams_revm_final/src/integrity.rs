@@ -0,0 +1,224 @@
+// Dataset integrity: transaction hashes and the transactions root
+//
+// Each transaction is re-encoded to its canonical form (legacy RLP list or
+// EIP-2718 typed envelope) and hashed; the result must equal its JSON
+// `hash`. The ordered trie over the same encodings must equal the header's
+// `transactionsRoot`. A block failing either check was corrupted somewhere
+// between the node and the dataset directory.
+
+use alloy_trie::root::ordered_trie_root_with_encoder;
+use serde_json::Value;
+
+use revm::primitives::{keccak256, B256};
+
+use crate::json_hex::field_b256;
+use crate::tx_rlp::encode_signed;
+
+/// A transaction whose recomputed hash differs from its JSON `hash`
+#[derive(Debug, Clone)]
+pub struct TxHashMismatch {
+    pub tx_index: usize,
+    pub claimed: B256,
+    pub computed: B256,
+}
+
+/// Integrity findings for one block
+#[derive(Debug, Clone, Default)]
+pub struct BlockIntegrity {
+    pub tx_count: usize,
+    pub hash_mismatches: Vec<TxHashMismatch>,
+    /// Transactions that could not be re-encoded (missing fields, hashes only)
+    pub encode_failures: Vec<(usize, String)>,
+    /// Transactions without a `hash` to check against
+    pub missing_hashes: Vec<usize>,
+    /// Header `transactionsRoot` and the recomputed root, when they differ
+    pub root_mismatch: Option<(B256, B256)>,
+    /// Header has no `transactionsRoot` to compare against
+    pub missing_root: bool,
+}
+
+impl BlockIntegrity {
+    pub fn is_corrupted(&self) -> bool {
+        !self.hash_mismatches.is_empty()
+            || !self.encode_failures.is_empty()
+            || !self.missing_hashes.is_empty()
+            || self.root_mismatch.is_some()
+    }
+}
+
+/// Re-encode, hash and root-check every transaction of a block
+pub fn verify_block_integrity(block: &Value) -> BlockIntegrity {
+    let mut integrity = BlockIntegrity::default();
+    let txs = block.get("transactions")
+        .and_then(|t| t.as_array())
+        .map(|t| t.as_slice())
+        .unwrap_or_default();
+    integrity.tx_count = txs.len();
+
+    let mut encoded = Vec::with_capacity(txs.len());
+    for (tx_index, tx) in txs.iter().enumerate() {
+        let bytes = match encode_signed(tx) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                integrity.encode_failures.push((tx_index, e.to_string()));
+                continue;
+            }
+        };
+
+        let computed = keccak256(&bytes);
+        match field_b256(tx, "hash") {
+            Some(claimed) if claimed != computed => {
+                integrity.hash_mismatches.push(TxHashMismatch { tx_index, claimed, computed });
+            }
+            Some(_) => {}
+            None => integrity.missing_hashes.push(tx_index),
+        }
+        encoded.push(bytes);
+    }
+
+    // A root over a partial list would mismatch anyway; only compare when
+    // every transaction could be encoded
+    if encoded.len() == txs.len() {
+        match field_b256(block, "transactionsRoot") {
+            Some(header_root) => {
+                let computed = ordered_trie_root_with_encoder(&encoded, |tx, buf| buf.extend_from_slice(tx));
+                if computed != header_root {
+                    integrity.root_mismatch = Some((header_root, computed));
+                }
+            }
+            None => integrity.missing_root = true,
+        }
+    }
+
+    integrity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloy_trie::EMPTY_ROOT_HASH;
+    use serde_json::json;
+
+    use crate::tx_rlp::tests::{eip155_example, first_mainnet_transaction, sign, typed_transactions};
+    use crate::tx_rlp::RlpList;
+
+    /// Leaf or branch child: embedded below 32 bytes, hashed otherwise
+    fn child(node: Vec<u8>) -> Vec<u8> {
+        if node.len() < 32 {
+            return node;
+        }
+        alloy_rlp::encode(keccak256(&node))
+    }
+
+    fn leaf(path: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut node = RlpList::default();
+        node.push(path);
+        node.push(value);
+        node.finish()
+    }
+
+    fn block(txs: Vec<Value>, root: B256) -> Value {
+        json!({ "transactionsRoot": root, "transactions": txs })
+    }
+
+    fn with_hash(mut tx: Value) -> Value {
+        tx["hash"] = json!(keccak256(encode_signed(&tx).unwrap()));
+        tx
+    }
+
+    #[test]
+    fn canonical_encodings_hash_to_known_values() {
+        // Signed form given in EIP-155
+        assert_eq!(
+            hex::encode(encode_signed(&eip155_example()).unwrap()),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a0\
+             28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b\
+             3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        // First transaction on mainnet, block 46147
+        assert_eq!(
+            keccak256(encode_signed(&first_mainnet_transaction()).unwrap()),
+            "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060".parse::<B256>().unwrap()
+        );
+        // Typed transactions are `type || rlp(fields)`
+        for tx in typed_transactions() {
+            let ty = tx["type"].as_str().unwrap().to_string();
+            let encoded = encode_signed(&sign(tx)).unwrap();
+            assert_eq!(format!("{:#x}", encoded[0]), ty);
+            assert!(encoded[1] >= 0xf8, "type {} payload is not a long list", ty);
+        }
+    }
+
+    #[test]
+    fn transactions_root_matches_the_trie_built_by_hand() {
+        assert!(!verify_block_integrity(&block(Vec::new(), EMPTY_ROOT_HASH)).is_corrupted());
+
+        // One transaction: a single leaf at key rlp(0) = 0x80, nibbles 8 0
+        let tx = with_hash(first_mainnet_transaction());
+        let root = keccak256(leaf(&[0x20, 0x80], &encode_signed(&tx).unwrap()));
+        assert!(!verify_block_integrity(&block(vec![tx.clone()], root)).is_corrupted());
+
+        // Every type: keys 0x80, 0x01, 0x02, … share no first nibble with
+        // index 0, so the root is a branch over nibbles 0 and 8, with a
+        // second branch under 0 for indices 1 to 4
+        let mut txs = vec![tx];
+        txs.extend(typed_transactions().into_iter().map(|tx| with_hash(sign(tx))));
+        let encoded: Vec<Vec<u8>> = txs.iter().map(|tx| encode_signed(tx).unwrap()).collect();
+
+        let branch = |children: &[(usize, Vec<u8>)]| {
+            let mut node = RlpList::default();
+            for nibble in 0..16 {
+                match children.iter().find(|(at, _)| *at == nibble) {
+                    Some((_, child)) => node.push_raw(child),
+                    None => node.push_raw(&[alloy_rlp::EMPTY_STRING_CODE]),
+                }
+            }
+            node.push_raw(&[alloy_rlp::EMPTY_STRING_CODE]);
+            node.finish()
+        };
+        let low: Vec<(usize, Vec<u8>)> = (1..encoded.len())
+            .map(|index| (index, child(leaf(&[0x20], &encoded[index]))))
+            .collect();
+        let root = keccak256(branch(&[
+            (0, child(branch(&low))),
+            (8, child(leaf(&[0x30], &encoded[0]))),
+        ]));
+        let integrity = verify_block_integrity(&block(txs.clone(), root));
+        assert!(!integrity.is_corrupted(), "{:?}", integrity);
+        assert_eq!(integrity.tx_count, 5);
+
+        // Transactions out of order: every hash still matches, the root does not
+        txs.swap(1, 2);
+        let integrity = verify_block_integrity(&block(txs, root));
+        assert!(integrity.hash_mismatches.is_empty());
+        assert!(integrity.root_mismatch.is_some());
+    }
+
+    #[test]
+    fn tampered_transactions_are_reported() {
+        let mut tx = with_hash(eip155_example());
+        let root = keccak256(leaf(&[0x20, 0x80], &encode_signed(&tx).unwrap()));
+        tx["value"] = json!("0xde0b6b3a7640001");
+
+        let integrity = verify_block_integrity(&block(vec![tx.clone()], root));
+        assert_eq!(integrity.hash_mismatches.len(), 1);
+        assert!(integrity.root_mismatch.is_some());
+
+        tx.as_object_mut().unwrap().remove("gas");
+        let integrity = verify_block_integrity(&block(vec![tx], root));
+        assert_eq!(integrity.encode_failures.len(), 1);
+        assert!(integrity.root_mismatch.is_none());
+    }
+
+    #[test]
+    fn missing_hash_is_not_an_encode_failure() {
+        let tx = eip155_example();
+        let root = keccak256(leaf(&[0x20, 0x80], &encode_signed(&tx).unwrap()));
+        let integrity = verify_block_integrity(&block(vec![tx], root));
+        assert_eq!(integrity.missing_hashes, vec![0]);
+        assert!(integrity.encode_failures.is_empty());
+        assert!(integrity.root_mismatch.is_none());
+        assert!(integrity.is_corrupted());
+    }
+}
@@ -0,0 +1,50 @@
+// Helpers for reading 0x-prefixed hex quantities out of JSON-RPC objects
+//
+// Every field is optional: a missing or malformed value yields `None` and
+// the caller decides whether that is an error, a default or a report entry.
+
+use serde_json::Value;
+
+use revm::primitives::{Address, Bytes, B256, U256};
+
+/// Raw hex digits of a string field, without the 0x prefix
+fn hex_str<'a>(obj: &'a Value, key: &str) -> Option<&'a str> {
+    obj.get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim_start_matches("0x"))
+}
+
+/// Quantity field as u64
+pub fn field_u64(obj: &Value, key: &str) -> Option<u64> {
+    let digits = hex_str(obj, key)?;
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// Quantity field as U256
+pub fn field_u256(obj: &Value, key: &str) -> Option<U256> {
+    let digits = hex_str(obj, key)?;
+    if digits.is_empty() {
+        return Some(U256::ZERO);
+    }
+    U256::from_str_radix(digits, 16).ok()
+}
+
+/// 32-byte hash field
+pub fn field_b256(obj: &Value, key: &str) -> Option<B256> {
+    let bytes = hex::decode(hex_str(obj, key)?).ok()?;
+    (bytes.len() == 32).then(|| B256::from_slice(&bytes))
+}
+
+/// 20-byte address field
+pub fn field_address(obj: &Value, key: &str) -> Option<Address> {
+    let bytes = hex::decode(hex_str(obj, key)?).ok()?;
+    (bytes.len() == 20).then(|| Address::from_slice(&bytes))
+}
+
+/// Arbitrary byte string field
+pub fn field_bytes(obj: &Value, key: &str) -> Option<Bytes> {
+    hex::decode(hex_str(obj, key)?).ok().map(Bytes::from)
+}
@@ -0,0 +1,47 @@
+// Williams Hybrid Executor library
+//
+// Everything behind the `williams-benchmark` CLI: datasets and block
+// sources, block preparation, pre-block state, and the block executors.
+// Executors run in-memory blocks on top of any state database:
+//
+//     let block = prepare_block(&json, None, &chain, false)?;
+//     let mut executor = WilliamsExecutor::new(chain.clone(), 16);
+//     let execution = executor.execute(&block, &state)?;
+//
+// where `state` is a `revm::DatabaseRef + Send + Sync` holding the state
+// before the block. `SequentialExecutor`, `NaiveParallelExecutor` and
+// `OptimisticExecutor` implement the same `BlockExecutor` trait.
+
+pub mod access;
+pub mod block_cache;
+pub mod block_source;
+pub mod chain_config;
+pub mod code_cache;
+pub mod download;
+pub mod era1;
+pub mod executor;
+pub mod finalize;
+pub mod header;
+pub mod header_chain;
+pub mod ingest;
+pub mod integrity;
+pub mod json_hex;
+pub mod manifest;
+pub mod overlay;
+pub mod policy;
+pub mod prefetch;
+pub mod prepare;
+pub mod prestate;
+pub mod raw_block;
+pub mod schedule;
+pub mod sender;
+pub mod system_calls;
+pub mod tx_rlp;
+
+pub use block_cache::{PreparedBlock, PreparedTx};
+pub use chain_config::ChainConfig;
+pub use executor::{
+    BlockExecution, BlockExecutor, NaiveParallelExecutor, OptimisticExecutor, SequentialExecutor, StageTimings,
+    WilliamsExecutor,
+};
+pub use prepare::prepare_block;
@@ -18,177 +18,503 @@
 // See LICENSE.md for full terms.
 // For licensing inquiries after bounty payment, contact via GitHub.
 
+mod checkpoint;
+mod classify;
+mod cli;
+mod compare;
+mod evm_bench;
+mod selection;
+mod trace;
+
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::collections::HashMap;
 
-use serde_json::Value;
 use rayon::prelude::*;
 use anyhow::{Result, Context, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use revm::primitives::U256;
 
-use revm::{
-    primitives::{
-        Address, U256, Bytes, TransactTo, TxEnv, 
-        BlockEnv, CfgEnvWithHandlerCfg, SpecId, B256,
-    },
-    db::{CacheDB, EmptyDB},
-    Evm,
+use williams_executor::block_cache::{self, BlockCache, BlockCacheWriter, CacheEntry, PrepareTimings, PreparedBlock};
+use williams_executor::block_source::{load_block, load_block_timed, BlockRef, Dataset, DatasetSummary};
+use williams_executor::chain_config::{self, ChainConfig};
+use williams_executor::code_cache::CodeCacheStats;
+use williams_executor::download::{download, rpc_host, DownloadConfig, DownloadSummary};
+use williams_executor::executor::{
+    BlockExecution, BlockExecutor, NaiveParallelExecutor, OptimisticExecutor, SequentialExecutor, StageTimings,
+    WilliamsExecutor,
 };
+use williams_executor::finalize::FinalizeSummary;
+use williams_executor::ingest::{ReadAhead, ReadAheadConfig, ReadAheadStats};
+use williams_executor::header_chain::{validate_header_chain, ChainReport, HeaderInfo};
+use williams_executor::integrity::{verify_block_integrity, BlockIntegrity};
+use williams_executor::json_hex;
+use williams_executor::manifest::{self, BlockEntry, Manifest};
+use williams_executor::policy::{Decision, POLICY_LOG_FILE};
+use williams_executor::prefetch::{AccessPlan, PrefetchSummary};
+use williams_executor::prepare::prepare_block;
+use williams_executor::prestate::{self, PrestateDB, StateSource};
+use williams_executor::schedule::{GasProfile, ScheduleSummary, GAS_PROFILE_FILE};
+use williams_executor::sender::SenderReport;
+use williams_executor::system_calls::SystemCallSummary;
+
+use checkpoint::Checkpoints;
+use cli::{Command, ReportFormat, RunOptions, EXECUTORS, USAGE};
+use selection::BlockSelection;
 
 /// Block execution result
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct BlockResult {
     block_number: u64,
     tx_count: usize,
     deterministic_count: usize,
     execution_time_us: u128,
+    missing_header_fields: Vec<String>,
+    finalize: FinalizeSummary,
+    system_calls: SystemCallSummary,
+    senders: SenderReport,
+    sender_recovery_us: u128,
+    prefetch: PrefetchSummary,
+    schedule: ScheduleSummary,
+    /// Strategy the policy chose, and the conflict rate the block then had
+    decision: Option<Decision>,
+    observed_conflicts: f64,
+    /// Transactions executed again after failing optimistic validation
+    reexecuted: usize,
+    gas_used: u64,
+    stages: StageTimings,
+    prepare: PrepareTimings,
 }
 
-/// Transaction classification
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum TxType {
-    Deterministic,
-    NonDeterministic,
-}
-
-/// OPTIMIZATION 1: Shared state cache for bulk prefetching
-/// Reduces per-thread clone overhead by sharing prefetched state
-#[derive(Clone)]
-struct SharedStateCache {
-    accounts: Arc<HashMap<Address, (U256, u64)>>,  // (balance, nonce)
+/// Microseconds per phase of loading and executing blocks
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+struct PhaseTimings {
+    read_us: u128,
+    parse_us: u128,
+    sender_recovery_us: u128,
+    classify_us: u128,
+    pool_creation_us: u128,
+    system_calls_us: u128,
+    prefetch_us: u128,
+    transactions_us: u128,
+    /// Part of `transactions_us`
+    commit_us: u128,
+    finalize_us: u128,
 }
 
-impl SharedStateCache {
-    fn new() -> Self {
+impl PhaseTimings {
+    fn of(result: &BlockResult) -> Self {
         Self {
-            accounts: Arc::new(HashMap::new()),
+            read_us: result.prepare.read_us,
+            parse_us: result.prepare.parse_us,
+            sender_recovery_us: result.sender_recovery_us,
+            classify_us: result.prepare.classify_us,
+            pool_creation_us: result.stages.pool_us,
+            system_calls_us: result.stages.system_calls_us,
+            prefetch_us: result.stages.prefetch_us,
+            transactions_us: result.stages.transactions_us,
+            commit_us: result.stages.commit_us,
+            finalize_us: result.stages.finalize_us,
         }
     }
     
-    fn with_data(accounts: HashMap<Address, (U256, u64)>) -> Self {
-        Self {
-            accounts: Arc::new(accounts),
+    fn total(results: &[BlockResult]) -> Self {
+        let mut total = Self::default();
+        for phases in results.iter().map(Self::of) {
+            total.read_us += phases.read_us;
+            total.parse_us += phases.parse_us;
+            total.sender_recovery_us += phases.sender_recovery_us;
+            total.classify_us += phases.classify_us;
+            total.pool_creation_us += phases.pool_creation_us;
+            total.system_calls_us += phases.system_calls_us;
+            total.prefetch_us += phases.prefetch_us;
+            total.transactions_us += phases.transactions_us;
+            total.commit_us += phases.commit_us;
+            total.finalize_us += phases.finalize_us;
         }
+        total
     }
-    
-    fn get_account(&self, addr: &Address) -> Option<(U256, u64)> {
-        self.accounts.get(addr).copied()
-    }
 }
 
-/// OPTIMIZATION 2: Sharded state tracking to reduce lock contention
-/// Splits state tracking across multiple shards (locks) for better parallelism
-const SHARD_COUNT: usize = 16;
+/// Run-wide measurements that belong to no single block
+struct RunTimings {
+    wallclock_us: u128,
+    /// Building the executor: its thread pools and gas profile
+    setup_us: u128,
+    ingestion: ReadAheadStats,
+}
+
+/// Executors the CLI runs blocks with, on top of their prestate
+type Executor = Box<dyn BlockExecutor<Arc<PrestateDB>>>;
 
-struct ShardedStateTracker {
-    shards: Vec<Mutex<HashMap<Address, u64>>>,  // Track access counts per shard
+/// Cache written by `convert` inside the data directory
+const BLOCK_CACHE_FILE: &str = "blocks.cache";
+
+/// Blocks loaded and decoded in parallel per batch by checks and convert
+const BLOCK_BATCH_SIZE: usize = 256;
+
+/// Results in SupraBTM format, and the same as JSON with `--format json`
+const RESULTS_FILE: &str = "williams_execution_time.txt";
+const RESULTS_JSON_FILE: &str = "williams_execution_time.json";
+
+const RUN_INFO_FILE: &str = "williams_run_info.txt";
+
+/// Where a command's blocks come from
+struct RunInputs<'a> {
+    dataset: &'a Dataset,
+    chain: &'a ChainConfig,
+    /// Pre-decoded blocks, used instead of the dataset files when present
+    cache: Option<&'a Arc<BlockCache>>,
+    state: &'a StateSource,
+    /// SHA-256 of the dataset manifest
+    manifest: Option<&'a str>,
+}
+
+/// A block still to be loaded, from the cache or from the dataset
+enum PendingBlock {
+    Cached(Arc<BlockCache>, CacheEntry),
+    File(Arc<BlockPreparer>, Result<BlockRef>),
 }
 
-impl ShardedStateTracker {
-    fn new() -> Self {
-        let mut shards = Vec::with_capacity(SHARD_COUNT);
-        for _ in 0..SHARD_COUNT {
-            shards.push(Mutex::new(HashMap::new()));
+impl PendingBlock {
+    /// `None` for blocks that turn out not to be selected once decoded
+    fn load(self) -> Option<Result<PreparedBlock>> {
+        match self {
+            PendingBlock::Cached(cache, entry) => {
+                let start = Instant::now();
+                let block = cache.block(&entry).map(|mut block| {
+                    block.timings.read_us = start.elapsed().as_micros();
+                    block
+                });
+                Some(block.with_context(|| format!("Block {}", entry.number)))
+            }
+            PendingBlock::File(preparer, block_ref) => match block_ref {
+                Ok(block_ref) => preparer.prepare(block_ref),
+                Err(e) => Some(Err(e)),
+            },
         }
-        Self { shards }
     }
-    
-    fn get_shard_index(&self, addr: &Address) -> usize {
-        // Hash address to shard index for even distribution
-        let addr_bytes = addr.as_slice();
-        let hash = addr_bytes[0] as usize;
-        hash % SHARD_COUNT
-    }
-    
-    fn record_access(&self, addr: Address) {
-        let shard_idx = self.get_shard_index(&addr);
-        if let Ok(mut shard) = self.shards[shard_idx].lock() {
-            *shard.entry(addr).or_insert(0) += 1;
+}
+
+/// Decodes dataset blocks, with sender recovery on its own pool
+struct BlockPreparer {
+    chain: ChainConfig,
+    verify_senders: bool,
+    selection: BlockSelection,
+    pool: rayon::ThreadPool,
+}
+
+impl BlockPreparer {
+    fn prepare(&self, block_ref: BlockRef) -> Option<Result<PreparedBlock>> {
+        let block = load_block_timed(&block_ref).and_then(|(json, load)| {
+            let mut block = self.pool.install(|| prepare_block(&json, block_ref.number, &self.chain, self.verify_senders))
+                .with_context(|| block_ref.name.clone())?;
+            block.timings.read_us = load.read_us;
+            block.timings.parse_us += load.parse_us;
+            Ok(block)
+        });
+        // Blocks without a number in their name are only known once decoded
+        match block {
+            Ok(block) if !self.selection.contains(block.number) => None,
+            block => Some(block),
         }
     }
+}
+
+type PendingBlocks = Box<dyn Iterator<Item = PendingBlock> + Send>;
+
+/// Blocks in dataset order, loaded ahead of the caller on reader threads
+struct BlockFeed {
+    /// Block count, when known before reading
+    known: Option<usize>,
+    queue: ReadAhead<PendingBlocks, Option<Result<PreparedBlock>>>,
+}
+
+impl BlockFeed {
+    fn stats(&self) -> ReadAheadStats {
+        self.queue.stats()
+    }
+}
+
+impl Iterator for BlockFeed {
+    type Item = Result<PreparedBlock>;
     
-    fn get_access_count(&self, addr: &Address) -> u64 {
-        let shard_idx = self.get_shard_index(addr);
-        if let Ok(shard) = self.shards[shard_idx].lock() {
-            shard.get(addr).copied().unwrap_or(0)
-        } else {
-            0
-        }
+    fn next(&mut self) -> Option<Self::Item> {
+        self.queue.by_ref().flatten().next()
     }
 }
 
 fn main() -> Result<()> {
+    let mut options = RunOptions::from_args()?;
+    if options.command == Command::Help {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+    
     println!("Williams Hybrid Executor - 100% REAL EVM Execution");
     println!("{}", "=".repeat(70));
     println!("ALL transactions executed with REVM");
     println!("Parallel execution using Rayon (real, not simulated)");
     println!();
     
-    let data_dir = std::env::args()
-        .nth(1)
-        .unwrap_or_else(|| "./data_bdf".to_string());
+    let mut dataset = Dataset::locate(&options.data_dir);
     
-    let thread_count_arg = std::env::args().nth(2);
+    let chain = ChainConfig::discover(&dataset.root)?;
     
-    let blocks_dir = format!("{}/blocks", data_dir);
+    match options.command {
+        Command::Download => return download_dataset(&mut dataset, &chain, &options.download),
+        Command::EvmBench => return evm_bench::run(&chain, options.iterations),
+        Command::Manifest => {
+            let source = options.source.clone().unwrap_or_else(|| "unknown".to_string());
+            return create_manifest(&mut dataset, &chain, source);
+        }
+        Command::Verify => {
+            options.check_headers = true;
+            options.verify_integrity = true;
+        }
+        _ => {}
+    }
+    fs::create_dir_all(&options.output_dir)
+        .with_context(|| format!("Failed to create {}", options.output_dir.display()))?;
     
-    println!("Loading blocks from: {}", blocks_dir);
+    println!("Loading blocks from: {}", dataset.blocks.display());
+    println!("Chain: {} (chain id {})", chain.name, chain.chain_id);
+    if !options.selection.is_all() {
+        println!("Blocks: {}", options.selection);
+    }
+    if options.verify_senders {
+        println!("Sender verification: recovering all senders from signatures");
+    }
+    
+    let state = StateSource::discover(&dataset.root, options.use_prestate);
+    match state.prestate_dir() {
+        Some(dir) => println!("State: prestate traces from {}", dir.display()),
+        None => println!("State: empty (no prestate traces, every account starts empty)"),
+    }
+    
+    let manifest = verify_manifest(&dataset, &chain)?;
+    println!();
     
-    // Check if specific thread count requested
-    if let Some(tc) = thread_count_arg {
-        let thread_count: usize = tc.parse()
-            .context("Thread count must be a positive integer (4, 8, or 16)")?;
-        if ![4, 8, 16].contains(&thread_count) {
-            bail!("Thread count must be 4, 8, or 16 (bounty requirement)");
+    if options.command == Command::Verify {
+        report_dataset_summary(&dataset.discover()?);
+        if !run_dataset_checks(&dataset, &chain, &options)? {
+            bail!("Dataset failed verification");
         }
-        println!("Running with {} threads (bounty configuration)", thread_count);
+        println!("✓ Dataset verified");
+        return Ok(());
+    }
+    
+    let cache_path = dataset.root.join(BLOCK_CACHE_FILE);
+    if options.command == Command::Convert {
+        if !options.selection.is_all() {
+            println!("Note: block selection ignored, the cache always holds the whole dataset");
+        }
+        report_dataset_summary(&dataset.discover()?);
+        run_dataset_checks(&dataset, &chain, &options)?;
+        return convert_dataset(&dataset, &cache_path, &chain, &options, manifest.as_deref());
+    }
+    
+    let cache = if options.use_cache && cache_path.exists() {
+        let cache = open_block_cache(&cache_path, &chain, &options, manifest.as_deref())?;
+        println!("Using block cache: {} ({} blocks, dataset checks ran at convert time)",
+            cache_path.display(), cache.entries.len()
+        );
+        println!();
+        Some(Arc::new(cache))
+    } else {
+        report_dataset_summary(&dataset.discover()?);
+        run_dataset_checks(&dataset, &chain, &options)?;
+        None
+    };
+    
+    let inputs = RunInputs {
+        dataset: &dataset,
+        chain: &chain,
+        cache: cache.as_ref(),
+        state: &state,
+        manifest: manifest.as_deref(),
+    };
+    
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if let Some(&most) = options.threads.iter().max().filter(|&&most| most > cores) {
+        println!("Note: {} threads requested but only {} cores available", most, cores);
         println!();
-        return run_benchmark_with_threads(&blocks_dir, thread_count);
     }
     
-    // Run all configurations as required by bounty
-    println!("Running multi-configuration benchmark (bounty requirement)");
-    println!("Testing with 4, 8, and 16 threads");
-    println!();
+    match options.command {
+        Command::Classify => return classify::run(&inputs, &options),
+        Command::Trace => return trace::run(&inputs, &options),
+        _ => {}
+    }
     
-    for thread_count in [4, 8, 16] {
-        println!("\n{}", "=".repeat(70));
-        println!("CONFIGURATION: {} THREADS", thread_count);
-        println!("{}", "=".repeat(70));
-        run_benchmark_with_threads(&blocks_dir, thread_count)?;
+    println!("Run parameters saved to {}", options.write_config()?.display());
+    if options.command == Command::Compare {
+        return compare::run(&inputs, &options);
     }
     
+    let mut checkpoints = match options.checkpoint_every {
+        0 => None,
+        _ => Some(Checkpoints::open(&options, inputs.manifest)?),
+    };
+    
+    if let [thread_count] = options.threads[..] {
+        println!("Running with {} threads", thread_count);
+        println!();
+        run_benchmark_with_threads(&inputs, &options, thread_count, checkpoints.as_mut())?;
+    } else {
+        let counts: Vec<String> = options.threads.iter().map(usize::to_string).collect();
+        println!("Running multi-configuration benchmark");
+        println!("Testing with {} threads", counts.join(", "));
+        println!();
+        
+        for &thread_count in &options.threads {
+            println!("\n{}", "=".repeat(70));
+            println!("CONFIGURATION: {} THREADS", thread_count);
+            println!("{}", "=".repeat(70));
+            if checkpoints.as_ref().is_some_and(|checkpoints| checkpoints.finished(thread_count)) {
+                println!("Finished before the restart, skipped");
+                continue;
+            }
+            run_benchmark_with_threads(&inputs, &options, thread_count, checkpoints.as_mut())?;
+        }
+    }
+    
+    if let Some(checkpoints) = checkpoints {
+        checkpoints.remove()?;
+    }
     Ok(())
 }
 
-fn run_benchmark_with_threads(blocks_dir: &str, thread_count: usize) -> Result<()> {
-    
-    // Load all block files
-    let mut block_files: Vec<PathBuf> = fs::read_dir(&blocks_dir)
-        .context("Failed to read blocks directory")?
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("json"))
-        .map(|e| e.path())
-        .collect();
+/// The selected blocks of the run, decoded from the cache or the dataset
+/// on reader threads ahead of the caller
+///
+/// Sender recovery for dataset blocks runs on one pool shared by the
+/// readers, as wide as there are readers, so loading ahead never competes
+/// with execution for more cores than it was given.
+fn prepared_blocks(inputs: &RunInputs, options: &RunOptions) -> Result<BlockFeed> {
+    prepared_blocks_from(inputs, options, 0)
+}
+
+/// Selected blocks, leaving out the first `position` dataset entries
+fn prepared_blocks_from(inputs: &RunInputs, options: &RunOptions, position: usize) -> Result<BlockFeed> {
+    let selection = &options.selection;
+    
+    let (known, pending): (_, PendingBlocks) = match inputs.cache {
+        Some(cache) => {
+            let entries: Vec<PendingBlock> = cache.entries.iter()
+                .filter(|entry| selection.contains(entry.number))
+                .map(|entry| PendingBlock::Cached(Arc::clone(cache), *entry))
+                .collect();
+            (Some(entries.len()), Box::new(entries.into_iter()))
+        }
+        None => {
+            let stream = inputs.dataset.open()?;
+            let known = stream.len.filter(|_| selection.is_all());
+            let preparer = Arc::new(BlockPreparer {
+                chain: inputs.chain.clone(),
+                verify_senders: options.verify_senders,
+                selection: selection.clone(),
+                pool: rayon::ThreadPoolBuilder::new()
+                    .num_threads(options.read_ahead.readers)
+                    .build()
+                    .context("Failed to create thread pool for sender recovery")?,
+            });
+            let listed = Arc::clone(&preparer);
+            let pending = stream
+                .filter(move |block_ref| match block_ref {
+                    Ok(BlockRef { number: Some(number), .. }) => listed.selection.contains(*number),
+                    _ => true,
+                })
+                .map(move |block_ref| PendingBlock::File(Arc::clone(&preparer), block_ref));
+            (known, Box::new(pending))
+        }
+    };
     
-    block_files.sort();
+    let pending: PendingBlocks = Box::new(pending.skip(position));
+    let queue = ReadAhead::spawn(pending, options.read_ahead, PendingBlock::load);
+    Ok(BlockFeed { known, queue })
+}
+
+fn run_benchmark_with_threads(
+    inputs: &RunInputs,
+    options: &RunOptions,
+    thread_count: usize,
+    mut checkpoints: Option<&mut Checkpoints>,
+) -> Result<()> {
+    let resumed = match checkpoints.as_deref_mut() {
+        Some(checkpoints) => checkpoints.resume(thread_count)?,
+        None => None,
+    };
+    let position = resumed.as_ref().map_or(0, |resumed| resumed.position);
+    let mut blocks = prepared_blocks_from(inputs, options, position)?;
+    match blocks.known {
+        Some(count) => println!("Found {} blocks", count),
+        None => println!("Streaming blocks from {}", inputs.dataset.blocks.display()),
+    }
     
-    println!("Found {} block files", block_files.len());
-    println!("Starting Williams Hybrid execution with REVM...");
+    // Executors that learn keep what they learned across blocks; the gas
+    // profile also carries over from earlier runs
+    let profile_path = options.output_path(GAS_PROFILE_FILE);
+    let setup_start = Instant::now();
+    let mut executor = build_executor(&options.executor, inputs.chain, thread_count, &profile_path)?;
+    let setup_us = setup_start.elapsed().as_micros();
+    
+    // A resumed run starts with what the interrupted one completed, and
+    // replays what the executor learned from it
+    let mut outcomes: Vec<Result<BlockResult>> = Vec::new();
+    let mut elapsed_us = 0;
+    let mut ingestion = ReadAheadStats::default();
+    if let Some(resumed) = resumed {
+        let conflict_rates: Vec<f64> = resumed.outcomes.iter()
+            .filter_map(|outcome| outcome.as_ref().ok())
+            .map(|r| r.observed_conflicts)
+            .collect();
+        executor.replay_conflicts(&conflict_rates);
+        println!("Resuming after block {} ({} blocks done)",
+            resumed.last_block.map_or("none".to_string(), |number| number.to_string()), resumed.outcomes.len()
+        );
+        outcomes = resumed.outcomes;
+        elapsed_us = resumed.elapsed_us;
+        ingestion = resumed.ingestion;
+    }
+    println!("Starting {} execution with REVM...", executor.name());
     println!();
     
     let start = Instant::now();
     
     // Execute blocks sequentially, with transactions parallelized within each block
-    // This ensures we use exactly thread_count threads as required by bounty
-    let results: Vec<BlockResult> = block_files
-        .iter()
-        .filter_map(|path| execute_block_williams(path, thread_count).ok())
-        .collect();
-    
-    let elapsed = start.elapsed();
+    // This ensures we use exactly thread_count threads
+    while let Some(block) = blocks.next() {
+        let outcome = block.and_then(|block| {
+            execute_prepared_block(&block, inputs.state, &mut executor)
+                .with_context(|| format!("Block {}", block.number))
+        });
+        outcomes.push(outcome);
+        
+        if let Some(checkpoints) = checkpoints.as_deref_mut().filter(|checkpoints| checkpoints.due(outcomes.len())) {
+            if let Some(profile) = executor.gas_profile() {
+                profile.save(&profile_path)?;
+            }
+            let mut stats = blocks.stats();
+            stats.add(&ingestion);
+            checkpoints.save(thread_count, &outcomes, position + stats.items - ingestion.items,
+                elapsed_us + start.elapsed().as_micros(), stats
+            )?;
+        }
+    }
+    ingestion.add(&blocks.stats());
+    let (results, failures): (Vec<_>, Vec<_>) = outcomes.into_iter().partition(|outcome| outcome.is_ok());
+    let results: Vec<BlockResult> = results.into_iter().flatten().collect();
+    
+    let timings = RunTimings {
+        wallclock_us: elapsed_us + start.elapsed().as_micros(),
+        setup_us,
+        ingestion,
+    };
     
     // Calculate statistics
     let total_blocks = results.len();
@@ -208,7 +534,7 @@ fn run_benchmark_with_threads(blocks_dir: &str, thread_count: usize) -> Result<(
     println!("{}", "=".repeat(70));
     println!("WILLIAMS HYBRID EXECUTOR - RESULTS ({} THREADS)", thread_count);
     println!("{}", "=".repeat(70));
-    println!("Thread Configuration:      {} threads (bounty requirement)", thread_count);
+    println!("Thread Configuration:      {} threads", thread_count);
     println!("Blocks processed:          {}", total_blocks);
     println!("Total transactions:        {}", total_txs);
     println!("Deterministic txs:         {} ({:.1}%)", total_det, det_percent);
@@ -221,7 +547,7 @@ fn run_benchmark_with_threads(blocks_dir: &str, thread_count: usize) -> Result<(
     println!("  Total time:              {:.2}ms ({:.2}s)", 
         total_exec_time_ms, total_exec_time_ms / 1000.0
     );
-    println!("  Wallclock time:          {:.2}s", elapsed.as_secs_f64());
+    println!("  Wallclock time:          {:.2}s", timings.wallclock_us as f64 / 1_000_000.0);
     println!("  Throughput:              {:.2} txs/sec", 
         if total_exec_time_ms > 0.0 {
             total_txs as f64 / (total_exec_time_ms / 1000.0)
@@ -229,13 +555,61 @@ fn run_benchmark_with_threads(blocks_dir: &str, thread_count: usize) -> Result<(
             0.0
         }
     );
+    println!("  Gas throughput:          {:.2} Mgas/s", mgas_per_sec(&results));
+    let latency = BlockLatency::of(&results);
+    println!("  Block latency:           p50 {:.3}ms, p95 {:.3}ms, p99 {:.3}ms",
+        latency.p50_us as f64 / 1000.0, latency.p95_us as f64 / 1000.0, latency.p99_us as f64 / 1000.0
+    );
     println!();
     
+    report_failed_blocks(&failures);
+    report_phases(&results, &timings, inputs.cache.is_some());
+    report_ingestion(&timings.ingestion, &options.read_ahead);
+    report_missing_header_fields(&results);
+    report_prefetch(&results);
+    if let Some(stats) = executor.code_cache_stats() {
+        report_code_cache(&stats);
+    }
+    if let Some(profile) = executor.gas_profile() {
+        report_schedule(&results, profile);
+    }
+    report_policy(&results);
+    report_validation(&results);
+    report_finalization(&results);
+    report_sender_recovery(&results);
+    
     // Output results in SupraBTM format
-    println!("Writing results to williams_execution_time.txt...");
-    write_results(&results, "williams_execution_time.txt")?;
+    let results_path = options.output_path(RESULTS_FILE);
+    let run_info_path = options.output_path(RUN_INFO_FILE);
+    println!("Writing results to {} (run info in {})...", results_path.display(), run_info_path.display());
+    write_results(&results, thread_count, &results_path)?;
+    write_run_info(inputs.chain, thread_count, executor.name(), results.len(), inputs.manifest, &run_info_path)?;
+    if options.format == ReportFormat::Json {
+        let json_path = options.output_path(RESULTS_JSON_FILE);
+        println!("JSON results saved to {}", json_path.display());
+        write_results_json(&results, failures.len(), thread_count, &timings, inputs, options, &json_path)?;
+    }
+    if let Some(profile) = executor.gas_profile() {
+        println!("Gas profile saved to {}", profile_path.display());
+        profile.save(&profile_path)?;
+    }
+    if results.iter().any(|r| r.decision.is_some()) {
+        let policy_path = options.output_path(POLICY_LOG_FILE);
+        println!("Policy decisions saved to {}", policy_path.display());
+        write_policy_log(&results, &policy_path)?;
+    }
+    if let Some(checkpoints) = checkpoints {
+        checkpoints.finish(thread_count)?;
+    }
     
     println!();
+    if executor.name() != "williams" {
+        println!("Executor:                  {} ({} threads)", executor.name(), thread_count);
+        println!();
+        println!("✓ Benchmark complete!");
+        println!("✓ Results saved to {}", results_path.display());
+        return Ok(());
+    }
     println!("Williams Hybrid Strategy:");
     println!("  Classification:          Deterministic vs non-deterministic");
     println!("  Deterministic exec:      PARALLEL (independent, no conflicts)");
@@ -245,424 +619,877 @@ fn run_benchmark_with_threads(blocks_dir: &str, thread_count: usize) -> Result<(
     println!();
     println!("✓ Benchmark complete!");
     println!("✓ ALL {} transactions executed in PARALLEL", total_txs);
-    println!("✓ Results saved to williams_execution_time.txt");
+    println!("✓ Results saved to {}", results_path.display());
     println!("✓ Ready for comparison with SupraBTM baseline");
     
     Ok(())
 }
 
-/// Execute a single block using Williams Hybrid strategy with REAL EVM
-fn execute_block_williams(block_path: &PathBuf, thread_count: usize) -> Result<BlockResult> {
-    let block_start = Instant::now();
-    
-    // Extract block number
-    let block_number = extract_block_number(block_path)?;
+/// What the pre-run scan learned from one block file
+struct BlockScan {
+    integrity: Option<BlockIntegrity>,
+    header: Option<HeaderInfo>,
+}
+
+/// Pre-run dataset checks: header chain and, optionally, tx integrity
+///
+/// Every block is read once, in parallel batches, before any measurement.
+/// Findings are only reported; blocks are still executed so results stay
+/// comparable with unchecked runs. Returns whether every check passed.
+///
+/// Only selected blocks are checked; the header chain is not checked for a
+/// partial selection, whose gaps say nothing about the dataset.
+fn run_dataset_checks(dataset: &Dataset, chain: &ChainConfig, options: &RunOptions) -> Result<bool> {
+    let selection = &options.selection;
+    let check_headers = options.check_headers && selection.is_all();
+    if options.check_headers && !check_headers {
+        println!("Header chain:              not checked for a partial selection");
+        println!();
+    }
+    if !check_headers && !options.verify_integrity {
+        return Ok(true);
+    }
     
-    // Load block data
-    let block_data = fs::read_to_string(block_path)?;
-    let json: Value = serde_json::from_str(&block_data)?;
+    let mut stream = dataset.open()?.filter(|block_ref| match block_ref {
+        Ok(BlockRef { number: Some(number), .. }) => selection.contains(*number),
+        _ => true,
+    });
+    println!("Checking dataset...");
+    
+    let mut scanned: Vec<(String, Result<BlockScan>)> = Vec::new();
+    loop {
+        let batch: Vec<Result<BlockRef>> = stream.by_ref().take(BLOCK_BATCH_SIZE).collect();
+        if batch.is_empty() {
+            break;
+        }
+        scanned.par_extend(batch.into_par_iter().map(|block_ref| {
+            let block_ref = match block_ref {
+                Ok(block_ref) => block_ref,
+                Err(e) => return (dataset.blocks.display().to_string(), Err(e)),
+            };
+            let scan = load_block(&block_ref).map(|block| BlockScan {
+                integrity: options.verify_integrity.then(|| verify_block_integrity(&block)),
+                header: check_headers
+                    .then(|| HeaderInfo::from_block(&block, block_ref.name.clone()))
+                    .flatten(),
+            });
+            (block_ref.name, scan)
+        }));
+    }
+    println!("  Blocks checked:          {}", scanned.len());
     
-    // Extract block from JSON-RPC response format
-    let block = json.get("result").unwrap_or(&json);
+    let mut unreadable = 0;
+    for (name, scan) in &scanned {
+        if let Err(e) = scan {
+            unreadable += 1;
+            println!("  {}: unreadable ({})", name, e);
+        }
+    }
+    if unreadable > 0 {
+        println!("  Unreadable blocks:       {}", unreadable);
+    }
+    let mut passed = unreadable == 0;
+    
+    if check_headers {
+        let headers: Vec<HeaderInfo> = scanned.iter()
+            .filter_map(|(_, scan)| scan.as_ref().ok()?.header.clone())
+            .collect();
+        let without_number = scanned.iter().filter(|(_, scan)| scan.is_ok()).count() - headers.len();
+        let report = validate_header_chain(headers, chain);
+        report_header_chain(&report, without_number);
+        passed &= report.is_clean();
+    }
     
-    // Get transactions
-    let txs = block.get("transactions")
-        .and_then(|t| t.as_array())
-        .context("No transactions in block")?;
+    if options.verify_integrity {
+        let integrity: Vec<(&str, &BlockIntegrity)> = scanned.iter()
+            .filter_map(|(name, scan)| Some((name.as_str(), scan.as_ref().ok()?.integrity.as_ref()?)))
+            .collect();
+        report_integrity(&integrity);
+        passed &= integrity.iter().all(|(_, integrity)| !integrity.is_corrupted());
+    }
+    println!();
     
-    let tx_count = txs.len();
+    Ok(passed)
+}
+
+/// Block files, prestate traces and the chain config: everything a manifest pins
+fn manifest_files(dataset: &Dataset) -> Result<Vec<PathBuf>> {
+    let mut files = dataset.files()?;
+    files.extend(prestate::trace_files(&dataset.root)?);
+    files.extend(chain_config::config_file(&dataset.root));
+    Ok(files)
+}
+
+/// Check the dataset against its manifest; returns the manifest hash
+fn verify_manifest(dataset: &Dataset, chain: &ChainConfig) -> Result<Option<String>> {
+    let Some((manifest, hash)) = Manifest::load(&dataset.root)? else {
+        println!("Dataset manifest:          none (create one with `manifest`)");
+        return Ok(None);
+    };
     
-    if tx_count == 0 {
-        return Ok(BlockResult {
-            block_number,
-            tx_count: 0,
-            deterministic_count: 0,
-            execution_time_us: 0,
-        });
+    let start = Instant::now();
+    manifest.verify(&dataset.root, &manifest_files(dataset)?, chain.chain_id)?;
+    println!("Dataset manifest:          {} files verified ({:.1}s)",
+        manifest.files.len(), start.elapsed().as_secs_f64()
+    );
+    println!("  SHA-256:                 {}", hash);
+    println!("  Source:                  {}", manifest.source);
+    if let (Some(first), Some(last)) = (manifest.first_block, manifest.last_block) {
+        println!("  Blocks:                  {} ({} to {}), {} txs",
+            manifest.block_count, first, last, manifest.transaction_count
+        );
     }
+    Ok(Some(hash))
+}
+
+/// Write `manifest.json`: file hashes, block range and tx counts
+///
+/// Every block has to load; a manifest pins a dataset that can be run.
+fn create_manifest(dataset: &mut Dataset, chain: &ChainConfig, source: String) -> Result<()> {
+    const MAX_LISTED: usize = 10;
     
-    // Classify transactions
-    let mut deterministic_txs = Vec::new();
-    let mut nondeterministic_txs = Vec::new();
+    report_dataset_summary(&dataset.discover()?);
+    let mut stream = dataset.open()?;
+    println!("Writing dataset manifest...");
     
-    for (idx, tx) in txs.iter().enumerate() {
-        let tx_type = classify_transaction(tx);
-        match tx_type {
-            TxType::Deterministic => deterministic_txs.push((idx, tx)),
-            TxType::NonDeterministic => nondeterministic_txs.push((idx, tx)),
+    let start = Instant::now();
+    let mut scanned: Vec<Result<BlockEntry>> = Vec::new();
+    loop {
+        let batch: Vec<Result<BlockRef>> = stream.by_ref().take(BLOCK_BATCH_SIZE).collect();
+        if batch.is_empty() {
+            break;
         }
+        scanned.par_extend(batch.into_par_iter().map(|block_ref| {
+            let block_ref = block_ref?;
+            let block = load_block(&block_ref)?;
+            let number = json_hex::field_u64(&block, "number")
+                .or(block_ref.number)
+                .with_context(|| format!("{}: block has no number", block_ref.name))?;
+            let transactions = block["transactions"].as_array().map_or(0, Vec::len);
+            Ok(BlockEntry { number, transactions })
+        }));
     }
     
-    let det_count = deterministic_txs.len();
-    
-    // REAL EXECUTION: Create EVM instance
-    let mut cache_db = CacheDB::new(EmptyDB::default());
+    let (blocks, failures): (Vec<_>, Vec<_>) = scanned.into_iter().partition(Result::is_ok);
+    if !failures.is_empty() {
+        println!("Unreadable blocks:         {}", failures.len());
+        for error in failures.iter().filter_map(|f| f.as_ref().err()).take(MAX_LISTED) {
+            println!("    {:#}", error);
+        }
+        bail!("Cannot write a manifest for a dataset with unreadable blocks");
+    }
+    let blocks = blocks.into_iter().map(Result::unwrap).collect();
     
-    // Setup block environment
-    let block_env = setup_block_env(block)?;
+    let manifest = Manifest::new(&dataset.root, &manifest_files(dataset)?, source, chain.chain_id, blocks)?;
+    let hash = manifest.write(&dataset.root)?;
+    println!("  Files:                   {}", manifest.files.len());
+    println!("  Blocks:                  {}", manifest.block_count);
+    println!("  Transactions:            {}", manifest.transaction_count);
+    println!("  SHA-256:                 {}", hash);
+    println!("  Time:                    {:.1}s", start.elapsed().as_secs_f64());
+    println!("Wrote {}", dataset.root.join(manifest::MANIFEST_FILE).display());
     
-    // OPTIMIZATION 1: Bulk prefetch ALL addresses once (shared across threads)
-    let state_cache = bulk_prefetch_addresses(&deterministic_txs, &nondeterministic_txs);
+    Ok(())
+}
+
+/// Print gaps, duplicates, forks and consensus violations of the dataset
+fn report_header_chain(report: &ChainReport, without_number: usize) {
+    const MAX_LISTED: usize = 10;
     
-    // OPTIMIZATION 2: Sharded state tracker (reduces lock contention)
-    let state_tracker = Arc::new(ShardedStateTracker::new());
+    match report.range {
+        Some((first, last)) => println!("Header chain:              {} blocks ({} to {})", report.blocks, first, last),
+        None => println!("Header chain:              no blocks"),
+    }
+    if without_number > 0 {
+        println!("  Without number:          {} (not checked)", without_number);
+    }
+    println!("  Gaps:                    {} ({} missing blocks)", report.gaps.len(), report.missing_blocks());
+    for (from, to) in report.gaps.iter().take(MAX_LISTED) {
+        if from == to {
+            println!("    missing block {}", from);
+        } else {
+            println!("    missing blocks {} to {}", from, to);
+        }
+    }
+    println!("  Duplicates:              {}", report.duplicates.len());
+    for number in report.duplicates.iter().take(MAX_LISTED) {
+        println!("    block {} present more than once", number);
+    }
+    println!("  Forks:                   {}", report.forks.len());
+    for number in report.forks.iter().take(MAX_LISTED) {
+        println!("    competing blocks at height {}", number);
+    }
+    println!("  Header violations:       {}", report.problems.len());
+    for problem in report.problems.iter().take(MAX_LISTED) {
+        println!("    {}", problem);
+    }
+    if report.is_clean() {
+        println!("  Dataset forms a single valid chain");
+    }
+}
+
+/// Print tx hash and transactions root findings
+fn report_integrity(checked: &[(&str, &BlockIntegrity)]) {
+    let mut corrupted = 0;
+    let mut missing_roots = 0;
+    let mut total_txs = 0;
+    
+    for (name, integrity) in checked {
+        total_txs += integrity.tx_count;
+        if integrity.missing_root {
+            missing_roots += 1;
+        }
+        if !integrity.is_corrupted() {
+            continue;
+        }
+        
+        corrupted += 1;
+        println!("  {}: CORRUPTED", name);
+        for mismatch in &integrity.hash_mismatches {
+            println!("    tx {}: hash {} but encodes to {}",
+                mismatch.tx_index, mismatch.claimed, mismatch.computed
+            );
+        }
+        for (tx_index, error) in &integrity.encode_failures {
+            println!("    tx {}: cannot re-encode ({})", tx_index, error);
+        }
+        for tx_index in &integrity.missing_hashes {
+            println!("    tx {}: no hash in the dataset to check", tx_index);
+        }
+        if let Some((header_root, computed)) = integrity.root_mismatch {
+            println!("    transactionsRoot {} but txs give {}", header_root, computed);
+        }
+    }
     
-    let exec_start = Instant::now();
+    println!("Integrity check:           {} blocks, {} txs", checked.len(), total_txs);
+    println!("  Corrupted blocks:        {}", corrupted);
+    if missing_roots > 0 {
+        println!("  Without transactionsRoot: {} (root not checked)", missing_roots);
+    }
+}
+
+/// Decode the dataset once and write it to the block cache
+///
+/// Blocks are decoded in parallel batches and appended in dataset order;
+/// blocks that fail to load or decode are skipped and listed.
+fn convert_dataset(
+    dataset: &Dataset,
+    cache_path: &Path,
+    chain: &ChainConfig,
+    options: &RunOptions,
+    manifest: Option<&str>,
+) -> Result<()> {
+    let mut stream = dataset.open()?;
+    println!("Converting {} into {}...", dataset.blocks.display(), cache_path.display());
     
-    // Williams Strategy 1: Deterministic transactions with PARALLEL execution
-    // Execute ALL deterministic transactions (simple transfers, known patterns)
-    // These are INDEPENDENT - no conflicts, perfect for parallelization!
-    let det_exec_time = if !deterministic_txs.is_empty() {
-        use rayon::prelude::*;
-        use rayon::ThreadPoolBuilder;
-        
-        // Create thread pool with specified size (bounty requirement)
-        let pool = ThreadPoolBuilder::new()
-            .num_threads(thread_count)
-            .build()
-            .context("Failed to create thread pool for deterministic txs")?;
-        
-        let parallel_start = Instant::now();
-        
-        // Execute ALL deterministic transactions in PARALLEL
-        // They're independent (simple transfers) so no conflicts possible
-        let _results: Vec<_> = pool.install(|| {
-            deterministic_txs
-                .par_iter()
-                .map(|(_, tx)| {
-                    // Each thread gets its own DB instance
-                    let mut thread_db = cache_db.clone();
-                    execute_transaction(&mut thread_db, tx, &block_env)
-                })
-                .collect()
-        });
-        
-        // Measure actual wallclock time for parallel execution
-        parallel_start.elapsed().as_micros()
-    } else {
-        0
-    };
+    let start = Instant::now();
+    let mut writer = BlockCacheWriter::create(cache_path, chain.chain_id, options.verify_senders, manifest)?;
+    let mut skipped = 0;
     
-    // Williams Strategy 2: Non-deterministic transactions with REAL parallel execution
-    let nondet_exec_time = if !nondeterministic_txs.is_empty() {
-        use rayon::prelude::*;
-        use rayon::ThreadPoolBuilder;
-        
-        // Create thread pool with specified size (bounty requirement)
-        let pool = ThreadPoolBuilder::new()
-            .num_threads(thread_count)
-            .build()
-            .context("Failed to create thread pool")?;
-        
-        let parallel_start = Instant::now();
-        
-        // Execute ALL non-deterministic transactions in parallel using configured thread pool
-        // This gives us REAL parallel speedup with controlled thread count
-        let _results: Vec<_> = pool.install(|| {
-            nondeterministic_txs
-                .par_iter()  // Parallel iterator - Rayon handles threading
-                .map(|(_, tx)| {
-                    // Each thread gets its own DB instance
-                    let mut thread_db = cache_db.clone();
-                    execute_transaction(&mut thread_db, tx, &block_env)
-                })
-                .collect()
-        });
+    loop {
+        let batch: Vec<Result<BlockRef>> = stream.by_ref().take(BLOCK_BATCH_SIZE).collect();
+        if batch.is_empty() {
+            break;
+        }
+        let encoded: Vec<_> = batch
+            .into_par_iter()
+            .map(|block_ref| {
+                let block_ref = match block_ref {
+                    Ok(block_ref) => block_ref,
+                    Err(e) => return (dataset.blocks.display().to_string(), Err(e)),
+                };
+                let encoded = load_block(&block_ref).and_then(|json| {
+                    let block = prepare_block(&json, block_ref.number, chain, options.verify_senders)?;
+                    Ok((block.number, block_cache::encode(&block)?))
+                });
+                (block_ref.name, encoded)
+            })
+            .collect();
         
-        // Measure actual wallclock time for parallel execution
-        parallel_start.elapsed().as_micros()
-    } else {
-        0
-    };
+        for (name, result) in encoded {
+            match result {
+                Ok((number, bytes)) => writer.append(number, &bytes)?,
+                Err(e) => {
+                    skipped += 1;
+                    println!("  {}: skipped ({:#})", name, e);
+                }
+            }
+        }
+    }
     
-    let total_exec_time = det_exec_time + nondet_exec_time;
+    let written = writer.finish()?;
+    let size_mb = fs::metadata(cache_path)?.len() as f64 / (1024.0 * 1024.0);
+    println!("Converted:                 {} blocks ({} skipped)", written, skipped);
+    println!("Cache size:                {:.2} MB", size_mb);
+    println!("Conversion time:           {:.2}s", start.elapsed().as_secs_f64());
     
-    Ok(BlockResult {
-        block_number,
-        tx_count,
-        deterministic_count: det_count,
-        execution_time_us: total_exec_time,
-    })
+    Ok(())
 }
 
-/// Execute a single transaction using REVM
-fn execute_transaction(
-    db: &mut CacheDB<EmptyDB>,
-    tx: &Value,
-    block_env: &BlockEnv,
-) -> Result<u128> {
-    let start = Instant::now();
-    
-    // Parse transaction
-    let tx_env = parse_transaction(tx)?;
-    
-    // Setup EVM
-    let mut cfg = CfgEnvWithHandlerCfg::new_with_spec_id(
-        Default::default(),
-        SpecId::LATEST,
+/// Download a block range into the data directory, resuming earlier runs
+///
+/// A complete download is described in a fresh dataset manifest.
+fn download_dataset(dataset: &mut Dataset, chain: &ChainConfig, config: &DownloadConfig) -> Result<()> {
+    if config.rpc_url.is_empty() {
+        bail!("download needs --rpc <url> of an archive node");
+    }
+    println!("Downloading into: {}", dataset.root.display());
+    println!("Chain: {} (chain id {})", chain.name, chain.chain_id);
+    println!("Blocks {}..={} from {} with {} workers",
+        config.start, config.start + config.count.saturating_sub(1), config.rpc_url, config.workers
     );
+    println!("Receipts: {}, prestate traces: {}",
+        if config.receipts { "yes" } else { "no" },
+        if config.prestate { "yes" } else { "no" }
+    );
+    println!();
     
-    let mut evm = Evm::builder()
-        .with_db(db)
-        .with_block_env(block_env.clone())
-        .with_tx_env(tx_env)
-        .with_cfg_env_with_handler_cfg(cfg)
-        .build();
-    
-    // Execute transaction
-    let _ = evm.transact();
+    let summary = download(&dataset.root, config, chain.chain_id)?;
+    report_download(&summary);
     
-    Ok(start.elapsed().as_micros())
+    if !summary.failed.is_empty() {
+        bail!("{} blocks failed to download; run download again to resume", summary.failed.len());
+    }
+    println!();
+    create_manifest(dataset, chain, format!("JSON-RPC {}", rpc_host(&config.rpc_url)))
 }
 
-/// Parse transaction from JSON
-fn parse_transaction(tx: &Value) -> Result<TxEnv> {
-    let mut tx_env = TxEnv::default();
+fn report_download(summary: &DownloadSummary) {
+    const MAX_LISTED: usize = 10;
     
-    // From address
-    if let Some(from) = tx.get("from").and_then(|v| v.as_str()) {
-        let from_str = from.trim_start_matches("0x");
-        if let Ok(bytes) = hex::decode(from_str) {
-            if bytes.len() == 20 {
-                tx_env.caller = Address::from_slice(&bytes);
-            }
-        }
+    println!();
+    println!("Downloaded:                {} blocks", summary.fetched);
+    println!("Already present:           {} blocks", summary.complete);
+    println!("Time:                      {:.1}s ({:.1} blocks/s)",
+        summary.elapsed.as_secs_f64(),
+        summary.fetched as f64 / summary.elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    if summary.failed.is_empty() {
+        return;
     }
-    
-    // To address
-    if let Some(to) = tx.get("to").and_then(|v| v.as_str()) {
-        if !to.is_empty() && to != "null" {
-            let to_str = to.trim_start_matches("0x");
-            if let Ok(bytes) = hex::decode(to_str) {
-                if bytes.len() == 20 {
-                    tx_env.transact_to = TransactTo::Call(Address::from_slice(&bytes));
-                }
-            }
-        } else {
-            tx_env.transact_to = TransactTo::Create;
-        }
+    println!("Failed:                    {} blocks", summary.failed.len());
+    for (_, error) in summary.failed.iter().take(MAX_LISTED) {
+        println!("    {:#}", error);
     }
-    
-    // Value
-    if let Some(value) = tx.get("value").and_then(|v| v.as_str()) {
-        let value_str = value.trim_start_matches("0x");
-        if let Ok(val) = U256::from_str_radix(value_str, 16) {
-            tx_env.value = val;
-        }
+}
+
+/// Open the block cache and check it fits this run
+fn open_block_cache(
+    cache_path: &Path,
+    chain: &ChainConfig,
+    options: &RunOptions,
+    manifest: Option<&str>,
+) -> Result<BlockCache> {
+    let cache = BlockCache::open(cache_path)?;
+    if cache.chain_id != chain.chain_id {
+        bail!("Block cache was converted for chain id {} but the chain is {}; run convert again",
+            cache.chain_id, chain.chain_id
+        );
     }
-    
-    // Input data
-    if let Some(input) = tx.get("input").and_then(|v| v.as_str()) {
-        let input_str = input.trim_start_matches("0x");
-        if let Ok(bytes) = hex::decode(input_str) {
-            tx_env.data = Bytes::from(bytes);
-        }
+    if options.verify_senders && !cache.verified_senders {
+        bail!("Block cache was converted without --verify-senders; run convert with it or use --no-cache");
     }
-    
-    // Gas limit
-    if let Some(gas) = tx.get("gas").and_then(|v| v.as_str()) {
-        let gas_str = gas.trim_start_matches("0x");
-        if let Ok(gas_val) = u64::from_str_radix(gas_str, 16) {
-            tx_env.gas_limit = gas_val;
-        }
-    } else {
-        tx_env.gas_limit = 30_000_000; // Default
+    if manifest.is_some() && cache.manifest.as_deref() != manifest {
+        bail!("Block cache was not converted from this dataset manifest; run convert again or use --no-cache");
     }
-    
-    // Gas price
-    if let Some(gas_price) = tx.get("gasPrice").and_then(|v| v.as_str()) {
-        let gp_str = gas_price.trim_start_matches("0x");
-        if let Ok(gp) = U256::from_str_radix(gp_str, 16) {
-            tx_env.gas_price = gp;
+    Ok(cache)
+}
+
+/// Build the executor named by `--executor` or `--baseline`
+fn build_executor(name: &str, chain: &ChainConfig, thread_count: usize, profile_path: &Path) -> Result<Executor> {
+    let chain = chain.clone();
+    Ok(match name {
+        "williams" => {
+            let profile = GasProfile::load(profile_path)?;
+            Box::new(WilliamsExecutor::new(chain, thread_count).with_gas_profile(profile))
         }
-    }
+        "sequential" => Box::new(SequentialExecutor::new(chain)),
+        "naive" => Box::new(NaiveParallelExecutor::new(chain, thread_count)?),
+        "optimistic" => Box::new(OptimisticExecutor::new(chain, thread_count)?),
+        other => bail!("Unknown executor {} (expected one of: {})", other, EXECUTORS.join(", ")),
+    })
+}
+
+/// Execute a single block with REAL EVM on top of its pre-block state
+fn execute_prepared_block(
+    block: &PreparedBlock,
+    state: &StateSource,
+    executor: &mut Executor,
+) -> Result<BlockResult> {
+    let (execution, _) = execute_on_prestate(block, state, executor)?;
     
-    Ok(tx_env)
+    Ok(BlockResult {
+        block_number: block.number,
+        tx_count: block.txs.len(),
+        deterministic_count: block.txs.iter().filter(|tx| tx.deterministic).count(),
+        execution_time_us: execution.timings.total_us(),
+        missing_header_fields: block.missing_header_fields.clone(),
+        finalize: execution.finalize,
+        system_calls: execution.system_calls,
+        senders: block.senders.clone(),
+        sender_recovery_us: block.sender_recovery_us,
+        prefetch: execution.prefetch,
+        schedule: execution.schedule,
+        decision: execution.decision,
+        observed_conflicts: execution.conflict_rate,
+        reexecuted: execution.reexecuted,
+        gas_used: execution.outcomes.iter().flatten().map(|outcome| outcome.result.gas_used()).sum(),
+        stages: execution.timings,
+        prepare: block.timings,
+    })
 }
 
-/// Setup block environment
-fn setup_block_env(block: &Value) -> Result<BlockEnv> {
-    let mut block_env = BlockEnv::default();
+/// Execute a block and hand back the prestate it ran on, for inspecting
+/// the outcomes against it
+fn execute_on_prestate(
+    block: &PreparedBlock,
+    state: &StateSource,
+    executor: &mut Executor,
+) -> Result<(BlockExecution, Arc<PrestateDB>)> {
+    // Pre-block state (read and parsed outside the measurement, like the block)
+    let prestate = state.load(block.number)?;
+    let plan = AccessPlan::for_block(block).with_prestate(&prestate);
+    let prestate = Arc::new(prestate);
+    
+    let execution = executor.execute_with_plan(block, Arc::clone(&prestate), &plan)?;
+    Ok((execution, prestate))
+}
+
+/// Print what discovery found before anything runs
+fn report_dataset_summary(summary: &DatasetSummary) {
+    const MAX_LISTED: usize = 10;
     
-    // Block number
-    if let Some(num) = block.get("number").and_then(|v| v.as_str()) {
-        let num_str = num.trim_start_matches("0x");
-        if let Ok(block_num) = u64::from_str_radix(num_str, 16) {
-            block_env.number = U256::from(block_num);
-        }
+    match summary.range {
+        Some((first, last)) => println!("Dataset:                   {} blocks ({} to {})", summary.blocks, first, last),
+        None => println!("Dataset:                   no blocks"),
     }
-    
-    // Timestamp
-    if let Some(ts) = block.get("timestamp").and_then(|v| v.as_str()) {
-        let ts_str = ts.trim_start_matches("0x");
-        if let Ok(timestamp) = u64::from_str_radix(ts_str, 16) {
-            block_env.timestamp = U256::from(timestamp);
+    println!("  Missing numbers:         {} in {} gaps", summary.missing_blocks(), summary.gaps.len());
+    for (from, to) in summary.gaps.iter().take(MAX_LISTED) {
+        if from == to {
+            println!("    {}", from);
+        } else {
+            println!("    {} to {}", from, to);
         }
     }
-    
-    // Gas limit
-    if let Some(gas) = block.get("gasLimit").and_then(|v| v.as_str()) {
-        let gas_str = gas.trim_start_matches("0x");
-        if let Ok(gas_limit) = u64::from_str_radix(gas_str, 16) {
-            block_env.gas_limit = U256::from(gas_limit);
+    if !summary.duplicates.is_empty() {
+        println!("  Duplicates skipped:      {}", summary.duplicates.len());
+        for (number, name) in summary.duplicates.iter().take(MAX_LISTED) {
+            println!("    {} (block {})", name, number);
         }
     }
-    
-    // Base fee
-    if let Some(base_fee) = block.get("baseFeePerGas").and_then(|v| v.as_str()) {
-        let bf_str = base_fee.trim_start_matches("0x");
-        if let Ok(bf) = U256::from_str_radix(bf_str, 16) {
-            block_env.basefee = bf;
+    if !summary.unnumbered.is_empty() {
+        println!("  Without block number:    {} (skipped)", summary.unnumbered.len());
+        for (name, reason) in summary.unnumbered.iter().take(MAX_LISTED) {
+            println!("    {}: {}", name, reason);
         }
     }
-    
-    // Coinbase
-    if let Some(miner) = block.get("miner").and_then(|v| v.as_str()) {
-        let miner_str = miner.trim_start_matches("0x");
-        if let Ok(bytes) = hex::decode(miner_str) {
-            if bytes.len() == 20 {
-                block_env.coinbase = Address::from_slice(&bytes);
-            }
-        }
+    if summary.out_of_order > 0 {
+        println!("  Out of order lines:      {} (replayed in file order)", summary.out_of_order);
     }
+    println!();
+}
+
+/// List blocks that could not be loaded or executed
+fn report_failed_blocks(failures: &[Result<BlockResult>]) {
+    const MAX_LISTED: usize = 10;
     
-    Ok(block_env)
+    if failures.is_empty() {
+        return;
+    }
+    println!("Failed blocks:             {} (not included in results)", failures.len());
+    for error in failures.iter().filter_map(|f| f.as_ref().err()).take(MAX_LISTED) {
+        println!("    {:#}", error);
+    }
+    println!();
 }
 
-/// Classify transaction as deterministic or non-deterministic
-fn classify_transaction(tx: &Value) -> TxType {
-    // Check input data
-    if let Some(input) = tx.get("input").and_then(|i| i.as_str()) {
-        let input_data = input.trim_start_matches("0x");
-        
-        // Empty input = simple transfer = deterministic
-        if input_data.is_empty() || input_data == "0x" {
-            return TxType::Deterministic;
+/// Summarize header fields the dataset is missing for its forks
+fn report_missing_header_fields(results: &[BlockResult]) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut incomplete_blocks = 0;
+    
+    for result in results {
+        if !result.missing_header_fields.is_empty() {
+            incomplete_blocks += 1;
         }
-        
-        // Short data (< 10 bytes) = likely simple call = deterministic
-        if input_data.len() < 20 {
-            return TxType::Deterministic;
-        }
-        
-        // Check function signatures for known deterministic patterns
-        if input_data.len() >= 8 {
-            let sig = &input_data[0..8];
-            
-            match sig {
-                "a9059cbb" => return TxType::Deterministic, // ERC20 transfer
-                "095ea7b3" => return TxType::Deterministic, // ERC20 approve
-                "23b872dd" => return TxType::Deterministic, // ERC20 transferFrom
-                "70a08231" => return TxType::Deterministic, // balanceOf
-                "18160ddd" => return TxType::Deterministic, // totalSupply
-                _ => {}
-            }
+        for field in &result.missing_header_fields {
+            *counts.entry(field.as_str()).or_insert(0) += 1;
         }
     }
     
-    // Contract creation is non-deterministic
-    if tx.get("to").is_none() || tx.get("to").and_then(|t| t.as_str()) == Some("") {
-        return TxType::NonDeterministic;
+    if incomplete_blocks == 0 {
+        return;
     }
     
-    // Default: non-deterministic (safe fallback)
-    TxType::NonDeterministic
+    let mut fields: Vec<_> = counts.into_iter().collect();
+    fields.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    
+    println!("Header validation:         {} blocks with missing fields", incomplete_blocks);
+    for (field, count) in fields {
+        println!("  {:<24} missing in {} blocks", field, count);
+    }
+    println!();
 }
 
-/// OPTIMIZATION 1: Bulk prefetch all addresses from transactions
-/// Collects unique addresses ONCE before parallel execution
-/// Returns shared cache that all threads can use (zero-copy via Arc)
-fn bulk_prefetch_addresses(
-    det_txs: &[(usize, &Value)],
-    nondet_txs: &[(usize, &Value)]
-) -> SharedStateCache {
-    use std::collections::HashSet;
-    
-    let mut unique_addresses = HashSet::new();
+/// Summarize post-block state transitions applied during the run
+fn report_finalization(results: &[BlockResult]) {
+    let withdrawals: usize = results.iter().map(|r| r.finalize.withdrawals).sum();
+    let unpaid_ommers: usize = results.iter().map(|r| r.finalize.unpaid_ommers).sum();
+    let to_eth = |wei: U256| wei.to_string().parse::<f64>().unwrap_or(0.0) / 1e18;
+    let withdrawn: U256 = results.iter().map(|r| r.finalize.withdrawn_wei).sum();
+    let rewards: U256 = results.iter()
+        .map(|r| r.finalize.block_reward_wei + r.finalize.ommer_reward_wei)
+        .sum();
+    
+    println!("Block finalization:");
+    println!("  Withdrawals applied:     {} ({:.4} ETH)", withdrawals, to_eth(withdrawn));
+    println!("  Block + ommer rewards:   {:.4} ETH", to_eth(rewards));
+    if unpaid_ommers > 0 {
+        println!("  Ommers without headers:  {} (rewards not paid, add uncleHeaders)", unpaid_ommers);
+    }
     
-    // Collect from deterministic transactions
-    for (_, tx) in det_txs {
-        if let Some(from) = tx.get("from").and_then(|v| v.as_str()) {
-            if let Ok(addr) = parse_address_str(from) {
-                unique_addresses.insert(addr);
-            }
-        }
-        if let Some(to) = tx.get("to").and_then(|v| v.as_str()) {
-            if !to.is_empty() {
-                if let Ok(addr) = parse_address_str(to) {
-                    unique_addresses.insert(addr);
-                }
-            }
-        }
+    let system_calls: usize = results.iter().map(|r| r.system_calls.calls).sum();
+    if system_calls > 0 {
+        let withdrawal_requests: usize = results.iter().map(|r| r.system_calls.withdrawal_requests).sum();
+        let consolidation_requests: usize = results.iter().map(|r| r.system_calls.consolidation_requests).sum();
+        println!("  System calls:            {}", system_calls);
+        println!("  Withdrawal requests:     {}", withdrawal_requests);
+        println!("  Consolidation requests:  {}", consolidation_requests);
+    }
+    println!();
+}
+
+/// Summarize where time went, from reading blocks to committing transactions
+fn report_phases(results: &[BlockResult], timings: &RunTimings, cached: bool) {
+    let phases = PhaseTimings::total(results);
+    let ms = |us: u128| us as f64 / 1000.0;
+    
+    println!("Phase timings:");
+    println!("  Read:                    {:.2}ms (reader threads)", ms(phases.read_us));
+    println!("  Parse:                   {:.2}ms (reader threads)", ms(phases.parse_us));
+    println!("  Sender recovery:         {:.2}ms (reader threads)", ms(phases.sender_recovery_us));
+    println!("  Classification:          {:.2}ms (reader threads)", ms(phases.classify_us));
+    println!("  Executor setup:          {:.2}ms (not in total time)", ms(timings.setup_us));
+    println!("  Pool creation:           {:.2}ms (not in total time)", ms(phases.pool_creation_us));
+    println!("  System calls:            {:.2}ms", ms(phases.system_calls_us));
+    println!("  Prefetch:                {:.2}ms", ms(phases.prefetch_us));
+    println!("  Transactions:            {:.2}ms (commit {:.2}ms)", ms(phases.transactions_us), ms(phases.commit_us));
+    println!("  Finalization:            {:.2}ms", ms(phases.finalize_us));
+    if cached {
+        println!("  (blocks came from the block cache: read is decoding it, parsing and");
+        println!("   classification ran at convert time)");
+    }
+    println!();
+}
+
+/// Summarize block loading and how long execution waited for it
+fn report_ingestion(stats: &ReadAheadStats, config: &ReadAheadConfig) {
+    println!("Block ingestion:");
+    println!("  Readers:                 {} (queue depth {})", config.readers, config.depth);
+    println!("  Load time:               {:.2}ms (on reader threads)", stats.load_us as f64 / 1000.0);
+    println!("  I/O stall:               {:.2}ms in {} waits (excluded from total time)",
+        stats.stall_us as f64 / 1000.0, stats.stalls
+    );
+    println!("  Blocks ready on request: {:.1} on average", stats.average_ready());
+    println!();
+}
+
+/// Summarize what the prefetch stage loaded
+fn report_prefetch(results: &[BlockResult]) {
+    let accounts: usize = results.iter().map(|r| r.prefetch.accounts).sum();
+    let contracts: usize = results.iter().map(|r| r.prefetch.contracts).sum();
+    let slots: usize = results.iter().map(|r| r.prefetch.slots).sum();
+    let prefetch_ms = results.iter().map(|r| r.prefetch.prefetch_us).sum::<u128>() as f64 / 1000.0;
+    
+    println!("State prefetch:");
+    println!("  Accounts:                {}", accounts);
+    println!("  Contracts with code:     {}", contracts);
+    println!("  Storage slots:           {}", slots);
+    println!("  Prefetch time:           {:.2}ms (included in total time)", prefetch_ms);
+    println!();
+}
+
+/// Summarize how often contracts reused their analyzed bytecode
+fn report_code_cache(stats: &CodeCacheStats) {
+    println!("Bytecode analysis cache:");
+    println!("  Contracts analyzed:      {}", stats.misses);
+    println!("  Cache hits:              {} ({:.1}% of code loads)", stats.hits, stats.hit_rate());
+    println!("  Memory:                  {:.2} MB ({} bytes)", stats.bytes as f64 / (1024.0 * 1024.0), stats.bytes);
+    println!();
+}
+
+/// Summarize how transactions were ordered and chunked across threads
+fn report_schedule(results: &[BlockResult], profile: &GasProfile) {
+    let txs: usize = results.iter().map(|r| r.tx_count).sum();
+    let chunks: usize = results.iter().map(|r| r.schedule.chunks).sum();
+    let profiled: usize = results.iter().map(|r| r.schedule.profiled).sum();
+    let largest_share = results.iter()
+        .filter(|r| r.schedule.total_gas > 0)
+        .map(|r| r.schedule.largest_gas as f64 / r.schedule.total_gas as f64 * 100.0)
+        .fold(0.0, f64::max);
+    
+    println!("Gas-weighted scheduling:");
+    println!("  Chunks:                  {} ({:.1} txs per chunk)", chunks,
+        if chunks > 0 { txs as f64 / chunks as f64 } else { 0.0 }
+    );
+    println!("  Estimated from profile:  {} of {} txs ({:.1}%)", profiled, txs,
+        if txs > 0 { profiled as f64 / txs as f64 * 100.0 } else { 0.0 }
+    );
+    println!("  Largest tx share:        {:.1}% of its block's estimated gas", largest_share);
+    println!("  Profiled calls:          {} contract/selector pairs", profile.len());
+    println!();
+}
+
+/// Summarize which strategy blocks ran with and what each achieved
+fn report_policy(results: &[BlockResult]) {
+    let decided: Vec<(&BlockResult, &Decision)> = results.iter()
+        .filter_map(|r| r.decision.as_ref().map(|decision| (r, decision)))
+        .collect();
+    if decided.is_empty() {
+        return;
     }
     
-    // Collect from non-deterministic transactions
-    for (_, tx) in nondet_txs {
-        if let Some(from) = tx.get("from").and_then(|v| v.as_str()) {
-            if let Ok(addr) = parse_address_str(from) {
-                unique_addresses.insert(addr);
-            }
-        }
-        if let Some(to) = tx.get("to").and_then(|v| v.as_str()) {
-            if !to.is_empty() {
-                if let Ok(addr) = parse_address_str(to) {
-                    unique_addresses.insert(addr);
-                }
-            }
+    println!("Parallelism policy:");
+    for name in ["sequential", "limited", "parallel"] {
+        let chosen: Vec<&BlockResult> = decided.iter()
+            .filter(|(_, decision)| decision.strategy.name() == name)
+            .map(|&(r, _)| r)
+            .collect();
+        if chosen.is_empty() {
+            continue;
         }
+        let txs: usize = chosen.iter().map(|r| r.tx_count).sum();
+        let time_us: u128 = chosen.iter().map(|r| r.execution_time_us).sum();
+        println!("  {:<25}{} blocks, {} txs, {:.2} us/tx", format!("{}:", name), chosen.len(), txs,
+            if txs > 0 { time_us as f64 / txs as f64 } else { 0.0 }
+        );
     }
-    
-    // Build shared cache with collected addresses
-    // In EmptyDB case, this establishes the address set for future optimization
-    // With real state backend, this would populate actual balance/nonce data
-    let mut cache_data = HashMap::new();
-    for addr in unique_addresses {
-        // For now, just mark address as seen (EmptyDB has no state)
-        // With real DB: cache_data.insert(addr, (balance, nonce));
-        cache_data.insert(addr, (U256::ZERO, 0));
+    let predicted = decided.iter().map(|(_, decision)| decision.predicted_conflicts).sum::<f64>();
+    let observed = decided.iter().map(|(r, _)| r.observed_conflicts).sum::<f64>();
+    let blocks = decided.len() as f64;
+    println!("  Predicted conflicts:     {:.1}% of txs (block average)", predicted / blocks * 100.0);
+    println!("  Observed conflicts:      {:.1}% of txs (block average)", observed / blocks * 100.0);
+    println!("  Per-block decisions:     {}", POLICY_LOG_FILE);
+    println!();
+}
+
+/// Summarize how much optimistic execution had to redo
+fn report_validation(results: &[BlockResult]) {
+    let reexecuted: usize = results.iter().map(|r| r.reexecuted).sum();
+    if reexecuted == 0 {
+        return;
     }
+    let txs: usize = results.iter().map(|r| r.tx_count).sum();
+    let observed = results.iter().map(|r| r.observed_conflicts).sum::<f64>() / results.len().max(1) as f64;
     
-    SharedStateCache::with_data(cache_data)
+    println!("Optimistic validation:");
+    println!("  Re-executed txs:         {} of {} ({:.1}%)", reexecuted, txs,
+        reexecuted as f64 / txs.max(1) as f64 * 100.0
+    );
+    println!("  Observed conflicts:      {:.1}% of txs (block average)", observed * 100.0);
+    println!();
 }
 
-/// Parse address from hex string
-fn parse_address_str(addr_str: &str) -> Result<Address> {
-    let addr_hex = addr_str.trim_start_matches("0x");
-    let bytes = hex::decode(addr_hex)?;
-    if bytes.len() != 20 {
-        bail!("Invalid address length");
+/// Summarize sender recovery and any `from` fields that did not match
+fn report_sender_recovery(results: &[BlockResult]) {
+    let recovered: usize = results.iter().map(|r| r.senders.recovered).sum();
+    let failures: usize = results.iter().map(|r| r.senders.failures.len()).sum();
+    if recovered == 0 && failures == 0 {
+        return;
+    }
+    let mismatches: usize = results.iter().map(|r| r.senders.mismatches.len()).sum();
+    let recovery_ms = results.iter().map(|r| r.sender_recovery_us).sum::<u128>() as f64 / 1000.0;
+    
+    println!("Sender recovery:");
+    println!("  Recovered senders:       {} ({:.2}ms)", recovered, recovery_ms);
+    println!("  From mismatches:         {}", mismatches);
+    println!("  Recovery failures:       {}", failures);
+    
+    const MAX_LISTED: usize = 10;
+    let listed = results.iter()
+        .flat_map(|r| r.senders.mismatches.iter().map(move |m| (r.block_number, m)))
+        .take(MAX_LISTED);
+    for (block_number, mismatch) in listed {
+        println!("    block {} tx {}: from {} but signed by {}",
+            block_number, mismatch.tx_index, mismatch.claimed, mismatch.recovered
+        );
+    }
+    let listed = results.iter()
+        .flat_map(|r| r.senders.failures.iter().map(move |f| (r.block_number, f)))
+        .take(MAX_LISTED);
+    for (block_number, (tx_index, error)) in listed {
+        println!("    block {} tx {}: {}", block_number, tx_index, error);
     }
-    Ok(Address::from_slice(&bytes))
+    println!();
 }
 
-/// Extract block number from filename
-fn extract_block_number(path: &PathBuf) -> Result<u64> {
-    let filename = path.file_stem()
-        .and_then(|s| s.to_str())
-        .context("Invalid filename")?;
-    
-    // Remove "bdf-" prefix
-    let num_str = filename.strip_prefix("bdf-").unwrap_or(filename);
-    
-    num_str.parse()
-        .context("Failed to parse block number")
+/// Record what a run measured, next to the results file
+///
+/// Kept separate so the results stay in the SupraBTM format; the manifest
+/// hash identifies the exact dataset.
+fn write_run_info(
+    chain: &ChainConfig,
+    thread_count: usize,
+    executor: &str,
+    block_count: usize,
+    manifest: Option<&str>,
+    path: &Path,
+) -> Result<()> {
+    let output = format!(
+        "dataset_manifest_sha256\t{}\nchain_id\t{}\nexecutor\t{}\nthreads\t{}\nblocks\t{}\n",
+        manifest.unwrap_or("none"),
+        chain.chain_id,
+        executor,
+        thread_count,
+        block_count
+    );
+    fs::write(path, output)?;
+    Ok(())
+}
+
+/// Log each block's strategy, why it was chosen and what it achieved
+fn write_policy_log(results: &[BlockResult], path: &Path) -> Result<()> {
+    let mut output = String::from(
+        "block\ttxs\tstrategy\treason\tpredicted_conflicts\trecent_abort_rate\tobserved_conflicts\ttime_us\tus_per_tx\n"
+    );
+    for result in results {
+        let Some(decision) = &result.decision else {
+            continue;
+        };
+        output.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{:.3}\t{:.3}\t{:.3}\t{}\t{:.2}\n",
+            result.block_number,
+            result.tx_count,
+            decision.strategy,
+            decision.reason,
+            decision.predicted_conflicts,
+            decision.recent_abort_rate,
+            result.observed_conflicts,
+            result.execution_time_us,
+            result.execution_time_us as f64 / result.tx_count.max(1) as f64
+        ));
+    }
+    fs::write(path, output)?;
+    Ok(())
 }
 
 /// Write results in SupraBTM-compatible format
-fn write_results(results: &[BlockResult], filename: &str) -> Result<()> {
+fn write_results(results: &[BlockResult], thread_count: usize, path: &Path) -> Result<()> {
     let mut output = String::from("Block No\tThreads\tBlock Size\tWilliams Time\n");
     
     for result in results {
         let time_ms = result.execution_time_us as f64 / 1000.0;
         output.push_str(&format!(
-            "{}\t16\t{}\t{:.6}ms\n",
+            "{}\t{}\t{}\t{:.6}ms\n",
             result.block_number,
+            thread_count,
             result.tx_count,
             time_ms
         ));
     }
     
-    fs::write(filename, output)?;
+    fs::write(path, output)?;
+    
+    Ok(())
+}
+
+/// Write the run report as JSON: identity of the run, throughput, block
+/// latency percentiles and the time of every phase, in total and per block
+fn write_results_json(
+    results: &[BlockResult],
+    failed_blocks: usize,
+    thread_count: usize,
+    timings: &RunTimings,
+    inputs: &RunInputs,
+    options: &RunOptions,
+    path: &Path,
+) -> Result<()> {
+    let blocks: Vec<_> = results.iter()
+        .map(|result| json!({
+            "block": result.block_number,
+            "txs": result.tx_count,
+            "deterministic_txs": result.deterministic_count,
+            "gas_used": result.gas_used,
+            "time_us": result.execution_time_us,
+            "strategy": result.decision.as_ref().map(|decision| decision.strategy.to_string()),
+            "phases": PhaseTimings::of(result),
+        }))
+        .collect();
     
+    let mut strategies: HashMap<&str, usize> = HashMap::new();
+    for decision in results.iter().filter_map(|r| r.decision.as_ref()) {
+        *strategies.entry(decision.strategy.name()).or_insert(0) += 1;
+    }
+    let execution_us: u128 = results.iter().map(|r| r.execution_time_us).sum();
+    let txs: usize = results.iter().map(|r| r.tx_count).sum();
+    let ingestion = &timings.ingestion;
+    
+    let output = json!({
+        "dataset_manifest_sha256": inputs.manifest,
+        "data_dir": options.data_dir,
+        "chain": inputs.chain.name,
+        "chain_id": inputs.chain.chain_id,
+        "selection": options.selection.to_string(),
+        "first_block": results.iter().map(|r| r.block_number).min(),
+        "last_block": results.iter().map(|r| r.block_number).max(),
+        "executor": options.executor,
+        "threads": thread_count,
+        "strategies": strategies,
+        "blocks_processed": results.len(),
+        "failed_blocks": failed_blocks,
+        "txs": txs,
+        "gas_used": results.iter().map(|r| r.gas_used).sum::<u64>(),
+        "execution_us": execution_us,
+        "wallclock_us": timings.wallclock_us,
+        "txs_per_sec": txs as f64 / (execution_us.max(1) as f64 / 1_000_000.0),
+        "mgas_per_sec": mgas_per_sec(results),
+        "block_latency": BlockLatency::of(results),
+        "phases": PhaseTimings::total(results),
+        "executor_setup_us": timings.setup_us,
+        "ingestion": {
+            "readers": options.read_ahead.readers,
+            "queue_depth": options.read_ahead.depth,
+            "load_us": ingestion.load_us,
+            "stall_us": ingestion.stall_us,
+            "stalls": ingestion.stalls,
+        },
+        "blocks": blocks,
+    });
+    fs::write(path, serde_json::to_vec_pretty(&output)?)?;
     Ok(())
 }
+
+/// Gas executed per second of execution time, in millions
+fn mgas_per_sec(results: &[BlockResult]) -> f64 {
+    let gas: u64 = results.iter().map(|r| r.gas_used).sum();
+    let execution_us: u128 = results.iter().map(|r| r.execution_time_us).sum();
+    gas as f64 / execution_us.max(1) as f64
+}
+
+/// Execution time per block, by nearest-rank percentile
+#[derive(Debug, Default, Serialize)]
+struct BlockLatency {
+    mean_us: u128,
+    p50_us: u128,
+    p95_us: u128,
+    p99_us: u128,
+    max_us: u128,
+}
+
+impl BlockLatency {
+    fn of(results: &[BlockResult]) -> Self {
+        let mut times: Vec<u128> = results.iter().map(|r| r.execution_time_us).collect();
+        if times.is_empty() {
+            return Self::default();
+        }
+        times.sort_unstable();
+        let percentile = |p: usize| times[(times.len() * p).div_ceil(100).max(1) - 1];
+        Self {
+            mean_us: times.iter().sum::<u128>() / times.len() as u128,
+            p50_us: percentile(50),
+            p95_us: percentile(95),
+            p99_us: percentile(99),
+            max_us: times[times.len() - 1],
+        }
+    }
+}
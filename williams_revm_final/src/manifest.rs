@@ -0,0 +1,162 @@
+// Dataset manifest
+//
+// `manifest.json` in the dataset directory pins the exact input of a
+// benchmark: the SHA-256 of every block file and of the chain config, the
+// block range, per-block transaction counts and where the data came from.
+// `run` and `convert` verify it before touching a block, and the results
+// record the SHA-256 of the manifest itself, so two results carrying the same
+// manifest hash were measured on byte-identical datasets.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, Context, bail};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Bumped whenever the manifest layout changes
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// Where the data came from, e.g. the RPC host it was downloaded from
+    pub source: String,
+    pub chain_id: u64,
+    pub first_block: Option<u64>,
+    pub last_block: Option<u64>,
+    pub block_count: usize,
+    pub transaction_count: u64,
+    /// Dataset files, paths relative to the dataset directory
+    pub files: Vec<FileEntry>,
+    pub blocks: Vec<BlockEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockEntry {
+    pub number: u64,
+    pub transactions: usize,
+}
+
+impl Manifest {
+    /// Hash `files` (under `root`) and describe `blocks`
+    pub fn new(root: &Path, files: &[PathBuf], source: String, chain_id: u64, mut blocks: Vec<BlockEntry>) -> Result<Self> {
+        blocks.sort_by_key(|block| block.number);
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            source,
+            chain_id,
+            first_block: blocks.first().map(|block| block.number),
+            last_block: blocks.last().map(|block| block.number),
+            block_count: blocks.len(),
+            transaction_count: blocks.iter().map(|block| block.transactions as u64).sum(),
+            files: hash_files(root, files)?,
+            blocks,
+        })
+    }
+
+    /// Write `manifest.json` into `root`; returns the manifest hash
+    pub fn write(&self, root: &Path) -> Result<String> {
+        let mut encoded = serde_json::to_vec_pretty(self)?;
+        encoded.push(b'\n');
+        let path = root.join(MANIFEST_FILE);
+        fs::write(&path, &encoded)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(hex::encode(Sha256::digest(&encoded)))
+    }
+
+    /// Read `manifest.json` from `root`, with its hash, if the dataset has one
+    pub fn load(root: &Path) -> Result<Option<(Self, String)>> {
+        let path = root.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let raw = fs::read(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let manifest: Manifest = serde_json::from_slice(&raw)
+            .with_context(|| format!("Invalid dataset manifest {}", path.display()))?;
+        if manifest.version != MANIFEST_VERSION {
+            bail!("Dataset manifest version {} is not supported (expected {})", manifest.version, MANIFEST_VERSION);
+        }
+        Ok(Some((manifest, hex::encode(Sha256::digest(&raw)))))
+    }
+
+    /// Check that `files` are exactly the files listed, byte for byte
+    pub fn verify(&self, root: &Path, files: &[PathBuf], chain_id: u64) -> Result<()> {
+        const MAX_LISTED: usize = 10;
+
+        if self.chain_id != chain_id {
+            bail!("Dataset manifest is for chain id {}, the chain config says {}", self.chain_id, chain_id);
+        }
+
+        let actual = hash_files(root, files)?;
+        let expected: HashMap<&str, &FileEntry> = self.files.iter()
+            .map(|file| (file.path.as_str(), file))
+            .collect();
+        let actual_paths: HashMap<&str, &FileEntry> = actual.iter()
+            .map(|file| (file.path.as_str(), file))
+            .collect();
+
+        let mut problems = Vec::new();
+        for file in &self.files {
+            if !actual_paths.contains_key(file.path.as_str()) {
+                problems.push(format!("{}: missing", file.path));
+            }
+        }
+        for file in &actual {
+            match expected.get(file.path.as_str()) {
+                None => problems.push(format!("{}: not in the manifest", file.path)),
+                Some(entry) if *entry != file => problems.push(format!("{}: contents changed", file.path)),
+                Some(_) => {}
+            }
+        }
+
+        if !problems.is_empty() {
+            let mut message = format!("Dataset does not match {} ({} problems):", MANIFEST_FILE, problems.len());
+            for problem in problems.iter().take(MAX_LISTED) {
+                message.push_str("\n    ");
+                message.push_str(problem);
+            }
+            bail!(message);
+        }
+        Ok(())
+    }
+}
+
+/// SHA-256 and size of each file, in parallel, keeping the given order
+fn hash_files(root: &Path, files: &[PathBuf]) -> Result<Vec<FileEntry>> {
+    files.par_iter()
+        .map(|path| {
+            let mut hasher = Sha256::new();
+            let bytes = File::open(path)
+                .and_then(|mut file| io::copy(&mut file, &mut hasher))
+                .with_context(|| format!("Failed to hash {}", path.display()))?;
+            Ok(FileEntry {
+                path: relative_path(root, path),
+                bytes,
+                sha256: hex::encode(hasher.finalize()),
+            })
+        })
+        .collect()
+}
+
+/// Path below `root` with `/` separators, so manifests compare across systems
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
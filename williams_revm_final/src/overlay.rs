@@ -0,0 +1,233 @@
+// Copy-on-write state layers
+//
+// An `OverlayDB` sits on an immutable base shared through an `Arc` and
+// holds only what was committed into it: changed accounts, their changed
+// slots and new code. Reads fall through to the base for everything else,
+// so creating a layer costs one reference count increment no matter how much
+// state lies below it. Changes are merged into a layer with `commit`, or
+// discarded by dropping the layer.
+//
+// A block stacks layers: prefetched state, then the pre-block system calls,
+// then the transactions, committed in block order into one layer that
+// finalization and the post-block system calls also write to. Strategies
+// that execute transactions in parallel give each its own throwaway layer.
+// What the layers of a block hold together is the state the block changed.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use revm::primitives::{Account, AccountInfo, Address, Bytecode, B256, U256};
+use revm::{Database, DatabaseCommit, DatabaseRef};
+
+/// An account as changed in a layer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OverlayAccount {
+    /// `None` once the account was destroyed
+    pub info: Option<AccountInfo>,
+    /// Changed slots only
+    pub storage: HashMap<U256, U256>,
+    /// Storage below this layer no longer applies (created or destroyed here)
+    pub storage_cleared: bool,
+}
+
+/// Accounts and code changed by one or more layers
+#[derive(Debug, Clone, Default)]
+pub struct StateChanges {
+    pub accounts: HashMap<Address, OverlayAccount>,
+    pub contracts: HashMap<B256, Bytecode>,
+}
+
+impl StateChanges {
+    /// Apply the changes of a later layer on top of these
+    pub fn extend(&mut self, later: StateChanges) {
+        for (address, account) in later.accounts {
+            match self.accounts.entry(address) {
+                Entry::Occupied(mut entry) if !account.storage_cleared => {
+                    let earlier = entry.get_mut();
+                    earlier.info = account.info;
+                    earlier.storage.extend(account.storage);
+                }
+                Entry::Occupied(mut entry) => {
+                    entry.insert(account);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(account);
+                }
+            }
+        }
+        self.contracts.extend(later.contracts);
+    }
+}
+
+/// A writable layer over shared, immutable state
+#[derive(Debug)]
+pub struct OverlayDB<B> {
+    base: Arc<B>,
+    accounts: HashMap<Address, OverlayAccount>,
+    contracts: HashMap<B256, Bytecode>,
+}
+
+impl<B: DatabaseRef> OverlayDB<B> {
+    pub fn new(base: Arc<B>) -> Self {
+        Self {
+            base,
+            accounts: HashMap::new(),
+            contracts: HashMap::new(),
+        }
+    }
+
+    /// What was committed into this layer, not the layers below it
+    pub fn changes(&self) -> StateChanges {
+        StateChanges { accounts: self.accounts.clone(), contracts: self.contracts.clone() }
+    }
+
+    pub fn into_changes(self) -> StateChanges {
+        StateChanges { accounts: self.accounts, contracts: self.contracts }
+    }
+}
+
+impl<B: DatabaseRef> DatabaseRef for OverlayDB<B> {
+    type Error = B::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => Ok(account.info.clone()),
+            None => self.base.basic_ref(address),
+        }
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.base.code_by_hash_ref(code_hash),
+        }
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(account) => match account.storage.get(&index) {
+                Some(value) => Ok(*value),
+                None if account.storage_cleared => Ok(U256::ZERO),
+                None => self.base.storage_ref(address, index),
+            },
+            None => self.base.storage_ref(address, index),
+        }
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.base.block_hash_ref(number)
+    }
+}
+
+impl<B: DatabaseRef> Database for OverlayDB<B> {
+    type Error = B::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+}
+
+impl<B: DatabaseRef> DatabaseCommit for OverlayDB<B> {
+    fn commit(&mut self, changes: revm::primitives::HashMap<Address, Account>) {
+        for (address, account) in changes {
+            if !account.is_touched() {
+                continue;
+            }
+            let layer = self.accounts.entry(address).or_default();
+            if account.is_selfdestructed() {
+                *layer = OverlayAccount { info: None, storage: HashMap::new(), storage_cleared: true };
+                continue;
+            }
+            if account.is_created() {
+                layer.storage.clear();
+                layer.storage_cleared = true;
+            }
+            if let Some(code) = account.info.code.as_ref().filter(|code| !code.is_empty()) {
+                self.contracts.insert(account.info.code_hash, code.clone());
+            }
+            // Slots the transaction only read stay in the layer below
+            layer.storage.extend(
+                account.storage.into_iter()
+                    .filter(|(_, value)| value.is_changed())
+                    .map(|(slot, value)| (slot, value.present_value())),
+            );
+            layer.info = Some(account.info);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use revm::db::{CacheDB, EmptyDB};
+    use revm::primitives::{address, AccountStatus, EvmStorageSlot};
+
+    fn account(balance: u64, slots: &[(u64, u64)], status: AccountStatus) -> Account {
+        Account {
+            info: AccountInfo { balance: U256::from(balance), ..Default::default() },
+            storage: slots.iter()
+                .map(|&(slot, value)| (U256::from(slot), EvmStorageSlot::new_changed(U256::ZERO, U256::from(value))))
+                .collect(),
+            status: status | AccountStatus::Touched,
+        }
+    }
+
+    #[test]
+    fn changes_of_stacked_layers_merge_in_order() {
+        let alice = address!("00000000000000000000000000000000000000a1");
+        let bob = address!("00000000000000000000000000000000000000b0");
+
+        let mut below = OverlayDB::new(Arc::new(EmptyDB::default()));
+        below.commit([(alice, account(1, &[(1, 10), (2, 20)], AccountStatus::Loaded)),
+            (bob, account(5, &[(1, 1)], AccountStatus::Loaded))].into_iter().collect());
+        let below = Arc::new(below);
+
+        let mut above = OverlayDB::new(below.clone());
+        above.commit([(alice, account(2, &[(2, 21)], AccountStatus::Loaded)),
+            (bob, account(6, &[(3, 3)], AccountStatus::Created))].into_iter().collect());
+        assert_eq!(above.storage_ref(alice, U256::from(1)).unwrap(), U256::from(10));
+        assert_eq!(above.storage_ref(bob, U256::from(1)).unwrap(), U256::ZERO);
+
+        let mut changes = below.changes();
+        changes.extend(above.into_changes());
+
+        let alice = &changes.accounts[&alice];
+        assert_eq!(alice.info.as_ref().unwrap().balance, U256::from(2));
+        assert_eq!(alice.storage, [(U256::from(1), U256::from(10)), (U256::from(2), U256::from(21))].into());
+        let bob = &changes.accounts[&bob];
+        assert_eq!(bob.info.as_ref().unwrap().balance, U256::from(6));
+        assert_eq!(bob.storage, [(U256::from(3), U256::from(3))].into());
+        assert!(bob.storage_cleared);
+    }
+
+    #[test]
+    fn slots_only_read_are_not_changes() {
+        let alice = address!("00000000000000000000000000000000000000a1");
+        let mut base = CacheDB::new(EmptyDB::default());
+        base.insert_account_info(alice, AccountInfo::default());
+        base.insert_account_storage(alice, U256::from(7), U256::from(70)).unwrap();
+
+        let mut layer = OverlayDB::new(Arc::new(base));
+        let mut changed = account(1, &[(1, 10)], AccountStatus::Loaded);
+        changed.storage.insert(U256::from(7), EvmStorageSlot::new(U256::from(70)));
+        layer.commit([(alice, changed)].into_iter().collect());
+
+        let changes = layer.changes();
+        assert_eq!(changes.accounts[&alice].storage, [(U256::from(1), U256::from(10))].into());
+        assert_eq!(layer.storage_ref(alice, U256::from(7)).unwrap(), U256::from(70));
+    }
+}
@@ -0,0 +1,272 @@
+// Per-block parallelism policy
+//
+// Parallel execution has a price: thread pools, work queues and an EVM per
+// worker. A block of a handful of transactions does not repay it, and a block
+// whose transactions mostly depend on each other does not either, since an
+// optimistic scheme would throw most of its work away. The policy therefore
+// picks, before each block, between
+//
+// - sequential:  one EVM on the calling thread, transactions committed in order
+// - limited(n):  parallel on fewer threads than available
+// - parallel(n): parallel on every thread
+//
+// from the block's transaction count, the conflicts predicted from its
+// transactions and the conflict (abort) rate observed over recent blocks.
+//
+// Predicted conflicts come from the transactions alone: a transaction
+// conflicts when its sender or target is an account an earlier transaction in
+// the block writes as sender or value recipient. Observed conflicts come from
+// execution: a transaction conflicts when it read an account or slot that an
+// earlier transaction in the block wrote, i.e. it would have to be re-executed
+// after running in parallel with it. The block's coinbase, credited by every
+// transaction, is left out of both.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use revm::primitives::{Address, EvmState, TransactTo, U256};
+use revm::DatabaseRef;
+
+use crate::access::{AccessSet, Location};
+use crate::block_cache::PreparedBlock;
+
+/// Per-block decisions and their outcome, written next to the results
+pub const POLICY_LOG_FILE: &str = "williams_policy_log.txt";
+
+/// Blocks with fewer transactions run sequentially
+const MIN_PARALLEL_TXS: usize = 16;
+
+/// Fewest transactions worth giving each thread
+const MIN_TXS_PER_THREAD: usize = 4;
+
+/// Expected conflict rate above which a block runs sequentially
+const HIGH_CONFLICT_RATE: f64 = 0.5;
+
+/// Expected conflict rate above which fewer threads are used
+const MODERATE_CONFLICT_RATE: f64 = 0.15;
+
+/// Blocks the recent abort rate is averaged over
+const ABORT_WINDOW: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strategy {
+    Sequential,
+    /// Parallel on fewer threads than configured
+    Limited(usize),
+    /// Parallel on all configured threads
+    Parallel(usize),
+}
+
+impl Strategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Sequential => "sequential",
+            Strategy::Limited(_) => "limited",
+            Strategy::Parallel(_) => "parallel",
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Sequential => write!(f, "sequential"),
+            Strategy::Limited(width) | Strategy::Parallel(width) => write!(f, "{}({})", self.name(), width),
+        }
+    }
+}
+
+/// What the policy chose for a block, and from what
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decision {
+    pub strategy: Strategy,
+    pub reason: String,
+    /// Fractions of the block's transactions
+    pub predicted_conflicts: f64,
+    pub recent_abort_rate: f64,
+}
+
+/// Chooses a strategy per block and learns from the conflicts it observes
+pub struct ParallelismPolicy {
+    thread_count: usize,
+    recent_aborts: VecDeque<f64>,
+}
+
+impl ParallelismPolicy {
+    pub fn new(thread_count: usize) -> Self {
+        Self { thread_count, recent_aborts: VecDeque::with_capacity(ABORT_WINDOW) }
+    }
+
+    pub fn choose(&self, block: &PreparedBlock) -> Decision {
+        let tx_count = block.txs.len();
+        let predicted_conflicts = predicted_conflicts(block);
+        let recent_abort_rate = match self.recent_aborts.len() {
+            0 => 0.0,
+            n => self.recent_aborts.iter().sum::<f64>() / n as f64,
+        };
+        let expected = predicted_conflicts.max(recent_abort_rate);
+        let useful_threads = (tx_count / MIN_TXS_PER_THREAD).max(1);
+
+        let (strategy, reason) = if self.thread_count <= 1 {
+            (Strategy::Sequential, "single thread")
+        } else if tx_count < MIN_PARALLEL_TXS {
+            (Strategy::Sequential, "few transactions")
+        } else if expected >= HIGH_CONFLICT_RATE {
+            (Strategy::Sequential, "high conflict rate")
+        } else if expected >= MODERATE_CONFLICT_RATE {
+            let width = ((1.0 - expected) * self.thread_count as f64).ceil() as usize;
+            (self.parallel_on(width.clamp(2, self.thread_count).min(useful_threads.max(2))), "moderate conflict rate")
+        } else if useful_threads < self.thread_count {
+            (self.parallel_on(useful_threads.max(2)), "too few transactions per thread")
+        } else {
+            (Strategy::Parallel(self.thread_count), "independent transactions")
+        };
+
+        Decision { strategy, reason: reason.to_string(), predicted_conflicts, recent_abort_rate }
+    }
+
+    /// Parallel on `width` threads: limited below the thread count, and
+    /// full parallel once it takes them all
+    fn parallel_on(&self, width: usize) -> Strategy {
+        if width < self.thread_count {
+            Strategy::Limited(width)
+        } else {
+            Strategy::Parallel(self.thread_count)
+        }
+    }
+
+    /// Feed back the conflict rate a block actually had
+    pub fn observe(&mut self, conflict_rate: f64) {
+        if self.recent_aborts.len() == ABORT_WINDOW {
+            self.recent_aborts.pop_front();
+        }
+        self.recent_aborts.push_back(conflict_rate);
+    }
+}
+
+/// Fraction of transactions whose sender or target an earlier one writes
+fn predicted_conflicts(block: &PreparedBlock) -> f64 {
+    let coinbase = block.block_env.coinbase;
+    let mut written: HashSet<Address> = HashSet::new();
+    let mut conflicts = 0;
+
+    for tx in &block.txs {
+        let tx_env = &tx.tx_env;
+        let target = match tx_env.transact_to {
+            TransactTo::Call(to) => Some(to),
+            TransactTo::Create => None,
+        };
+        let reads = std::iter::once(tx_env.caller).chain(target);
+        if reads.filter(|address| *address != coinbase).any(|address| written.contains(&address)) {
+            conflicts += 1;
+        }
+
+        written.insert(tx_env.caller);
+        if let Some(to) = target.filter(|_| tx_env.value > U256::ZERO) {
+            written.insert(to);
+        }
+    }
+    conflicts as f64 / block.txs.len().max(1) as f64
+}
+
+/// Fraction of executed transactions that read what an earlier one wrote
+///
+/// `states` are in block order, `None` for invalid transactions. An account
+/// counts as written when its balance, nonce or code differ from `base`.
+pub fn observed_conflicts<B: DatabaseRef>(states: &[Option<&EvmState>], base: &B, coinbase: Address) -> f64 {
+    let mut written: HashSet<Location> = HashSet::new();
+    let mut executed = 0;
+    let mut conflicts = 0;
+
+    for state in states.iter().flatten() {
+        let access = AccessSet::of(state, base, coinbase);
+        executed += 1;
+        conflicts += access.reads_any(&written) as usize;
+        written.extend(access.writes);
+    }
+    conflicts as f64 / executed.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use revm::primitives::{BlockEnv, SpecId, TxEnv};
+
+    use crate::block_cache::PreparedTx;
+
+    /// A block of `tx_count` transfers between distinct accounts
+    fn independent_block(tx_count: usize) -> PreparedBlock {
+        let txs = (0..tx_count)
+            .map(|index| PreparedTx {
+                tx_env: TxEnv {
+                    caller: Address::left_padding_from(&[1, index as u8]),
+                    transact_to: TransactTo::Call(Address::left_padding_from(&[2, index as u8])),
+                    value: U256::from(1),
+                    ..Default::default()
+                },
+                deterministic: true,
+            })
+            .collect();
+        PreparedBlock {
+            number: 1,
+            block_env: BlockEnv::default(),
+            spec_id: SpecId::CANCUN,
+            missing_header_fields: Vec::new(),
+            txs,
+            senders: Default::default(),
+            sender_recovery_us: 0,
+            finalize: Default::default(),
+            system_calls: Default::default(),
+            timings: Default::default(),
+        }
+    }
+
+    fn choose_for(thread_count: usize, abort_rate: f64, block: &PreparedBlock) -> Strategy {
+        let mut policy = ParallelismPolicy::new(thread_count);
+        policy.observe(abort_rate);
+        policy.choose(block).strategy
+    }
+
+    fn choose(thread_count: usize, abort_rate: f64, tx_count: usize) -> Strategy {
+        choose_for(thread_count, abort_rate, &independent_block(tx_count))
+    }
+
+    #[test]
+    fn moderate_conflicts_narrow_the_pool() {
+        assert_eq!(choose(4, 0.4, 40), Strategy::Limited(3));
+        assert_eq!(choose(16, 0.3, 200), Strategy::Limited(12));
+        // Narrowed to every thread there is: that is full parallel
+        assert_eq!(choose(4, 0.2, 40), Strategy::Parallel(4));
+        assert_eq!(choose(2, 0.4, 40), Strategy::Parallel(2));
+    }
+
+    #[test]
+    fn limited_is_always_narrower_than_the_thread_count() {
+        for tx_count in (16..=160).step_by(8) {
+            let block = independent_block(tx_count);
+            for thread_count in 2..=16 {
+                for abort_rate in [0.0, 0.1, 0.15, 0.2, 0.3, 0.45] {
+                    match choose_for(thread_count, abort_rate, &block) {
+                        Strategy::Limited(width) => assert!(
+                            (2..thread_count).contains(&width),
+                            "limited({}) of {} threads, {} txs, abort rate {}", width, thread_count, tx_count, abort_rate
+                        ),
+                        Strategy::Parallel(width) => assert_eq!(width, thread_count),
+                        Strategy::Sequential => {}
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn small_or_entangled_blocks_run_sequentially() {
+        assert_eq!(choose(1, 0.0, 100), Strategy::Sequential);
+        assert_eq!(choose(8, 0.0, MIN_PARALLEL_TXS - 1), Strategy::Sequential);
+        assert_eq!(choose(8, HIGH_CONFLICT_RATE, 100), Strategy::Sequential);
+        assert_eq!(choose(8, 0.0, 100), Strategy::Parallel(8));
+    }
+}
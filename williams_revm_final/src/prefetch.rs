@@ -0,0 +1,285 @@
+// Prefetch stage: a block's state, loaded once and shared by all workers
+//
+// Before any transaction runs, every account the block is expected to touch
+// is read from the state backend in parallel, together with its code and
+// the storage slots predicted for it. The result is an immutable
+// `SharedStateCache` that each worker's copy-on-write layer reads through,
+// so workers only hold what a transaction actually writes.
+// Anything the prediction missed falls through to the backend.
+//
+// Predicted accounts are senders, call targets, access-list entries, the
+// coinbase and withdrawal/ommer recipients; predicted slots come from access
+// lists. A prestate trace names exactly what the block touches and is added
+// to the prediction when the dataset has one.
+//
+// All code passing through the layer, prefetched or not, is swapped for its
+// analyzed form from the run's `CodeCache`.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use revm::primitives::{AccountInfo, Address, Bytecode, TransactTo, B256, KECCAK_EMPTY, U256};
+use revm::DatabaseRef;
+
+use crate::block_cache::PreparedBlock;
+use crate::code_cache::CodeCache;
+use crate::prestate::PrestateDB;
+
+/// Accounts a block is expected to touch, each with its predicted slots
+#[derive(Debug, Default)]
+pub struct AccessPlan {
+    accounts: HashMap<Address, HashSet<U256>>,
+}
+
+impl AccessPlan {
+    /// Accounts and slots predicted from the block's transactions
+    pub fn for_block(block: &PreparedBlock) -> Self {
+        let mut plan = Self::default();
+        plan.add(block.block_env.coinbase);
+
+        for tx in &block.txs {
+            plan.add(tx.tx_env.caller);
+            if let TransactTo::Call(to) = tx.tx_env.transact_to {
+                plan.add(to);
+            }
+            for item in &tx.tx_env.access_list {
+                plan.accounts.entry(item.address).or_default()
+                    .extend(item.storage_keys.iter().map(|key| U256::from_be_bytes(key.0)));
+            }
+        }
+        for &(address, _) in block.finalize.withdrawals.iter().chain(&block.finalize.ommers) {
+            plan.add(address);
+        }
+
+        plan
+    }
+
+    /// Add everything a prestate trace says the block touches
+    pub fn with_prestate(mut self, prestate: &PrestateDB) -> Self {
+        for address in prestate.addresses() {
+            self.accounts.entry(address).or_default().extend(prestate.slots(&address));
+        }
+        self
+    }
+
+    fn add(&mut self, address: Address) {
+        self.accounts.entry(address).or_default();
+    }
+}
+
+/// What one prefetch loaded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrefetchSummary {
+    /// Accounts looked up, including ones that do not exist
+    pub accounts: usize,
+    pub contracts: usize,
+    pub slots: usize,
+    pub prefetch_us: u128,
+}
+
+/// Immutable read layer over a state backend, filled by `prefetch`
+pub struct SharedStateCache<'c, B> {
+    /// `None` marks an account known not to exist
+    accounts: HashMap<Address, Option<AccountInfo>>,
+    storage: HashMap<Address, HashMap<U256, U256>>,
+    contracts: HashMap<B256, Bytecode>,
+    backend: B,
+    code_cache: &'c CodeCache,
+}
+
+impl<'c, B> SharedStateCache<'c, B>
+where
+    B: DatabaseRef + Sync,
+    B::Error: std::fmt::Debug,
+{
+    /// Load everything in `plan` from `backend`, in parallel on the current pool
+    pub fn prefetch(backend: B, plan: &AccessPlan, code_cache: &'c CodeCache) -> Result<Self> {
+        type Loaded = (Address, Option<AccountInfo>, HashMap<U256, U256>);
+
+        let loaded: Vec<Loaded> = plan.accounts
+            .par_iter()
+            .map(|(&address, slots)| {
+                let mut info = backend.basic_ref(address)
+                    .map_err(|e| anyhow!("Failed to load account {}: {:?}", address, e))?;
+                if let Some(info) = info.as_mut() {
+                    if info.code.is_none() && info.code_hash != KECCAK_EMPTY {
+                        let code = backend.code_by_hash_ref(info.code_hash)
+                            .map_err(|e| anyhow!("Failed to load code of {}: {:?}", address, e))?;
+                        info.code = Some(code);
+                    }
+                    if let Some(code) = info.code.take() {
+                        info.code = Some(code_cache.analyzed(info.code_hash, code));
+                    }
+                }
+                let storage = slots.iter()
+                    .map(|&slot| {
+                        let value = backend.storage_ref(address, slot)
+                            .map_err(|e| anyhow!("Failed to load slot {} of {}: {:?}", slot, address, e))?;
+                        Ok((slot, value))
+                    })
+                    .collect::<Result<HashMap<_, _>>>()?;
+                Ok((address, info, storage))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut cache = Self {
+            accounts: HashMap::with_capacity(loaded.len()),
+            storage: HashMap::new(),
+            contracts: HashMap::new(),
+            backend,
+            code_cache,
+        };
+        for (address, info, storage) in loaded {
+            if let Some(info) = &info {
+                if let Some(code) = info.code.as_ref().filter(|code| !code.is_empty()) {
+                    cache.contracts.insert(info.code_hash, code.clone());
+                }
+            }
+            if !storage.is_empty() {
+                cache.storage.insert(address, storage);
+            }
+            cache.accounts.insert(address, info);
+        }
+        Ok(cache)
+    }
+
+    pub fn summary(&self, prefetch_us: u128) -> PrefetchSummary {
+        PrefetchSummary {
+            accounts: self.accounts.len(),
+            contracts: self.contracts.len(),
+            slots: self.storage.values().map(HashMap::len).sum(),
+            prefetch_us,
+        }
+    }
+}
+
+impl<B: DatabaseRef> DatabaseRef for SharedStateCache<'_, B> {
+    type Error = B::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.accounts.get(&address) {
+            Some(info) => Ok(info.clone()),
+            None => Ok(self.backend.basic_ref(address)?.map(|mut info| {
+                if let Some(code) = info.code.take() {
+                    info.code = Some(self.code_cache.analyzed(info.code_hash, code));
+                }
+                info
+            })),
+        }
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => Ok(self.code_cache.analyzed(code_hash, self.backend.code_by_hash_ref(code_hash)?)),
+        }
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.storage.get(&address).and_then(|slots| slots.get(&index)) {
+            Some(value) => Ok(*value),
+            None => self.backend.storage_ref(address, index),
+        }
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.backend.block_hash_ref(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+
+    use revm::db::{CacheDB, EmptyDB};
+    use revm::primitives::address;
+    use serde_json::json;
+
+    use crate::chain_config::ChainConfig;
+    use crate::prepare::prepare_block;
+
+    /// State backend that records every slot read
+    struct RecordingDB {
+        state: CacheDB<EmptyDB>,
+        slot_reads: Mutex<Vec<(Address, U256)>>,
+    }
+
+    impl DatabaseRef for RecordingDB {
+        type Error = Infallible;
+
+        fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+            self.state.basic_ref(address)
+        }
+
+        fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+            self.state.code_by_hash_ref(code_hash)
+        }
+
+        fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+            self.slot_reads.lock().unwrap().push((address, index));
+            self.state.storage_ref(address, index)
+        }
+
+        fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+            self.state.block_hash_ref(number)
+        }
+    }
+
+    #[test]
+    fn access_list_slots_are_prefetched() {
+        let token = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let block = json!({
+            "number": "0x112a880",
+            "timestamp": "0x64e9a50f",
+            "gasLimit": "0x1c9c380",
+            "miner": "0x95222290dd7278aa3ddd389cc1e1d165cc4bafe5",
+            "baseFeePerGas": "0x3b9aca00",
+            "withdrawals": [],
+            "transactions": [{
+                "type": "0x2",
+                "chainId": "0x1",
+                "nonce": "0x0",
+                "from": "0x00000000000000000000000000000000000000a1",
+                "to": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                "value": "0x0",
+                "input": "0x",
+                "gas": "0x186a0",
+                "maxFeePerGas": "0x77359400",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "accessList": [{
+                    "address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                    "storageKeys": [
+                        "0x0000000000000000000000000000000000000000000000000000000000000001",
+                        "0x0000000000000000000000000000000000000000000000000000000000000007",
+                    ],
+                }],
+                "v": "0x0",
+                "r": "0x1",
+                "s": "0x1",
+            }],
+        });
+        let prepared = prepare_block(&block, None, &ChainConfig::mainnet(), false).unwrap();
+
+        let mut state = CacheDB::new(EmptyDB::default());
+        state.insert_account_info(token, AccountInfo::default());
+        state.insert_account_storage(token, U256::from(7), U256::from(42)).unwrap();
+        let backend = RecordingDB { state, slot_reads: Mutex::new(Vec::new()) };
+
+        let code_cache = CodeCache::new();
+        let cache = SharedStateCache::prefetch(backend, &AccessPlan::for_block(&prepared), &code_cache).unwrap();
+        assert_eq!(cache.summary(0).slots, 2);
+        let mut prefetched = cache.backend.slot_reads.lock().unwrap().clone();
+        prefetched.sort();
+        assert_eq!(prefetched, vec![(token, U256::from(1)), (token, U256::from(7))]);
+
+        // Declared slots are served from the cache, not the backend
+        assert_eq!(cache.storage_ref(token, U256::from(7)).unwrap(), U256::from(42));
+        assert_eq!(cache.backend.slot_reads.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,313 @@
+// Block preparation
+//
+// Turns a block's JSON (as returned by `eth_getBlockByNumber` with full
+// transactions, or decoded from RLP/era1) into a `PreparedBlock`: block
+// environment, fork, senders resolved and transactions parsed and classified.
+// Everything an executor needs is in the result, so preparation stays out of
+// the measured execution time and a prepared block can be cached.
+
+use std::time::Instant;
+
+use anyhow::{Result, Context, bail};
+use serde_json::Value;
+
+use revm::primitives::{Address, AuthorizationList, TransactTo, TxEnv};
+
+use crate::block_cache::{PrepareTimings, PreparedBlock, PreparedTx};
+use crate::chain_config::ChainConfig;
+use crate::finalize::FinalizeInputs;
+use crate::header::{setup_block_env, validate_header};
+use crate::json_hex::{field_address, field_bytes, field_u256, field_u64};
+use crate::sender::{resolve_senders, SenderReport};
+use crate::system_calls::SystemCallInputs;
+use crate::tx_rlp::{
+    access_list, authorization_list, blob_hashes, tx_signature, tx_type, ACCESS_LIST_TX_TYPE, BLOB_TX_TYPE,
+    DYNAMIC_FEE_TX_TYPE, LEGACY_TX_TYPE, SET_CODE_TX_TYPE,
+};
+
+/// Transaction classification
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TxType {
+    Deterministic,
+    NonDeterministic,
+}
+
+/// Decode a block into everything execution needs
+///
+/// Sender recovery runs on the current rayon pool and is timed separately;
+/// nothing here is part of the measured execution time.
+pub fn prepare_block(
+    block: &Value,
+    number: Option<u64>,
+    chain: &ChainConfig,
+    verify_senders: bool,
+) -> Result<PreparedBlock> {
+    // Get transactions
+    let txs = block.get("transactions")
+        .and_then(|t| t.as_array())
+        .context("No transactions in block")?;
+    
+    // Block number from the header, else from the file name or archive index
+    let number = field_u64(block, "number")
+        .or(number)
+        .context("Block has no number")?;
+    
+    // Setup block environment and the fork active at this block
+    let block_env = setup_block_env(block, chain)?;
+    let spec_id = chain.spec_for_block(&block_env);
+    let missing_header_fields = validate_header(block, spec_id)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    
+    // Resolve senders before execution: signatures are recovered in parallel
+    // for txs without `from`, or for every tx when verifying senders
+    let recovery_start = Instant::now();
+    let needs_recovery = verify_senders || txs.iter().any(|tx| tx.get("from").is_none());
+    let (senders, sender_report) = if needs_recovery {
+        resolve_senders(txs, verify_senders)
+    } else {
+        (vec![None; txs.len()], SenderReport::default())
+    };
+    let sender_recovery_us = recovery_start.elapsed().as_micros();
+    
+    // Parse transactions, the recovered signer overrides `from`. A transaction
+    // with neither has no caller it could honestly run as, so the block fails
+    let parse_start = Instant::now();
+    let tx_envs = txs.iter()
+        .zip(senders)
+        .enumerate()
+        .map(|(tx_index, (tx, sender))| {
+            let caller = sender.or_else(|| field_address(tx, "from")).with_context(|| {
+                let reason = sender_report.failures.iter()
+                    .find(|(index, _)| *index == tx_index)
+                    .map_or("no signature", |(_, error)| error.as_str());
+                format!("Transaction {} has no sender: {}", tx_index, reason)
+            })?;
+            parse_transaction(tx, caller)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let parse_us = parse_start.elapsed().as_micros();
+    
+    // Classify transactions
+    let classify_start = Instant::now();
+    let prepared_txs: Vec<PreparedTx> = txs.iter()
+        .zip(tx_envs)
+        .map(|(tx, tx_env)| PreparedTx {
+            tx_env,
+            deterministic: classify_transaction(tx) == TxType::Deterministic,
+        })
+        .collect();
+    let timings = PrepareTimings {
+        parse_us,
+        classify_us: classify_start.elapsed().as_micros(),
+        ..Default::default()
+    };
+    
+    Ok(PreparedBlock {
+        number,
+        block_env,
+        spec_id,
+        missing_header_fields,
+        txs: prepared_txs,
+        senders: sender_report,
+        sender_recovery_us,
+        finalize: FinalizeInputs::from_block(block, spec_id)?,
+        system_calls: SystemCallInputs::from_block(block),
+        timings,
+    })
+}
+
+/// Parse transaction from JSON
+///
+/// Every field that takes part in validation or gas accounting is carried
+/// over; one the transaction type requires but the JSON lacks is an error.
+fn parse_transaction(tx: &Value, caller: Address) -> Result<TxEnv> {
+    let ty = tx_type(tx);
+    let mut tx_env = TxEnv {
+        caller,
+        gas_limit: field_u64(tx, "gas").context("Transaction has no gas limit")?,
+        transact_to: match field_address(tx, "to") {
+            Some(to) => TransactTo::Call(to),
+            None => TransactTo::Create,
+        },
+        value: field_u256(tx, "value").unwrap_or_default(),
+        data: field_bytes(tx, "input").unwrap_or_default(),
+        nonce: field_u64(tx, "nonce"),
+        ..Default::default()
+    };
+
+    match ty {
+        LEGACY_TX_TYPE | ACCESS_LIST_TX_TYPE => {
+            tx_env.gas_price = field_u256(tx, "gasPrice").context("Transaction has no gasPrice")?;
+        }
+        DYNAMIC_FEE_TX_TYPE | BLOB_TX_TYPE | SET_CODE_TX_TYPE => {
+            tx_env.gas_price = field_u256(tx, "maxFeePerGas").context("Transaction has no maxFeePerGas")?;
+            tx_env.gas_priority_fee = Some(
+                field_u256(tx, "maxPriorityFeePerGas").context("Transaction has no maxPriorityFeePerGas")?,
+            );
+        }
+        _ => bail!("Unsupported transaction type: {:#x}", ty),
+    }
+
+    // Pre-EIP-155 legacy transactions are valid on every chain
+    tx_env.chain_id = if ty == LEGACY_TX_TYPE {
+        tx_signature(tx).ok().and_then(|signature| signature.legacy_chain_id)
+    } else {
+        Some(field_u64(tx, "chainId").context("Transaction has no chainId")?)
+    };
+    if ty != LEGACY_TX_TYPE {
+        tx_env.access_list = access_list(tx)?;
+    }
+    if ty == BLOB_TX_TYPE {
+        tx_env.blob_hashes = blob_hashes(tx)?;
+        tx_env.max_fee_per_blob_gas = Some(
+            field_u256(tx, "maxFeePerBlobGas").context("Blob transaction has no maxFeePerBlobGas")?,
+        );
+    }
+    if ty == SET_CODE_TX_TYPE {
+        tx_env.authorization_list = Some(AuthorizationList::Signed(authorization_list(tx)?));
+    }
+
+    Ok(tx_env)
+}
+
+/// Classify transaction as deterministic or non-deterministic
+fn classify_transaction(tx: &Value) -> TxType {
+    // Check input data
+    if let Some(input) = tx.get("input").and_then(|i| i.as_str()) {
+        let input_data = input.trim_start_matches("0x");
+        
+        // Empty input = simple transfer = deterministic
+        if input_data.is_empty() || input_data == "0x" {
+            return TxType::Deterministic;
+        }
+        
+        // Short data (< 10 bytes) = likely simple call = deterministic
+        if input_data.len() < 20 {
+            return TxType::Deterministic;
+        }
+        
+        // Check function signatures for known deterministic patterns
+        if input_data.len() >= 8 {
+            let sig = &input_data[0..8];
+            
+            match sig {
+                "a9059cbb" => return TxType::Deterministic, // ERC20 transfer
+                "095ea7b3" => return TxType::Deterministic, // ERC20 approve
+                "23b872dd" => return TxType::Deterministic, // ERC20 transferFrom
+                "70a08231" => return TxType::Deterministic, // balanceOf
+                "18160ddd" => return TxType::Deterministic, // totalSupply
+                _ => {}
+            }
+        }
+    }
+    
+    // Contract creation is non-deterministic
+    if tx.get("to").is_none() || tx.get("to").and_then(|t| t.as_str()) == Some("") {
+        return TxType::NonDeterministic;
+    }
+    
+    // Default: non-deterministic (safe fallback)
+    TxType::NonDeterministic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use revm::db::{CacheDB, EmptyDB};
+    use revm::primitives::{address, calc_blob_gasprice, AccountInfo, U256, GAS_PER_BLOB};
+    use serde_json::json;
+
+    use crate::executor::{BlockExecutor, SequentialExecutor};
+
+    /// A Cancun block in `eth_getBlockByNumber` form holding one transaction
+    fn cancun_block(tx: Value) -> Value {
+        json!({
+            "number": "0x12a05f2",
+            "timestamp": "0x65fa0a50",
+            "gasLimit": "0x1c9c380",
+            "miner": "0x95222290dd7278aa3ddd389cc1e1d165cc4bafe5",
+            "baseFeePerGas": "0x2540be400",
+            "mixHash": "0x0d1a4a5d2fd4bd2e1a8c1c2a4e8a3cf1c0d4f2b5e1a9c3d7f6e5b4a392817160",
+            "excessBlobGas": "0x3a60000",
+            "blobGasUsed": "0x40000",
+            "withdrawals": [],
+            "transactions": [tx],
+        })
+    }
+
+    fn blob_tx() -> Value {
+        json!({
+            "type": "0x3",
+            "chainId": "0x1",
+            "nonce": "0x0",
+            "from": "0x00000000000000000000000000000000000000a1",
+            "to": "0x00000000000000000000000000000000000000b0",
+            "value": "0x0",
+            "input": "0x",
+            "gas": "0x5208",
+            "gasPrice": "0x2625a0000",
+            "maxFeePerGas": "0x6fc23ac00",
+            "maxPriorityFeePerGas": "0x3b9aca00",
+            "maxFeePerBlobGas": "0x3b9aca00",
+            "accessList": [],
+            "blobVersionedHashes": [
+                "0x01a8c3e5b8b0e2b5b3e1d0f6c4f8e6f0a9b2d4c6e8f0a1b3c5d7e9f1a3b5c7d9",
+                "0x01b4d6f8a0c2e4f6a8b0c2d4e6f8a0b2c4d6e8f0a2b4c6d8e0f2a4b6c8d0e2f4",
+            ],
+            "v": "0x1",
+            "yParity": "0x1",
+            "r": "0x1",
+            "s": "0x1",
+        })
+    }
+
+    #[test]
+    fn blob_transaction_pays_blob_gas() {
+        let block = cancun_block(blob_tx());
+        let chain = ChainConfig::mainnet();
+        let prepared = prepare_block(&block, None, &chain, false).unwrap();
+        let tx_env = &prepared.txs[0].tx_env;
+        assert_eq!(tx_env.blob_hashes.len(), 2);
+        assert_eq!(tx_env.max_fee_per_blob_gas, Some(U256::from(1_000_000_000u64)));
+        assert_eq!(tx_env.gas_priority_fee, Some(U256::from(1_000_000_000u64)));
+        assert_eq!(tx_env.chain_id, Some(1));
+        assert_eq!(tx_env.nonce, Some(0));
+
+        let sender = address!("00000000000000000000000000000000000000a1");
+        let balance = U256::from(10u64).pow(U256::from(18));
+        let mut state = CacheDB::new(EmptyDB::default());
+        state.insert_account_info(sender, AccountInfo { balance, ..Default::default() });
+
+        let execution = SequentialExecutor::new(chain).execute(&prepared, state).unwrap();
+        let outcome = execution.outcomes[0].as_ref().expect("blob transaction rejected");
+        assert_eq!(outcome.result.gas_used(), 21_000);
+
+        // Base fee plus tip on execution gas, and the blob base fee on two blobs
+        let gas_cost = U256::from(21_000u64) * U256::from(11_000_000_000u64);
+        let blob_cost = U256::from(2 * GAS_PER_BLOB) * U256::from(calc_blob_gasprice(0x3a60000));
+        assert_eq!(outcome.state[&sender].info.balance, balance - gas_cost - blob_cost);
+        assert_eq!(execution.state.accounts[&sender].info.as_ref().unwrap().balance, balance - gas_cost - blob_cost);
+    }
+
+    #[test]
+    fn transaction_without_gas_limit_is_rejected() {
+        let mut tx = blob_tx();
+        tx.as_object_mut().unwrap().remove("gas");
+        let error = prepare_block(&cancun_block(tx), None, &ChainConfig::mainnet(), false).unwrap_err();
+        assert!(format!("{:#}", error).contains("no gas limit"), "{:#}", error);
+    }
+
+    #[test]
+    fn unrecoverable_sender_fails_the_block() {
+        let mut tx = blob_tx();
+        let fields = tx.as_object_mut().unwrap();
+        fields.remove("from");
+        fields.insert("r".to_string(), json!("0x0"));
+        fields.insert("s".to_string(), json!("0x0"));
+        let error = prepare_block(&cancun_block(tx), None, &ChainConfig::mainnet(), false).unwrap_err();
+        assert!(format!("{:#}", error).contains("Transaction 0 has no sender"), "{:#}", error);
+    }
+}
@@ -0,0 +1,189 @@
+// Pre-block state from prestate traces
+//
+// `debug_traceBlockByNumber` with the prestate tracer returns, per
+// transaction, every account and storage slot the transaction touched with
+// its value before that transaction ran. The first appearance of an account
+// or slot within a block is therefore its value before the block, and the
+// union over all transactions is the state the block reads.
+//
+// `download` stores the traces as `prestate/bdf-<n>.json`. When a dataset has
+// them, blocks execute against this state; without them every account
+// starts empty.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, Context, bail};
+use serde_json::Value;
+
+use revm::primitives::{AccountInfo, Address, Bytecode, B256, U256};
+use revm::db::EmptyDB;
+use revm::DatabaseRef;
+
+use crate::json_hex::{field_bytes, field_u256, field_u64};
+
+/// Directory of prestate traces inside a dataset
+pub const PRESTATE_DIR: &str = "prestate";
+
+/// Prestate trace files of a dataset, in name order; none without `prestate/`
+pub fn trace_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let dir = root.join(PRESTATE_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Where blocks get their pre-block state
+pub struct StateSource {
+    prestate_dir: Option<PathBuf>,
+}
+
+impl StateSource {
+    /// Use the dataset's prestate traces if it has any
+    pub fn discover(root: &Path, use_prestate: bool) -> Self {
+        let dir = root.join(PRESTATE_DIR);
+        Self { prestate_dir: (use_prestate && dir.is_dir()).then_some(dir) }
+    }
+
+    pub fn prestate_dir(&self) -> Option<&Path> {
+        self.prestate_dir.as_deref()
+    }
+
+    /// State before block `number`; empty without prestate traces
+    pub fn load(&self, number: u64) -> Result<PrestateDB> {
+        let Some(dir) = &self.prestate_dir else {
+            return Ok(PrestateDB::default());
+        };
+        let path = dir.join(format!("bdf-{}.json", number));
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("No prestate trace for block {} ({})", number, path.display()))?;
+        let trace: Value = serde_json::from_str(&text)
+            .with_context(|| format!("Invalid prestate trace {}", path.display()))?;
+        PrestateDB::from_trace(&trace)
+            .with_context(|| format!("Invalid prestate trace {}", path.display()))
+    }
+}
+
+/// The state a block reads, as of before its first transaction
+///
+/// Accounts the trace never mentions do not exist, as in an empty database.
+#[derive(Debug, Default)]
+pub struct PrestateDB {
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<Address, HashMap<U256, U256>>,
+    contracts: HashMap<B256, Bytecode>,
+}
+
+impl PrestateDB {
+    /// Merge a block's per-transaction prestate traces
+    ///
+    /// Accepts the JSON-RPC envelope or the bare result array; each entry is
+    /// `{ txHash, result: { address: account } }` or the account map itself.
+    pub fn from_trace(trace: &Value) -> Result<Self> {
+        let entries = trace.get("result").unwrap_or(trace)
+            .as_array()
+            .context("Prestate trace is not an array")?;
+
+        let mut state = Self::default();
+        for (index, entry) in entries.iter().enumerate() {
+            if let Some(error) = entry.get("error") {
+                bail!("Trace of transaction {} failed: {}", index, error);
+            }
+            let accounts = entry.get("result").unwrap_or(entry)
+                .as_object()
+                .with_context(|| format!("Trace of transaction {} has no accounts", index))?;
+            for (address, account) in accounts {
+                let address: Address = address.parse()
+                    .with_context(|| format!("Invalid address {} in prestate trace", address))?;
+                state.merge_account(address, account)
+                    .with_context(|| format!("Invalid prestate of {}", address))?;
+            }
+        }
+        Ok(state)
+    }
+
+    /// Keep the first value seen for the account and each of its slots
+    fn merge_account(&mut self, address: Address, account: &Value) -> Result<()> {
+        if !self.accounts.contains_key(&address) {
+            let balance = field_u256(account, "balance").unwrap_or_default();
+            // geth reports the nonce as a number, other clients as a quantity
+            let nonce = account["nonce"].as_u64()
+                .or_else(|| field_u64(account, "nonce"))
+                .unwrap_or_default();
+            let info = match field_bytes(account, "code").filter(|code| !code.is_empty()) {
+                Some(code) => {
+                    let bytecode = Bytecode::new_raw(code);
+                    let code_hash = bytecode.hash_slow();
+                    self.contracts.insert(code_hash, bytecode.clone());
+                    AccountInfo::new(balance, nonce, code_hash, bytecode)
+                }
+                None => AccountInfo { balance, nonce, ..Default::default() },
+            };
+            self.accounts.insert(address, info);
+        }
+
+        if let Some(slots) = account.get("storage").and_then(Value::as_object) {
+            let storage = self.storage.entry(address).or_default();
+            for (slot, value) in slots {
+                let slot = parse_word(slot).with_context(|| format!("Invalid storage slot {}", slot))?;
+                let value = value.as_str().and_then(parse_word)
+                    .with_context(|| format!("Invalid value of storage slot {}", slot))?;
+                storage.entry(slot).or_insert(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Accounts the block touches
+    pub fn addresses(&self) -> impl Iterator<Item = Address> + '_ {
+        self.accounts.keys().copied()
+    }
+
+    /// Storage slots of `address` the block touches
+    pub fn slots(&self, address: &Address) -> impl Iterator<Item = U256> + '_ {
+        self.storage.get(address).into_iter().flat_map(|slots| slots.keys().copied())
+    }
+}
+
+impl DatabaseRef for PrestateDB {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.accounts.get(&address).cloned())
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Ok(self.contracts.get(&code_hash).cloned().unwrap_or_default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(self.storage.get(&address)
+            .and_then(|slots| slots.get(&index))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        // Traces carry no block hashes; answer as the empty database does
+        EmptyDB::default().block_hash_ref(number)
+    }
+}
+
+/// A 0x-prefixed storage key or value
+fn parse_word(hex: &str) -> Option<U256> {
+    let digits = hex.trim_start_matches("0x");
+    if digits.is_empty() {
+        return Some(U256::ZERO);
+    }
+    U256::from_str_radix(digits, 16).ok()
+}
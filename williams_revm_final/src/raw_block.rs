@@ -0,0 +1,565 @@
+// Raw RLP blocks: decoding into the JSON-RPC block shape
+//
+// `debug_getRawBlock` and Era1 archives carry blocks as consensus RLP
+// (header, transactions, ommers and, from Shanghai, withdrawals). Rather
+// than teaching every stage a second input format, a raw block is decoded
+// into the same JSON object `eth_getBlockByNumber` with full transactions
+// returns. Senders are not part of the encoding; they are recovered from
+// the signatures before execution. Ommer headers are kept in `uncleHeaders`
+// so ommer rewards can be paid. Receipts, which Era1 stores beside each
+// block, decode into the consensus fields of `eth_getTransactionReceipt`.
+
+use anyhow::{Result, Context, bail};
+use alloy_rlp::Header;
+use serde_json::{json, Map, Value};
+
+use revm::primitives::{keccak256, U256};
+
+use crate::json_hex::{field_u256, field_u64};
+use crate::tx_rlp::{
+    ACCESS_LIST_TX_TYPE, BLOB_TX_TYPE, DYNAMIC_FEE_TX_TYPE, LEGACY_TX_TYPE, SET_CODE_TX_TYPE,
+};
+
+/// How a field is rendered in JSON-RPC
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Minimal hex number ("0x0", "0x1b")
+    Quantity,
+    /// Hex byte string, leading zeros kept
+    Data,
+}
+
+use Kind::{Data, Quantity};
+
+/// Header fields in consensus order; fork fields follow the base fifteen
+const HEADER_FIELDS: [(&str, Kind); 21] = [
+    ("parentHash", Data), ("sha3Uncles", Data), ("miner", Data), ("stateRoot", Data),
+    ("transactionsRoot", Data), ("receiptsRoot", Data), ("logsBloom", Data),
+    ("difficulty", Quantity), ("number", Quantity), ("gasLimit", Quantity),
+    ("gasUsed", Quantity), ("timestamp", Quantity), ("extraData", Data),
+    ("mixHash", Data), ("nonce", Data),
+    ("baseFeePerGas", Quantity), ("withdrawalsRoot", Data), ("blobGasUsed", Quantity),
+    ("excessBlobGas", Quantity), ("parentBeaconBlockRoot", Data), ("requestsHash", Data),
+];
+
+/// One decoded RLP item, borrowing from the input
+#[derive(Clone, Copy)]
+pub struct Rlp<'a> {
+    /// Complete encoding, header included
+    pub raw: &'a [u8],
+    payload: &'a [u8],
+    list: bool,
+}
+
+impl<'a> Rlp<'a> {
+    /// Decode the single item that makes up `data`
+    pub fn decode(data: &'a [u8]) -> Result<Self> {
+        let mut buf = data;
+        let item = Self::next(&mut buf)?;
+        if !buf.is_empty() {
+            bail!("{} trailing bytes after RLP item", buf.len());
+        }
+        Ok(item)
+    }
+
+    /// Decode the item at the front of `buf` and advance past it
+    fn next(buf: &mut &'a [u8]) -> Result<Self> {
+        let start = *buf;
+        let header = Header::decode(buf).map_err(|e| anyhow::anyhow!("Invalid RLP: {}", e))?;
+        let (payload, rest) = buf.split_at(header.payload_length);
+        *buf = rest;
+        Ok(Self {
+            raw: &start[..start.len() - rest.len()],
+            payload,
+            list: header.list,
+        })
+    }
+
+    /// Items of a list
+    pub fn items(&self) -> Result<Vec<Rlp<'a>>> {
+        if !self.list {
+            bail!("Expected an RLP list, found a string");
+        }
+        let mut buf = self.payload;
+        let mut items = Vec::new();
+        while !buf.is_empty() {
+            items.push(Self::next(&mut buf)?);
+        }
+        Ok(items)
+    }
+
+    /// Contents of a string
+    pub fn bytes(&self) -> Result<&'a [u8]> {
+        if self.list {
+            bail!("Expected an RLP string, found a list");
+        }
+        Ok(self.payload)
+    }
+
+    fn json(&self, kind: Kind) -> Result<Value> {
+        let bytes = self.bytes()?;
+        Ok(Value::String(match kind {
+            Quantity => {
+                let value = U256::try_from_be_slice(bytes).context("Quantity wider than 256 bits")?;
+                format!("{:#x}", value)
+            }
+            Data => format!("0x{}", hex::encode(bytes)),
+        }))
+    }
+}
+
+/// Parse hex text or raw bytes, whichever a file holds
+///
+/// `debug_getRawBlock` returns a 0x-prefixed hex string; saved archives are
+/// usually plain binary.
+pub fn raw_bytes(contents: Vec<u8>) -> Result<Vec<u8>> {
+    let text = std::str::from_utf8(&contents).ok().map(str::trim);
+    match text {
+        Some(text) if text.starts_with("0x") => {
+            hex::decode(&text[2..]).context("Invalid hex in raw block")
+        }
+        _ => Ok(contents),
+    }
+}
+
+/// Decode an RLP block into a JSON-RPC block object
+pub fn decode_block(data: &[u8]) -> Result<Value> {
+    let block = Rlp::decode(data).context("Invalid block RLP")?;
+    let parts = block.items()?;
+    if parts.len() < 3 {
+        bail!("Block RLP has {} parts, expected at least 3", parts.len());
+    }
+
+    let mut out = decode_header(&parts[0])?;
+    let number = field_u64(&out, "number").unwrap_or_default();
+    let base_fee = field_u256(&out, "baseFeePerGas");
+
+    let txs = parts[1].items()?
+        .iter()
+        .enumerate()
+        .map(|(index, tx)| {
+            decode_transaction(tx, base_fee).map(|mut tx| {
+                tx["blockNumber"] = json!(format!("{:#x}", number));
+                tx["transactionIndex"] = json!(format!("{:#x}", index));
+                tx
+            })
+            .with_context(|| format!("Transaction {}", index))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let ommers = parts[2].items()?
+        .iter()
+        .map(decode_header)
+        .collect::<Result<Vec<_>>>()
+        .context("Invalid ommer header")?;
+
+    out["size"] = json!(format!("{:#x}", data.len()));
+    out["transactions"] = Value::Array(txs);
+    out["uncles"] = Value::Array(ommers.iter().map(|o| o["hash"].clone()).collect());
+    out["uncleHeaders"] = Value::Array(ommers);
+
+    if let Some(withdrawals) = parts.get(3) {
+        out["withdrawals"] = Value::Array(
+            withdrawals.items()?
+                .iter()
+                .map(decode_withdrawal)
+                .collect::<Result<Vec<_>>>()?,
+        );
+    }
+
+    Ok(out)
+}
+
+/// Header fields plus the block hash
+fn decode_header(header: &Rlp) -> Result<Value> {
+    let fields = header.items()?;
+    if fields.len() < 15 || fields.len() > HEADER_FIELDS.len() {
+        bail!("Header has {} fields", fields.len());
+    }
+
+    let mut out = Map::new();
+    for (field, (name, kind)) in fields.iter().zip(HEADER_FIELDS) {
+        out.insert(name.to_string(), field.json(kind).with_context(|| format!("Header field {}", name))?);
+    }
+    out.insert("hash".to_string(), json!(keccak256(header.raw).to_string()));
+    Ok(Value::Object(out))
+}
+
+/// Transaction fields, its hash and, for 1559-style txs, the effective gas price
+fn decode_transaction(tx: &Rlp, base_fee: Option<U256>) -> Result<Value> {
+    // Legacy txs are plain lists; typed txs are strings holding `type || rlp`
+    let (ty, envelope, body) = if tx.list {
+        (LEGACY_TX_TYPE, tx.raw, *tx)
+    } else {
+        let envelope = tx.bytes()?;
+        let (&ty, list) = envelope.split_first().context("Empty typed transaction")?;
+        (ty as u64, envelope, Rlp::decode(list)?)
+    };
+    let fields = body.items()?;
+
+    let layout: &[(&str, Kind)] = match ty {
+        LEGACY_TX_TYPE => &[
+            ("nonce", Quantity), ("gasPrice", Quantity), ("gas", Quantity), ("to", Data),
+            ("value", Quantity), ("input", Data), ("v", Quantity), ("r", Quantity), ("s", Quantity),
+        ],
+        ACCESS_LIST_TX_TYPE => &[
+            ("chainId", Quantity), ("nonce", Quantity), ("gasPrice", Quantity), ("gas", Quantity),
+            ("to", Data), ("value", Quantity), ("input", Data),
+        ],
+        DYNAMIC_FEE_TX_TYPE | BLOB_TX_TYPE | SET_CODE_TX_TYPE => &[
+            ("chainId", Quantity), ("nonce", Quantity), ("maxPriorityFeePerGas", Quantity),
+            ("maxFeePerGas", Quantity), ("gas", Quantity), ("to", Data), ("value", Quantity),
+            ("input", Data),
+        ],
+        _ => bail!("Unsupported transaction type: {:#x}", ty),
+    };
+
+    let mut out = Map::new();
+    out.insert("type".to_string(), json!(format!("{:#x}", ty)));
+    out.insert("hash".to_string(), json!(keccak256(envelope).to_string()));
+
+    let mut fields = fields.into_iter();
+    let mut next = |name: &str| fields.next().with_context(|| format!("Transaction has no {}", name));
+    for (name, kind) in layout {
+        let field = next(name)?;
+        let value = match (*name, field.bytes()?.is_empty()) {
+            ("to", true) => Value::Null,
+            _ => field.json(*kind)?,
+        };
+        out.insert(name.to_string(), value);
+    }
+
+    if ty != LEGACY_TX_TYPE {
+        out.insert("accessList".to_string(), decode_access_list(&next("accessList")?)?);
+        if ty == BLOB_TX_TYPE {
+            out.insert("maxFeePerBlobGas".to_string(), next("maxFeePerBlobGas")?.json(Quantity)?);
+            let hashes = next("blobVersionedHashes")?.items()?
+                .iter()
+                .map(|hash| hash.json(Data))
+                .collect::<Result<Vec<_>>>()?;
+            out.insert("blobVersionedHashes".to_string(), Value::Array(hashes));
+        }
+        if ty == SET_CODE_TX_TYPE {
+            out.insert("authorizationList".to_string(), decode_authorizations(&next("authorizationList")?)?);
+        }
+        let parity = next("yParity")?.json(Quantity)?;
+        out.insert("v".to_string(), parity.clone());
+        out.insert("yParity".to_string(), parity);
+        out.insert("r".to_string(), next("r")?.json(Quantity)?);
+        out.insert("s".to_string(), next("s")?.json(Quantity)?);
+    }
+
+    let mut out = Value::Object(out);
+
+    // JSON-RPC reports what the tx actually paid per gas; execution reads it
+    if ty >= DYNAMIC_FEE_TX_TYPE {
+        let max_fee = field_u256(&out, "maxFeePerGas").unwrap_or_default();
+        let priority = field_u256(&out, "maxPriorityFeePerGas").unwrap_or_default();
+        let price = base_fee.map_or(max_fee, |base_fee| max_fee.min(base_fee.saturating_add(priority)));
+        out["gasPrice"] = json!(format!("{:#x}", price));
+    }
+
+    Ok(out)
+}
+
+fn decode_access_list(list: &Rlp) -> Result<Value> {
+    let entries = list.items()?
+        .iter()
+        .map(|entry| {
+            let parts = entry.items()?;
+            let [address, keys] = parts.as_slice() else {
+                bail!("Access list entry has {} parts", parts.len());
+            };
+            let keys = keys.items()?
+                .iter()
+                .map(|key| key.json(Data))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(json!({ "address": address.json(Data)?, "storageKeys": keys }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Value::Array(entries))
+}
+
+fn decode_authorizations(list: &Rlp) -> Result<Value> {
+    const FIELDS: [(&str, Kind); 6] = [
+        ("chainId", Quantity), ("address", Data), ("nonce", Quantity),
+        ("yParity", Quantity), ("r", Quantity), ("s", Quantity),
+    ];
+    let auths = list.items()?
+        .iter()
+        .map(|auth| {
+            let parts = auth.items()?;
+            if parts.len() != FIELDS.len() {
+                bail!("Authorization has {} parts", parts.len());
+            }
+            let mut out = Map::new();
+            for (part, (name, kind)) in parts.iter().zip(FIELDS) {
+                out.insert(name.to_string(), part.json(kind)?);
+            }
+            Ok(Value::Object(out))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Value::Array(auths))
+}
+
+/// Decode a block's receipt list into JSON-RPC receipts
+///
+/// Only consensus fields are encoded; `gasUsed` follows from consecutive
+/// cumulative values, and pre-Byzantium receipts carry a state `root`
+/// instead of a `status`.
+pub fn decode_receipts(data: &[u8]) -> Result<Vec<Value>> {
+    let mut previous_cumulative = U256::ZERO;
+    let mut log_index = 0usize;
+    Rlp::decode(data)?
+        .items()?
+        .iter()
+        .enumerate()
+        .map(|(index, receipt)| {
+            let mut out = decode_receipt(receipt, &mut log_index).with_context(|| format!("Receipt {}", index))?;
+            let cumulative = field_u256(&out, "cumulativeGasUsed").unwrap_or_default();
+            let gas_used = cumulative.checked_sub(previous_cumulative)
+                .with_context(|| format!("Receipt {}: cumulative gas used decreases", index))?;
+            previous_cumulative = cumulative;
+            out["gasUsed"] = json!(format!("{:#x}", gas_used));
+            out["transactionIndex"] = json!(format!("{:#x}", index));
+            Ok(out)
+        })
+        .collect()
+}
+
+fn decode_receipt(receipt: &Rlp, log_index: &mut usize) -> Result<Value> {
+    // Like transactions, typed receipts are strings holding `type || rlp`
+    let (ty, body) = if receipt.list {
+        (LEGACY_TX_TYPE, *receipt)
+    } else {
+        let (&ty, list) = receipt.bytes()?.split_first().context("Empty typed receipt")?;
+        (ty as u64, Rlp::decode(list)?)
+    };
+    let parts = body.items()?;
+    let [outcome, cumulative, bloom, logs] = parts.as_slice() else {
+        bail!("Receipt has {} parts", parts.len());
+    };
+
+    let mut out = Map::new();
+    out.insert("type".to_string(), json!(format!("{:#x}", ty)));
+    if outcome.bytes()?.len() == 32 {
+        out.insert("root".to_string(), outcome.json(Data)?);
+    } else {
+        out.insert("status".to_string(), outcome.json(Quantity)?);
+    }
+    out.insert("cumulativeGasUsed".to_string(), cumulative.json(Quantity)?);
+    out.insert("logsBloom".to_string(), bloom.json(Data)?);
+
+    let logs = logs.items()?
+        .iter()
+        .map(|log| {
+            let parts = log.items()?;
+            let [address, topics, data] = parts.as_slice() else {
+                bail!("Log has {} parts", parts.len());
+            };
+            let topics = topics.items()?
+                .iter()
+                .map(|topic| topic.json(Data))
+                .collect::<Result<Vec<_>>>()?;
+            let out = json!({
+                "address": address.json(Data)?,
+                "topics": topics,
+                "data": data.json(Data)?,
+                "logIndex": format!("{:#x}", *log_index),
+            });
+            *log_index += 1;
+            Ok(out)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    out.insert("logs".to_string(), Value::Array(logs));
+    Ok(Value::Object(out))
+}
+
+fn decode_withdrawal(withdrawal: &Rlp) -> Result<Value> {
+    let parts = withdrawal.items()?;
+    let [index, validator_index, address, amount] = parts.as_slice() else {
+        bail!("Withdrawal has {} parts", parts.len());
+    };
+    Ok(json!({
+        "index": index.json(Quantity)?,
+        "validatorIndex": validator_index.json(Quantity)?,
+        "address": address.json(Data)?,
+        "amount": amount.json(Quantity)?,
+    }))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use revm::primitives::{b256, B256};
+
+    use crate::sender::recover_sender;
+    use crate::tx_rlp::tests::{first_mainnet_transaction, sign, typed_transactions, EXAMPLE_SIGNER};
+    use crate::tx_rlp::{encode_signed, RlpList};
+
+    pub(crate) const GENESIS_HASH: B256 = b256!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3");
+
+    /// Consensus encoding of the mainnet genesis header, field by field
+    pub(crate) fn genesis_header() -> Vec<u8> {
+        let empty_root = b256!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+        let mut header = RlpList::default();
+        header.push(&B256::ZERO);
+        header.push(&b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"));
+        header.push(&[0u8; 20][..]);
+        header.push(&b256!("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544"));
+        header.push(&empty_root);
+        header.push(&empty_root);
+        header.push(&[0u8; 256][..]);
+        header.push(&0x4_0000_0000u64);
+        header.push(&0u64);
+        header.push(&5000u64);
+        header.push(&0u64);
+        header.push(&0u64);
+        header.push(&b256!("11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa"));
+        header.push(&B256::ZERO);
+        header.push(&hex::decode("0000000000000042").unwrap()[..]);
+        header.finish()
+    }
+
+    /// A block of `header` with the given transaction list and ommers
+    pub(crate) fn block(header: &[u8], txs: &[Vec<u8>], ommers: &[Vec<u8>]) -> Vec<u8> {
+        let mut tx_list = RlpList::default();
+        for tx in txs {
+            tx_list.push_raw(tx);
+        }
+        let mut ommer_list = RlpList::default();
+        for ommer in ommers {
+            ommer_list.push_raw(ommer);
+        }
+        let mut block = RlpList::default();
+        block.push_raw(header);
+        block.push_raw(&tx_list.finish());
+        block.push_raw(&ommer_list.finish());
+        block.finish()
+    }
+
+    /// Block list item of a signed transaction: legacy lists go in as they
+    /// are, typed envelopes as byte strings
+    pub(crate) fn list_item(encoded: &[u8]) -> Vec<u8> {
+        if encoded[0] >= 0xc0 {
+            encoded.to_vec()
+        } else {
+            alloy_rlp::encode(encoded)
+        }
+    }
+
+    #[test]
+    fn genesis_decodes_to_its_hash() {
+        let decoded = decode_block(&block(&genesis_header(), &[], &[])).unwrap();
+        assert_eq!(decoded["hash"], json!(GENESIS_HASH));
+        assert_eq!(decoded["number"], json!("0x0"));
+        assert_eq!(decoded["difficulty"], json!("0x400000000"));
+        assert_eq!(decoded["gasLimit"], json!("0x1388"));
+        assert_eq!(decoded["nonce"], json!("0x0000000000000042"));
+        assert_eq!(decoded["miner"], json!("0x0000000000000000000000000000000000000000"));
+        assert_eq!(decoded["transactions"], json!([]));
+        assert!(decoded.get("withdrawals").is_none());
+    }
+
+    #[test]
+    fn transactions_of_every_type_round_trip() {
+        let mut txs = vec![first_mainnet_transaction()];
+        txs.extend(typed_transactions().into_iter().map(sign));
+        let encoded: Vec<Vec<u8>> = txs.iter().map(|tx| encode_signed(tx).unwrap()).collect();
+        let items: Vec<Vec<u8>> = encoded.iter().map(|tx| list_item(tx)).collect();
+
+        let decoded = decode_block(&block(&genesis_header(), &items, &[genesis_header()])).unwrap();
+        assert_eq!(decoded["uncles"], json!([GENESIS_HASH]));
+        let decoded_txs = decoded["transactions"].as_array().unwrap();
+        assert_eq!(decoded_txs.len(), txs.len());
+        assert_eq!(
+            decoded_txs[0]["hash"],
+            json!("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060")
+        );
+
+        for (index, (tx, original)) in decoded_txs.iter().zip(&encoded).enumerate() {
+            assert_eq!(&encode_signed(tx).unwrap(), original, "transaction {}", index);
+            assert_eq!(tx["hash"], json!(keccak256(original)), "transaction {}", index);
+            assert_eq!(tx["transactionIndex"], json!(format!("{:#x}", index)));
+        }
+        for tx in &decoded_txs[1..] {
+            assert_eq!(recover_sender(tx).unwrap(), EXAMPLE_SIGNER);
+        }
+        // No base fee in the header: 1559-style transactions pay their fee cap
+        for tx in &decoded_txs[2..] {
+            assert_eq!(tx["gasPrice"], tx["maxFeePerGas"]);
+        }
+        assert_eq!(decoded_txs[3]["blobVersionedHashes"], txs[3]["blobVersionedHashes"]);
+        assert_eq!(decoded_txs[4]["authorizationList"], txs[4]["authorizationList"]);
+        assert_eq!(decoded_txs[1]["accessList"], txs[1]["accessList"]);
+    }
+
+    /// A receipt as it appears in a block's receipt list
+    pub(crate) fn receipt(ty: u8, outcome: &[u8], cumulative_gas: u64, logs: &[Vec<u8>]) -> Vec<u8> {
+        let mut log_list = RlpList::default();
+        for log in logs {
+            log_list.push_raw(log);
+        }
+        let mut fields = RlpList::default();
+        fields.push(outcome);
+        fields.push(&cumulative_gas);
+        fields.push(&[0u8; 256][..]);
+        fields.push_raw(&log_list.finish());
+        let list = fields.finish();
+        if ty == 0 {
+            list
+        } else {
+            list_item(&[&[ty][..], &list].concat())
+        }
+    }
+
+    #[test]
+    fn receipts_of_every_era_decode() {
+        let mut topics = RlpList::default();
+        topics.push(&B256::repeat_byte(0x11));
+        let mut log = RlpList::default();
+        log.push(&[0xaa; 20][..]);
+        log.push_raw(&topics.finish());
+        log.push(&[1u8, 2, 3][..]);
+        let log = log.finish();
+
+        // Pre-Byzantium state root, Byzantium status, then a failed typed transaction
+        let mut list = RlpList::default();
+        list.push_raw(&receipt(0, &[0x55; 32], 21_000, &[]));
+        list.push_raw(&receipt(0, &[1], 74_000, std::slice::from_ref(&log)));
+        list.push_raw(&receipt(2, &[], 100_000, &[log]));
+        let receipts = decode_receipts(&list.finish()).unwrap();
+
+        assert_eq!(receipts.len(), 3);
+        assert_eq!(receipts[0]["root"], json!(B256::repeat_byte(0x55)));
+        assert!(receipts[0].get("status").is_none());
+        assert_eq!(receipts[1]["status"], json!("0x1"));
+        assert_eq!(receipts[2]["status"], json!("0x0"));
+        assert_eq!(receipts[2]["type"], json!("0x2"));
+        let gas_used: Vec<_> = receipts.iter().map(|r| r["gasUsed"].clone()).collect();
+        assert_eq!(gas_used, vec![json!("0x5208"), json!("0xcf08"), json!("0x6590")]);
+        assert_eq!(receipts[2]["transactionIndex"], json!("0x2"));
+        assert_eq!(receipts[1]["logs"], json!([{
+            "address": format!("0x{}", "aa".repeat(20)),
+            "topics": [B256::repeat_byte(0x11)],
+            "data": "0x010203",
+            "logIndex": "0x0",
+        }]));
+        assert_eq!(receipts[2]["logs"][0]["logIndex"], json!("0x1"));
+
+        let mut list = RlpList::default();
+        list.push_raw(&receipt(0, &[1], 50_000, &[]));
+        list.push_raw(&receipt(0, &[1], 21_000, &[]));
+        assert!(decode_receipts(&list.finish()).is_err());
+    }
+
+    #[test]
+    fn raw_bytes_accepts_hex_and_binary() {
+        let raw = block(&genesis_header(), &[], &[]);
+        let text = format!("0x{}\n", hex::encode(&raw));
+        assert_eq!(raw_bytes(text.into_bytes()).unwrap(), raw);
+        assert_eq!(raw_bytes(raw.clone()).unwrap(), raw);
+        assert!(decode_block(&raw[..raw.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+// Gas-weighted scheduling of a block's transactions
+//
+// Splitting transactions evenly by count leaves threads idle whenever one
+// transaction costs far more than the rest: the thread that drew it is still
+// running long after the others ran dry. Instead every transaction gets an
+// estimated cost, transactions are ordered most expensive first and cut into
+// chunks of roughly equal cost, and workers take the next chunk from a shared
+// queue as soon as they finish one. Long transactions thus start first and
+// alone, while cheap ones travel in batches so the queue is not touched per
+// transfer.
+//
+// The estimate is the gas used by earlier calls to the same contract with the
+// same 4-byte selector, measured during this run and kept between runs in a
+// gas profile; a call never seen before is estimated at its gas limit.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use revm::primitives::{Address, FixedBytes, TransactTo, TxEnv};
+
+use crate::block_cache::PreparedTx;
+
+/// Gas profile written next to the results and read back by later runs
+pub const GAS_PROFILE_FILE: &str = "williams_gas_profile.json";
+
+/// Chunks per thread to aim for, so the queue can even out misestimates
+const CHUNKS_PER_THREAD: usize = 8;
+
+/// Smallest chunk worth a trip to the queue, about ten transfers
+const MIN_CHUNK_GAS: u64 = 200_000;
+
+/// Contract and selector a call is profiled under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct CallKey {
+    contract: Address,
+    /// `None` for calls with less than four bytes of input
+    selector: Option<FixedBytes<4>>,
+}
+
+impl CallKey {
+    /// Contract creations have no key and are always estimated by gas limit
+    fn of(tx_env: &TxEnv) -> Option<Self> {
+        let TransactTo::Call(contract) = tx_env.transact_to else {
+            return None;
+        };
+        let selector = tx_env.data.get(..4).map(FixedBytes::from_slice);
+        Some(Self { contract, selector })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct GasSamples {
+    calls: u64,
+    gas_used: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProfileEntry {
+    #[serde(flatten)]
+    key: CallKey,
+    #[serde(flatten)]
+    samples: GasSamples,
+}
+
+/// Measured gas use per contract and selector
+#[derive(Debug, Default)]
+pub struct GasProfile {
+    calls: HashMap<CallKey, GasSamples>,
+}
+
+impl GasProfile {
+    /// Read a profile saved by an earlier run; empty if there is none
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw = fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let entries: Vec<ProfileEntry> = serde_json::from_slice(&raw)
+            .with_context(|| format!("Invalid gas profile {}", path.display()))?;
+        Ok(Self {
+            calls: entries.into_iter().map(|entry| (entry.key, entry.samples)).collect(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut entries: Vec<ProfileEntry> = self.calls.iter()
+            .map(|(&key, &samples)| ProfileEntry { key, samples })
+            .collect();
+        entries.sort_by_key(|entry| (entry.key.contract, entry.key.selector));
+        fs::write(path, serde_json::to_vec_pretty(&entries)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Contracts and selectors with measurements
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Add the gas a transaction used
+    pub fn record(&mut self, tx_env: &TxEnv, gas_used: u64) {
+        if let Some(key) = CallKey::of(tx_env) {
+            let samples = self.calls.entry(key).or_default();
+            samples.calls += 1;
+            samples.gas_used += gas_used;
+        }
+    }
+
+    /// Expected gas of a transaction, and whether it came from measurements
+    fn estimate(&self, tx_env: &TxEnv) -> (u64, bool) {
+        let measured = CallKey::of(tx_env)
+            .and_then(|key| self.calls.get(&key))
+            .filter(|samples| samples.calls > 0);
+        match measured {
+            Some(samples) => ((samples.gas_used / samples.calls).min(tx_env.gas_limit), true),
+            None => (tx_env.gas_limit, false),
+        }
+    }
+}
+
+/// How one class of a block's transactions was scheduled
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScheduleSummary {
+    pub chunks: usize,
+    /// Transactions estimated from measured gas rather than their gas limit
+    pub profiled: usize,
+    /// Estimated gas of the most expensive transaction and of all of them
+    pub largest_gas: u64,
+    pub total_gas: u64,
+}
+
+impl ScheduleSummary {
+    /// Fold in another class of the same block
+    pub fn combine(&mut self, other: &ScheduleSummary) {
+        self.chunks += other.chunks;
+        self.profiled += other.profiled;
+        self.largest_gas = self.largest_gas.max(other.largest_gas);
+        self.total_gas += other.total_gas;
+    }
+}
+
+/// Chunks of transaction indices, most expensive first, handed out in order
+pub struct Schedule {
+    chunks: Vec<Vec<usize>>,
+    next: AtomicUsize,
+    summary: ScheduleSummary,
+}
+
+impl Schedule {
+    pub fn plan(txs: &[&PreparedTx], profile: &GasProfile, thread_count: usize) -> Self {
+        let mut estimates: Vec<(usize, u64)> = Vec::with_capacity(txs.len());
+        let mut profiled = 0;
+        for (index, tx) in txs.iter().enumerate() {
+            let (gas, measured) = profile.estimate(&tx.tx_env);
+            profiled += measured as usize;
+            estimates.push((index, gas));
+        }
+        estimates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let total_gas: u64 = estimates.iter().map(|&(_, gas)| gas).sum();
+        let target = (total_gas / (thread_count.max(1) * CHUNKS_PER_THREAD) as u64).max(MIN_CHUNK_GAS);
+
+        let mut chunks = Vec::new();
+        let mut chunk = Vec::new();
+        let mut chunk_gas = 0;
+        for &(index, gas) in &estimates {
+            chunk.push(index);
+            chunk_gas += gas;
+            if chunk_gas >= target {
+                chunks.push(std::mem::take(&mut chunk));
+                chunk_gas = 0;
+            }
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+
+        let summary = ScheduleSummary {
+            chunks: chunks.len(),
+            profiled,
+            largest_gas: estimates.first().map_or(0, |&(_, gas)| gas),
+            total_gas,
+        };
+        Self { chunks, next: AtomicUsize::new(0), summary }
+    }
+
+    /// The next chunk nobody has taken yet
+    pub fn next_chunk(&self) -> Option<&[usize]> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        self.chunks.get(index).map(Vec::as_slice)
+    }
+
+    pub fn summary(&self) -> &ScheduleSummary {
+        &self.summary
+    }
+}
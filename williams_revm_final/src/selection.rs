@@ -0,0 +1,290 @@
+// Block selection for partial runs
+//
+// A selection narrows a dataset down by block number, so the same options
+// pick the same blocks from a directory of JSON files, a JSONL file, RLP
+// files, era1 archives or the block cache:
+//
+// - a range:   `--blocks N..M` (inclusive, either end may be left open)
+// - sampling:  `--every N` keeps every Nth block number, counted from the
+//              start of the range; `--sample F --seed S` keeps a fraction F
+// - a list:    `--block-list <file>` keeps only the numbers in the file
+//
+// Criteria combine: a block is selected when it passes all of them. Random
+// sampling hashes the seed with the block number instead of drawing from a
+// generator, so whether a block is sampled depends on neither dataset order
+// nor format, and a seed always selects the same blocks.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Result, Context, bail};
+
+/// Seed of `--sample` when none is given
+pub const DEFAULT_SEED: u64 = 1;
+
+/// Inclusive range of block numbers, open at either end
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BlockRange {
+    pub first: Option<u64>,
+    pub last: Option<u64>,
+}
+
+impl BlockRange {
+    pub fn contains(&self, number: u64) -> bool {
+        self.first.is_none_or(|first| number >= first) && self.last.is_none_or(|last| number <= last)
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.first.is_none() && self.last.is_none()
+    }
+}
+
+impl FromStr for BlockRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bound = |text: &str| -> Result<Option<u64>> {
+            let text = text.trim();
+            if text.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(text.parse().with_context(|| format!("Invalid block number {}", text))?))
+        };
+        let range = match s.split_once("..") {
+            Some((first, last)) => Self { first: bound(first)?, last: bound(last)? },
+            None => {
+                let number = bound(s)?.context("Empty block range")?;
+                Self { first: Some(number), last: Some(number) }
+            }
+        };
+        if let (Some(first), Some(last)) = (range.first, range.last) {
+            if first > last {
+                bail!("Block range {} ends before it starts", s);
+            }
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for BlockRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) if first == last => write!(f, "{}", first),
+            (first, last) => {
+                if let Some(first) = first {
+                    write!(f, "{}", first)?;
+                }
+                write!(f, "..")?;
+                if let Some(last) = last {
+                    write!(f, "{}", last)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Block numbers read from a `--block-list` file
+#[derive(Debug, Clone)]
+pub struct BlockList {
+    pub path: PathBuf,
+    numbers: HashSet<u64>,
+}
+
+impl BlockList {
+    /// Numbers separated by whitespace or commas; `#` starts a comment
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read block list {}", path.display()))?;
+        let mut numbers = HashSet::new();
+        for (line_index, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            for word in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|word| !word.is_empty()) {
+                let number = word.parse()
+                    .with_context(|| format!("{}:{}: invalid block number {}", path.display(), line_index + 1, word))?;
+                numbers.insert(number);
+            }
+        }
+        if numbers.is_empty() {
+            bail!("Block list {} names no blocks", path.display());
+        }
+        Ok(Self { path: path.to_path_buf(), numbers })
+    }
+
+    pub fn len(&self) -> usize {
+        self.numbers.len()
+    }
+}
+
+/// Which blocks of a dataset a command works on
+#[derive(Debug, Clone)]
+pub struct BlockSelection {
+    pub range: BlockRange,
+    /// Keep every Nth block number, counted from the start of `range`
+    pub every: Option<u64>,
+    /// Fraction of blocks kept by random sampling
+    pub sample: Option<f64>,
+    pub seed: u64,
+    pub list: Option<BlockList>,
+}
+
+impl Default for BlockSelection {
+    fn default() -> Self {
+        Self { range: BlockRange::default(), every: None, sample: None, seed: DEFAULT_SEED, list: None }
+    }
+}
+
+impl BlockSelection {
+    pub fn contains(&self, number: u64) -> bool {
+        self.range.contains(number)
+            && self.every.is_none_or(|every| (number - self.range.first.unwrap_or(0)).is_multiple_of(every))
+            && self.sample.is_none_or(|fraction| sample_point(self.seed, number) < fraction)
+            && self.list.as_ref().is_none_or(|list| list.numbers.contains(&number))
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.range.is_all() && self.every.is_none() && self.sample.is_none() && self.list.is_none()
+    }
+
+    pub fn check(&self) -> Result<()> {
+        if self.every == Some(0) {
+            bail!("--every needs a positive block count");
+        }
+        if let Some(fraction) = self.sample {
+            if !(fraction > 0.0 && fraction <= 1.0) {
+                bail!("--sample needs a fraction above 0 and at most 1, got {}", fraction);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BlockSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut criteria = Vec::new();
+        if !self.range.is_all() {
+            criteria.push(self.range.to_string());
+        }
+        if let Some(every) = self.every {
+            criteria.push(format!("every {} blocks", every));
+        }
+        if let Some(fraction) = self.sample {
+            criteria.push(format!("{:.2}% sample (seed {})", fraction * 100.0, self.seed));
+        }
+        if let Some(list) = &self.list {
+            criteria.push(format!("{} listed in {}", list.len(), list.path.display()));
+        }
+        if criteria.is_empty() {
+            return write!(f, "all");
+        }
+        write!(f, "{}", criteria.join(", "))
+    }
+}
+
+/// Where a block falls in [0, 1) for a seed: splitmix64 of seed and number
+fn sample_point(seed: u64, number: u64) -> f64 {
+    let mut z = seed.wrapping_add(number.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selected(selection: &BlockSelection, numbers: std::ops::Range<u64>) -> Vec<u64> {
+        numbers.filter(|&number| selection.contains(number)).collect()
+    }
+
+    #[test]
+    fn ranges_parse_and_print() {
+        let range: BlockRange = "100..200".parse().unwrap();
+        assert_eq!(range, BlockRange { first: Some(100), last: Some(200) });
+        assert!(range.contains(100) && range.contains(200));
+        assert!(!range.contains(99) && !range.contains(201));
+
+        assert_eq!("..5".parse::<BlockRange>().unwrap(), BlockRange { first: None, last: Some(5) });
+        assert_eq!("5..".parse::<BlockRange>().unwrap(), BlockRange { first: Some(5), last: None });
+        assert!("..".parse::<BlockRange>().unwrap().is_all());
+        assert_eq!("7".parse::<BlockRange>().unwrap().to_string(), "7");
+        assert_eq!("5..".parse::<BlockRange>().unwrap().to_string(), "5..");
+    }
+
+    #[test]
+    fn empty_and_invalid_ranges_are_rejected() {
+        assert!("".parse::<BlockRange>().is_err());
+        assert!(" ".parse::<BlockRange>().is_err());
+        assert!("200..100".parse::<BlockRange>().is_err());
+        assert!("1..x".parse::<BlockRange>().is_err());
+        assert!("-1".parse::<BlockRange>().is_err());
+        // A single block is the smallest range
+        assert_eq!(selected(&BlockSelection { range: "9..9".parse().unwrap(), ..Default::default() }, 0..20), [9]);
+    }
+
+    #[test]
+    fn every_nth_counts_from_the_start_of_the_range() {
+        let every = |range: &str, n| BlockSelection { range: range.parse().unwrap(), every: Some(n), ..Default::default() };
+        assert_eq!(selected(&every("..", 5), 0..21), [0, 5, 10, 15, 20]);
+        assert_eq!(selected(&every("3..20", 5), 0..30), [3, 8, 13, 18]);
+        assert_eq!(selected(&every("3..", 1), 0..6), [3, 4, 5]);
+
+        assert!(every("..", 0).check().is_err());
+        assert!(every("..", 1).check().is_ok());
+    }
+
+    #[test]
+    fn samples_depend_only_on_seed_and_number() {
+        let sample = |fraction, seed| BlockSelection { sample: Some(fraction), seed, ..Default::default() };
+        let first = selected(&sample(0.1, 7), 0..10_000);
+        assert_eq!(first, selected(&sample(0.1, 7), 0..10_000));
+        assert!((900..1100).contains(&first.len()), "{} blocks sampled", first.len());
+        assert_ne!(first, selected(&sample(0.1, 8), 0..10_000));
+
+        // Whether a block is sampled does not depend on which others are asked about
+        let reversed: Vec<u64> = (0..10_000).rev().filter(|&number| sample(0.1, 7).contains(number)).collect();
+        assert_eq!(reversed.into_iter().rev().collect::<Vec<_>>(), first);
+
+        // A larger fraction keeps everything a smaller one kept
+        let wider = selected(&sample(0.5, 7), 0..10_000);
+        assert!(first.iter().all(|number| wider.contains(number)));
+        assert_eq!(selected(&sample(1.0, 7), 0..100).len(), 100);
+
+        for fraction in [0.0, -0.5, 1.5, f64::NAN] {
+            assert!(sample(fraction, 7).check().is_err(), "{}", fraction);
+        }
+    }
+
+    #[test]
+    fn criteria_combine() {
+        let dir = std::env::temp_dir().join(format!("williams-selection-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blocks.txt");
+        fs::write(&path, "# slow blocks\n10, 12 14\n\n16 # and one more\n30\n").unwrap();
+        let list = BlockList::load(&path).unwrap();
+        assert_eq!(list.len(), 5);
+
+        let selection = BlockSelection {
+            range: "11..20".parse().unwrap(),
+            every: Some(2),
+            list: Some(list),
+            ..Default::default()
+        };
+        assert_eq!(selected(&selection, 0..40), Vec::<u64>::new());
+        let selection = BlockSelection { range: "10..20".parse().unwrap(), ..selection };
+        assert_eq!(selected(&selection, 0..40), [10, 12, 14, 16]);
+        assert!(!selection.is_all());
+        assert!(BlockSelection::default().is_all());
+        assert_eq!(BlockSelection::default().to_string(), "all");
+
+        fs::write(&path, "# nothing\n").unwrap();
+        assert!(BlockList::load(&path).is_err());
+        fs::write(&path, "12 twelve\n").unwrap();
+        assert!(BlockList::load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
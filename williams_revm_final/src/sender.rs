@@ -0,0 +1,84 @@
+// Transaction sender recovery
+//
+// The JSON `from` field is a claim made by whoever produced the dataset.
+// Recovering the signer from the signature (secp256k1 over the typed-tx
+// signing hash) makes a tampered or mis-encoded dataset visible, and lets
+// the executor run raw signed transactions that carry no `from` at all.
+
+use anyhow::{Result, anyhow};
+use alloy_primitives::PrimitiveSignature;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use revm::primitives::Address;
+
+use crate::json_hex::field_address;
+use crate::tx_rlp::{signing_hash, tx_signature};
+
+/// A transaction whose recovered signer differs from its `from` field
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SenderMismatch {
+    pub tx_index: usize,
+    pub claimed: Address,
+    pub recovered: Address,
+}
+
+/// Outcome of sender recovery for one block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SenderReport {
+    pub recovered: usize,
+    pub mismatches: Vec<SenderMismatch>,
+    /// Transactions whose signature could not be recovered
+    pub failures: Vec<(usize, String)>,
+}
+
+/// Recover the signer of a transaction from its signature
+pub fn recover_sender(tx: &Value) -> Result<Address> {
+    let sig = tx_signature(tx)?;
+    let hash = signing_hash(tx)?;
+    PrimitiveSignature::new(sig.r, sig.s, sig.odd_y_parity)
+        .recover_address_from_prehash(&hash)
+        .map_err(|e| anyhow!("Signature recovery failed: {}", e))
+}
+
+/// Resolve the caller of every transaction in a block
+///
+/// Transactions without `from` are always recovered. With `verify` every
+/// transaction is recovered and checked against its `from`. Recovery runs
+/// on the current rayon pool. A `None` entry means "use the JSON `from`";
+/// a failed recovery is `None` as well and listed in the report.
+pub fn resolve_senders(txs: &[Value], verify: bool) -> (Vec<Option<Address>>, SenderReport) {
+    let outcomes: Vec<Option<Result<Address>>> = txs
+        .par_iter()
+        .map(|tx| {
+            let claimed = field_address(tx, "from");
+            (verify || claimed.is_none()).then(|| recover_sender(tx))
+        })
+        .collect();
+
+    let mut report = SenderReport::default();
+    let mut senders = Vec::with_capacity(txs.len());
+
+    for (tx_index, (tx, outcome)) in txs.iter().zip(outcomes).enumerate() {
+        match outcome {
+            None => senders.push(None),
+            Some(Ok(recovered)) => {
+                report.recovered += 1;
+                if let Some(claimed) = field_address(tx, "from") {
+                    if claimed != recovered {
+                        report.mismatches.push(SenderMismatch { tx_index, claimed, recovered });
+                    }
+                }
+                senders.push(Some(recovered));
+            }
+            Some(Err(e)) => {
+                report.failures.push((tx_index, e.to_string()));
+                senders.push(None);
+            }
+        }
+    }
+
+    (senders, report)
+}
+
@@ -0,0 +1,341 @@
+// System calls around block execution
+//
+// Cancun and Prague blocks run contract calls from SYSTEM_ADDRESS that are
+// not part of the transaction list:
+// - EIP-4788 (Cancun+, before txs): parentBeaconBlockRoot into the beacon
+//   roots contract
+// - EIP-2935 (Prague+, before txs): parent hash into history storage
+// - EIP-7002 / EIP-7251 (Prague+, after withdrawals): dequeue withdrawal and
+//   consolidation requests
+//
+// A system call pays no gas, bypasses the block gas limit and only commits
+// the target contract's state, never the system address or coinbase.
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use revm::{
+    primitives::{
+        address, Address, BlockEnv, Bytes, ExecutionResult, SpecId, TransactTo, TxEnv, B256,
+        KECCAK_EMPTY, U256,
+    },
+    Database, DatabaseCommit, Evm,
+};
+
+use crate::chain_config::ChainConfig;
+use crate::json_hex::field_b256;
+
+/// Caller of all system calls
+pub const SYSTEM_ADDRESS: Address = address!("fffffffffffffffffffffffffffffffffffffffe");
+
+/// EIP-4788 beacon roots contract
+pub const BEACON_ROOTS_ADDRESS: Address = address!("000F3df6D732807Ef1319fB7B8bB8522d0Beac02");
+
+/// EIP-2935 history storage contract
+pub const HISTORY_STORAGE_ADDRESS: Address = address!("0000F90827F1C53a10cb7A02335B175320002935");
+
+/// EIP-7002 withdrawal request predeploy
+pub const WITHDRAWAL_REQUEST_ADDRESS: Address = address!("00000961Ef480Eb55e80D19ad83579A64c007002");
+
+/// EIP-7251 consolidation request predeploy
+pub const CONSOLIDATION_REQUEST_ADDRESS: Address = address!("0000BBdDc7CE488642fb579F8B00f3a590007251");
+
+/// Gas limit of every system call
+const SYSTEM_CALL_GAS_LIMIT: u64 = 30_000_000;
+
+/// Encoded size of one EIP-7002 withdrawal request
+const WITHDRAWAL_REQUEST_SIZE: usize = 76;
+
+/// Encoded size of one EIP-7251 consolidation request
+const CONSOLIDATION_REQUEST_SIZE: usize = 116;
+
+/// System calls performed for a block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SystemCallSummary {
+    pub calls: usize,
+    pub withdrawal_requests: usize,
+    pub consolidation_requests: usize,
+}
+
+/// Header values the pre-block system calls write into state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SystemCallInputs {
+    pub parent_hash: Option<B256>,
+    pub parent_beacon_block_root: Option<B256>,
+}
+
+impl SystemCallInputs {
+    pub fn from_block(block: &Value) -> Self {
+        Self {
+            parent_hash: field_b256(block, "parentHash"),
+            parent_beacon_block_root: field_b256(block, "parentBeaconBlockRoot"),
+        }
+    }
+}
+
+/// EIP-4788 and EIP-2935 calls, before the first transaction
+pub fn apply_pre_block_system_calls<DB>(
+    db: &mut DB,
+    inputs: &SystemCallInputs,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+    chain: &ChainConfig,
+) -> Result<SystemCallSummary>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: std::fmt::Debug,
+{
+    let mut summary = SystemCallSummary::default();
+
+    // Genesis has no parent, so there is nothing to record
+    if block_env.number == U256::ZERO {
+        return Ok(summary);
+    }
+
+    if spec_id >= SpecId::CANCUN {
+        if let Some(root) = inputs.parent_beacon_block_root {
+            let data = Bytes::copy_from_slice(root.as_slice());
+            if system_call(db, BEACON_ROOTS_ADDRESS, data, block_env, spec_id, chain)?.is_some() {
+                summary.calls += 1;
+            }
+        }
+    }
+
+    if spec_id >= SpecId::PRAGUE {
+        if let Some(parent_hash) = inputs.parent_hash {
+            let data = Bytes::copy_from_slice(parent_hash.as_slice());
+            if system_call(db, HISTORY_STORAGE_ADDRESS, data, block_env, spec_id, chain)?.is_some() {
+                summary.calls += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// EIP-7002 and EIP-7251 request calls, after withdrawals are applied
+pub fn apply_post_block_system_calls<DB>(
+    db: &mut DB,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+    chain: &ChainConfig,
+    summary: &mut SystemCallSummary,
+) -> Result<()>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: std::fmt::Debug,
+{
+    if spec_id < SpecId::PRAGUE {
+        return Ok(());
+    }
+
+    if let Some(output) = system_call(db, WITHDRAWAL_REQUEST_ADDRESS, Bytes::new(), block_env, spec_id, chain)? {
+        summary.calls += 1;
+        summary.withdrawal_requests += output.len() / WITHDRAWAL_REQUEST_SIZE;
+    }
+    if let Some(output) = system_call(db, CONSOLIDATION_REQUEST_ADDRESS, Bytes::new(), block_env, spec_id, chain)? {
+        summary.calls += 1;
+        summary.consolidation_requests += output.len() / CONSOLIDATION_REQUEST_SIZE;
+    }
+
+    Ok(())
+}
+
+/// Execute one system call and commit the target contract's changes
+///
+/// Returns `None` when the target has no code (contract not deployed on
+/// this chain yet), in which case the call is skipped as the EIPs require.
+fn system_call<DB>(
+    db: &mut DB,
+    target: Address,
+    data: Bytes,
+    block_env: &BlockEnv,
+    spec_id: SpecId,
+    chain: &ChainConfig,
+) -> Result<Option<Bytes>>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: std::fmt::Debug,
+{
+    let deployed = db.basic(target)
+        .map_err(|e| anyhow!("Failed to load system contract {}: {:?}", target, e))?
+        .is_some_and(|info| info.code_hash != KECCAK_EMPTY);
+    if !deployed {
+        return Ok(None);
+    }
+
+    let tx_env = TxEnv {
+        caller: SYSTEM_ADDRESS,
+        transact_to: TransactTo::Call(target),
+        data,
+        gas_limit: SYSTEM_CALL_GAS_LIMIT,
+        gas_price: U256::ZERO,
+        nonce: None,
+        ..Default::default()
+    };
+
+    // Free of charge and outside the block gas accounting
+    let mut system_block_env = block_env.clone();
+    system_block_env.basefee = U256::ZERO;
+    system_block_env.gas_limit = U256::from(u64::MAX);
+
+    let coinbase = block_env.coinbase;
+    let result = {
+        let mut evm = Evm::builder()
+            .with_db(&mut *db)
+            .with_block_env(system_block_env)
+            .with_tx_env(tx_env)
+            .with_cfg_env_with_handler_cfg(chain.cfg_env(spec_id))
+            .build();
+        chain.apply_precompiles(&mut evm);
+        evm.transact()
+            .map_err(|e| anyhow!("System call to {} failed: {:?}", target, e))?
+    };
+
+    let output = match result.result {
+        ExecutionResult::Success { output, .. } => output.into_data(),
+        other => bail!("System call to {} did not succeed: {:?}", target, other),
+    };
+
+    let mut state = result.state;
+    state.remove(&SYSTEM_ADDRESS);
+    state.remove(&coinbase);
+    db.commit(state);
+
+    Ok(Some(output))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    use revm::db::{CacheDB, EmptyDB};
+    use revm::primitives::{b256, AccountInfo, Bytecode};
+
+    use crate::overlay::OverlayDB;
+
+    /// EIP-4788 beacon roots contract runtime code
+    const BEACON_ROOTS_CODE: &str = "3373fffffffffffffffffffffffffffffffffffffffe14604d57602036146024575f5ffd5b5f35801560495762001fff810690815414603c575f5ffd5b62001fff01545f5260205ff35b5f5ffd5b62001fff42064281555f359062001fff015500";
+
+    /// EIP-2935 history storage contract runtime code
+    const HISTORY_STORAGE_CODE: &str = "3373fffffffffffffffffffffffffffffffffffffffe14604657602036036042575f35600143038111604257611fff81430311604257611fff9006545f5260205ff35b5f5ffd5b5f35611fff60014303065500";
+
+    const COINBASE: Address = address!("00000000000000000000000000000000000000c0");
+
+    /// Ring buffer length of both contracts
+    const HISTORY_BUFFER_LENGTH: u64 = 8191;
+
+    /// A layer over a state holding the given contracts, so what a call commits is visible
+    fn state_with(contracts: &[(Address, &str)]) -> OverlayDB<CacheDB<EmptyDB>> {
+        let mut base = CacheDB::new(EmptyDB::default());
+        for (address, code) in contracts {
+            let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+            base.insert_account_info(*address, AccountInfo::from_bytecode(code));
+        }
+        OverlayDB::new(Arc::new(base))
+    }
+
+    fn block_env(number: u64, timestamp: u64) -> BlockEnv {
+        BlockEnv {
+            number: U256::from(number),
+            timestamp: U256::from(timestamp),
+            coinbase: COINBASE,
+            basefee: U256::from(7),
+            ..Default::default()
+        }
+    }
+
+    fn inputs() -> SystemCallInputs {
+        SystemCallInputs {
+            parent_hash: Some(b256!("1111111111111111111111111111111111111111111111111111111111111111")),
+            parent_beacon_block_root: Some(b256!("2222222222222222222222222222222222222222222222222222222222222222")),
+        }
+    }
+
+    fn changed_accounts(db: &OverlayDB<CacheDB<EmptyDB>>) -> Vec<Address> {
+        db.changes().accounts.into_keys().collect()
+    }
+
+    #[test]
+    fn beacon_root_is_stored_by_timestamp() {
+        let mut db = state_with(&[(BEACON_ROOTS_ADDRESS, BEACON_ROOTS_CODE)]);
+        let (number, timestamp) = (19_426_587, 1_710_338_135);
+        let chain = ChainConfig::mainnet();
+        let summary = apply_pre_block_system_calls(&mut db, &inputs(), &block_env(number, timestamp), SpecId::CANCUN, &chain)
+            .unwrap();
+        assert_eq!(summary.calls, 1);
+
+        let slot = timestamp % HISTORY_BUFFER_LENGTH;
+        let root = inputs().parent_beacon_block_root.unwrap();
+        assert_eq!(db.storage(BEACON_ROOTS_ADDRESS, U256::from(slot)).unwrap(), U256::from(timestamp));
+        assert_eq!(
+            db.storage(BEACON_ROOTS_ADDRESS, U256::from(slot + HISTORY_BUFFER_LENGTH)).unwrap(),
+            U256::from_be_bytes(root.0),
+        );
+        // Neither the system address nor the coinbase is committed
+        assert_eq!(changed_accounts(&db), vec![BEACON_ROOTS_ADDRESS]);
+    }
+
+    #[test]
+    fn parent_hash_is_stored_by_number() {
+        let mut db = state_with(&[
+            (BEACON_ROOTS_ADDRESS, BEACON_ROOTS_CODE),
+            (HISTORY_STORAGE_ADDRESS, HISTORY_STORAGE_CODE),
+        ]);
+        let number = 22_431_084;
+        let chain = ChainConfig::mainnet();
+        let summary = apply_pre_block_system_calls(&mut db, &inputs(), &block_env(number, 1_746_612_311), SpecId::PRAGUE, &chain)
+            .unwrap();
+        assert_eq!(summary.calls, 2);
+
+        let slot = (number - 1) % HISTORY_BUFFER_LENGTH;
+        let parent_hash = inputs().parent_hash.unwrap();
+        assert_eq!(db.storage(HISTORY_STORAGE_ADDRESS, U256::from(slot)).unwrap(), U256::from_be_bytes(parent_hash.0));
+        let mut changed = changed_accounts(&db);
+        changed.sort();
+        assert_eq!(changed, vec![HISTORY_STORAGE_ADDRESS, BEACON_ROOTS_ADDRESS]);
+    }
+
+    #[test]
+    fn calls_are_skipped_without_code_or_before_their_fork() {
+        let chain = ChainConfig::mainnet();
+
+        // Contract not deployed
+        let mut db = state_with(&[]);
+        let summary = apply_pre_block_system_calls(&mut db, &inputs(), &block_env(1, 1), SpecId::PRAGUE, &chain).unwrap();
+        assert_eq!(summary.calls, 0);
+
+        // Shanghai has no beacon roots call, genesis has no parent
+        let mut db = state_with(&[(BEACON_ROOTS_ADDRESS, BEACON_ROOTS_CODE)]);
+        let summary = apply_pre_block_system_calls(&mut db, &inputs(), &block_env(1, 1), SpecId::SHANGHAI, &chain).unwrap();
+        assert_eq!(summary.calls, 0);
+        let summary = apply_pre_block_system_calls(&mut db, &inputs(), &block_env(0, 1), SpecId::CANCUN, &chain).unwrap();
+        assert_eq!(summary.calls, 0);
+        assert!(changed_accounts(&db).is_empty());
+    }
+
+    #[test]
+    fn request_outputs_are_split_into_records() {
+        // Stand-ins returning two withdrawal and three consolidation requests worth of zero bytes
+        let returns = |len: usize| format!("61{:04x}5ff3", len);
+        let (withdrawals, consolidations) = (returns(2 * WITHDRAWAL_REQUEST_SIZE), returns(3 * CONSOLIDATION_REQUEST_SIZE));
+        let chain = ChainConfig::mainnet();
+        let mut db = state_with(&[
+            (WITHDRAWAL_REQUEST_ADDRESS, &withdrawals),
+            (CONSOLIDATION_REQUEST_ADDRESS, &consolidations),
+        ]);
+
+        let mut summary = SystemCallSummary::default();
+        apply_post_block_system_calls(&mut db, &block_env(1, 1), SpecId::CANCUN, &chain, &mut summary).unwrap();
+        assert_eq!(summary.calls, 0);
+
+        apply_post_block_system_calls(&mut db, &block_env(1, 1), SpecId::PRAGUE, &chain, &mut summary).unwrap();
+        assert_eq!(summary.calls, 2);
+        assert_eq!(summary.withdrawal_requests, 2);
+        assert_eq!(summary.consolidation_requests, 3);
+    }
+}
@@ -0,0 +1,157 @@
+// Per-transaction execution traces
+//
+// `trace` executes the selected blocks, usually a single one picked with
+// `--blocks`, and records what each transaction did: its status and gas, the
+// accounts and storage slots it read and wrote (judged against the block's
+// prestate, as optimistic validation does), and whether it read something an
+// earlier transaction of the block wrote. Stage timings are printed per
+// block. It is meant for looking into one slow or mismatching block, not for
+// measuring a dataset.
+
+use std::collections::HashSet;
+use std::fs;
+
+use anyhow::{Result, Context};
+use serde::Serialize;
+
+use revm::primitives::{Address, ExecutionResult, ResultAndState, TransactTo};
+
+use williams_executor::access::{AccessSet, Location};
+use williams_executor::block_cache::PreparedBlock;
+use williams_executor::executor::BlockExecution;
+use williams_executor::prestate::PrestateDB;
+use williams_executor::schedule::GAS_PROFILE_FILE;
+
+use crate::cli::{ReportFormat, RunOptions};
+use crate::{build_executor, execute_on_prestate, prepared_blocks, RunInputs};
+
+/// Per-transaction records, written to `williams_trace.*`
+const TRACE_FILE: &str = "williams_trace";
+
+#[derive(Debug, Serialize)]
+struct TxTrace {
+    block: u64,
+    index: usize,
+    from: Address,
+    /// `None` for contract creations
+    to: Option<Address>,
+    deterministic: bool,
+    status: &'static str,
+    gas_used: u64,
+    reads: usize,
+    writes: usize,
+    /// Read something an earlier transaction of the block wrote
+    conflict: bool,
+}
+
+/// Status of a transaction's outcome and the gas it used
+pub fn outcome_status(outcome: Option<&ResultAndState>) -> (&'static str, u64) {
+    match outcome.map(|outcome| &outcome.result) {
+        None => ("invalid", 0),
+        Some(ExecutionResult::Success { gas_used, .. }) => ("success", *gas_used),
+        Some(ExecutionResult::Revert { gas_used, .. }) => ("revert", *gas_used),
+        Some(ExecutionResult::Halt { gas_used, .. }) => ("halt", *gas_used),
+    }
+}
+
+pub fn run(inputs: &RunInputs, options: &RunOptions) -> Result<()> {
+    const MAX_LISTED: usize = 10;
+
+    let thread_count = options.max_threads();
+    let blocks = prepared_blocks(inputs, options)?;
+    let mut executor = build_executor(&options.executor, inputs.chain, thread_count, &options.output_path(GAS_PROFILE_FILE))?;
+    println!("Tracing with {} ({} threads)...", executor.name(), thread_count);
+    println!();
+
+    let mut traces = Vec::new();
+    for block in blocks {
+        let block = block?;
+        let (execution, prestate) = execute_on_prestate(&block, inputs.state, &mut executor)
+            .with_context(|| format!("Block {}", block.number))?;
+        let block_traces = trace_block(&block, &execution, &prestate);
+
+        let timings = &execution.timings;
+        let conflicts = block_traces.iter().filter(|trace| trace.conflict).count();
+        println!("Block {}:", block.number);
+        println!("  Transactions:            {} ({} read an earlier tx's writes)", block.txs.len(), conflicts);
+        if let Some(decision) = &execution.decision {
+            println!("  Strategy:                {} ({})", decision.strategy, decision.reason);
+        }
+        println!("  Prefetch:                {:.3}ms", timings.prefetch_us as f64 / 1000.0);
+        println!("  Transaction stage:       {:.3}ms", timings.transactions_us as f64 / 1000.0);
+        println!("  Finalization:            {:.3}ms", timings.finalize_us as f64 / 1000.0);
+        println!("  System calls:            {:.3}ms", timings.system_calls_us as f64 / 1000.0);
+        println!("  Total:                   {:.3}ms", timings.total_us() as f64 / 1000.0);
+
+        let mut by_gas: Vec<&TxTrace> = block_traces.iter().collect();
+        by_gas.sort_by(|a, b| b.gas_used.cmp(&a.gas_used).then(a.index.cmp(&b.index)));
+        if !by_gas.is_empty() {
+            println!("  Most gas:");
+        }
+        for trace in by_gas.iter().take(MAX_LISTED) {
+            println!("    tx {:<4} {:<8} {:>9} gas  {} reads, {} writes{}",
+                trace.index, trace.status, trace.gas_used, trace.reads, trace.writes,
+                if trace.conflict { ", conflict" } else { "" }
+            );
+        }
+        println!();
+        traces.extend(block_traces);
+    }
+
+    let path = options.output_path(&format!("{}.{}", TRACE_FILE, options.format.extension()));
+    let output = match options.format {
+        ReportFormat::Json => serde_json::to_string_pretty(&traces)?,
+        ReportFormat::Text => {
+            let mut output = String::from(
+                "block\tindex\tfrom\tto\tdeterministic\tstatus\tgas_used\treads\twrites\tconflict\n"
+            );
+            for trace in &traces {
+                output.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    trace.block, trace.index, trace.from,
+                    trace.to.map_or("create".to_string(), |to| to.to_string()),
+                    trace.deterministic, trace.status, trace.gas_used,
+                    trace.reads, trace.writes, trace.conflict
+                ));
+            }
+            output
+        }
+    };
+    fs::write(&path, output)?;
+    println!("Trace of {} transactions saved to {}", traces.len(), path.display());
+
+    Ok(())
+}
+
+fn trace_block(block: &PreparedBlock, execution: &BlockExecution, prestate: &PrestateDB) -> Vec<TxTrace> {
+    let coinbase = block.block_env.coinbase;
+    let mut written: HashSet<Location> = HashSet::new();
+
+    block.txs.iter()
+        .zip(&execution.outcomes)
+        .enumerate()
+        .map(|(index, (tx, outcome))| {
+            let access = outcome.as_ref()
+                .map(|outcome| AccessSet::of(&outcome.state, prestate, coinbase))
+                .unwrap_or_default();
+            let conflict = access.reads_any(&written);
+            written.extend(access.writes.iter().copied());
+
+            let (status, gas_used) = outcome_status(outcome.as_ref());
+            TxTrace {
+                block: block.number,
+                index,
+                from: tx.tx_env.caller,
+                to: match tx.tx_env.transact_to {
+                    TransactTo::Call(to) => Some(to),
+                    TransactTo::Create => None,
+                },
+                deterministic: tx.deterministic,
+                status,
+                gas_used,
+                reads: access.reads.len(),
+                writes: access.writes.len(),
+                conflict,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,448 @@
+// RLP encoding of transactions from their JSON-RPC representation
+//
+// Covers legacy (with and without EIP-155), EIP-2930, EIP-1559, EIP-4844
+// and EIP-7702 transactions. Every field that is part of the encoding must
+// be present in the JSON; unlike execution, encoding never substitutes a
+// default because the result would hash to something else.
+
+use anyhow::{Result, Context, bail};
+use alloy_rlp::{Encodable, Header};
+use serde_json::Value;
+
+// REVM's authorizations still carry the older signature type
+#[allow(deprecated)]
+use revm::primitives::Signature;
+use revm::primitives::{keccak256, AccessListItem, Authorization, SignedAuthorization, B256, U256};
+
+use crate::json_hex::{field_address, field_bytes, field_u256, field_u64};
+
+pub const LEGACY_TX_TYPE: u64 = 0;
+pub const ACCESS_LIST_TX_TYPE: u64 = 1;
+pub const DYNAMIC_FEE_TX_TYPE: u64 = 2;
+pub const BLOB_TX_TYPE: u64 = 3;
+pub const SET_CODE_TX_TYPE: u64 = 4;
+
+/// Accumulates encoded items and wraps them in a list header
+#[derive(Default)]
+pub struct RlpList {
+    payload: Vec<u8>,
+}
+
+impl RlpList {
+    pub fn push<T: Encodable + ?Sized>(&mut self, value: &T) {
+        value.encode(&mut self.payload);
+    }
+
+    /// Append an item that is already RLP encoded (e.g. a nested list)
+    pub fn push_raw(&mut self, encoded: &[u8]) {
+        self.payload.extend_from_slice(encoded);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let header = Header { list: true, payload_length: self.payload.len() };
+        let mut out = Vec::with_capacity(header.length_with_payload());
+        header.encode(&mut out);
+        out.extend_from_slice(&self.payload);
+        out
+    }
+}
+
+/// Signature values of a transaction
+#[derive(Debug, Clone, Copy)]
+pub struct TxSignature {
+    pub r: U256,
+    pub s: U256,
+    /// Recovery parity (y coordinate odd)
+    pub odd_y_parity: bool,
+    /// EIP-155 chain id folded into a legacy `v`
+    pub legacy_chain_id: Option<u64>,
+}
+
+/// EIP-2718 type of a transaction, legacy when absent
+pub fn tx_type(tx: &Value) -> u64 {
+    field_u64(tx, "type").unwrap_or(LEGACY_TX_TYPE)
+}
+
+/// Read the signature fields, normalizing legacy `v` to a parity
+pub fn tx_signature(tx: &Value) -> Result<TxSignature> {
+    let r = field_u256(tx, "r").context("Transaction has no r")?;
+    let s = field_u256(tx, "s").context("Transaction has no s")?;
+
+    if tx_type(tx) == LEGACY_TX_TYPE {
+        let v = field_u64(tx, "v").context("Legacy transaction has no v")?;
+        let (odd_y_parity, legacy_chain_id) = match v {
+            27 | 28 => (v == 28, None),
+            v if v >= 35 => ((v - 35) % 2 == 1, Some((v - 35) / 2)),
+            _ => bail!("Invalid legacy signature v: {}", v),
+        };
+        return Ok(TxSignature { r, s, odd_y_parity, legacy_chain_id });
+    }
+
+    let parity = field_u64(tx, "yParity")
+        .or_else(|| field_u64(tx, "v"))
+        .context("Typed transaction has no yParity")?;
+    if parity > 1 {
+        bail!("Invalid yParity: {}", parity);
+    }
+    Ok(TxSignature { r, s, odd_y_parity: parity == 1, legacy_chain_id: None })
+}
+
+/// Hash the sender signed: keccak256 of the signing payload
+pub fn signing_hash(tx: &Value) -> Result<B256> {
+    Ok(keccak256(signing_payload(tx)?))
+}
+
+/// Bytes covered by the signature, including the type byte for typed txs
+pub fn signing_payload(tx: &Value) -> Result<Vec<u8>> {
+    let ty = tx_type(tx);
+    let mut fields = unsigned_fields(tx, ty)?;
+
+    if ty == LEGACY_TX_TYPE {
+        // EIP-155 appends (chain_id, 0, 0) to the signed list
+        if let Some(chain_id) = tx_signature(tx)?.legacy_chain_id {
+            fields.push(&chain_id);
+            fields.push(&0u8);
+            fields.push(&0u8);
+        }
+        return Ok(fields.finish());
+    }
+
+    Ok(typed(ty, fields.finish()))
+}
+
+/// Canonical signed encoding: the legacy RLP list or the typed envelope
+pub fn encode_signed(tx: &Value) -> Result<Vec<u8>> {
+    let ty = tx_type(tx);
+    let signature = tx_signature(tx)?;
+    let mut fields = unsigned_fields(tx, ty)?;
+
+    if ty == LEGACY_TX_TYPE {
+        let v = match signature.legacy_chain_id {
+            Some(chain_id) => chain_id * 2 + 35 + signature.odd_y_parity as u64,
+            None => 27 + signature.odd_y_parity as u64,
+        };
+        fields.push(&v);
+    } else {
+        fields.push(&signature.odd_y_parity);
+    }
+    fields.push(&signature.r);
+    fields.push(&signature.s);
+
+    if ty == LEGACY_TX_TYPE {
+        return Ok(fields.finish());
+    }
+    Ok(typed(ty, fields.finish()))
+}
+
+/// Prefix an encoded list with its EIP-2718 type byte
+pub fn typed(ty: u64, list: Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(list.len() + 1);
+    out.push(ty as u8);
+    out.extend_from_slice(&list);
+    out
+}
+
+/// Transaction fields in envelope order, without the signature
+pub fn unsigned_fields(tx: &Value, ty: u64) -> Result<RlpList> {
+    let mut fields = RlpList::default();
+
+    let nonce = require_u64(tx, "nonce")?;
+    let gas = require_u64(tx, "gas")?;
+    let value = require_u256(tx, "value")?;
+    let input = field_bytes(tx, "input").context("Transaction has no input")?;
+
+    match ty {
+        LEGACY_TX_TYPE => {
+            fields.push(&nonce);
+            fields.push(&require_u256(tx, "gasPrice")?);
+            fields.push(&gas);
+            push_to(&mut fields, tx);
+            fields.push(&value);
+            fields.push(&input);
+        }
+        ACCESS_LIST_TX_TYPE => {
+            fields.push(&require_u64(tx, "chainId")?);
+            fields.push(&nonce);
+            fields.push(&require_u256(tx, "gasPrice")?);
+            fields.push(&gas);
+            push_to(&mut fields, tx);
+            fields.push(&value);
+            fields.push(&input);
+            fields.push_raw(&encode_access_list(tx)?);
+        }
+        DYNAMIC_FEE_TX_TYPE | BLOB_TX_TYPE | SET_CODE_TX_TYPE => {
+            fields.push(&require_u64(tx, "chainId")?);
+            fields.push(&nonce);
+            fields.push(&require_u256(tx, "maxPriorityFeePerGas")?);
+            fields.push(&require_u256(tx, "maxFeePerGas")?);
+            fields.push(&gas);
+            push_to(&mut fields, tx);
+            fields.push(&value);
+            fields.push(&input);
+            fields.push_raw(&encode_access_list(tx)?);
+
+            if ty == BLOB_TX_TYPE {
+                fields.push(&require_u256(tx, "maxFeePerBlobGas")?);
+                fields.push_raw(&encode_blob_hashes(tx)?);
+            }
+            if ty == SET_CODE_TX_TYPE {
+                fields.push_raw(&encode_authorization_list(tx)?);
+            }
+        }
+        _ => bail!("Unsupported transaction type: {:#x}", ty),
+    }
+
+    Ok(fields)
+}
+
+/// Recipient, or the empty string for contract creation
+fn push_to(fields: &mut RlpList, tx: &Value) {
+    match field_address(tx, "to") {
+        Some(to) => fields.push(&to),
+        None => fields.push_raw(&[alloy_rlp::EMPTY_STRING_CODE]),
+    }
+}
+
+fn encode_access_list(tx: &Value) -> Result<Vec<u8>> {
+    let mut list = RlpList::default();
+    for entry in access_list(tx)? {
+        let mut item = RlpList::default();
+        item.push(&entry.address);
+        let mut keys = RlpList::default();
+        for key in &entry.storage_keys {
+            keys.push(key);
+        }
+        item.push_raw(&keys.finish());
+        list.push_raw(&item.finish());
+    }
+    Ok(list.finish())
+}
+
+fn encode_blob_hashes(tx: &Value) -> Result<Vec<u8>> {
+    let mut list = RlpList::default();
+    for hash in blob_hashes(tx)? {
+        list.push(&hash);
+    }
+    Ok(list.finish())
+}
+
+fn encode_authorization_list(tx: &Value) -> Result<Vec<u8>> {
+    let mut list = RlpList::default();
+    for auth in authorization_list(tx)? {
+        let mut item = RlpList::default();
+        item.push(&auth.chain_id);
+        item.push(&auth.address);
+        item.push(&auth.nonce);
+        item.push(&(auth.signature().v().y_parity() as u8));
+        item.push(&auth.signature().r());
+        item.push(&auth.signature().s());
+        list.push_raw(&item.finish());
+    }
+    Ok(list.finish())
+}
+
+/// EIP-2930 access list, empty when the transaction declares none
+pub fn access_list(tx: &Value) -> Result<Vec<AccessListItem>> {
+    let entries = tx.get("accessList").and_then(|a| a.as_array()).map(|a| a.as_slice()).unwrap_or_default();
+    entries.iter()
+        .map(|entry| {
+            let address = field_address(entry, "address").context("Access list entry has no address")?;
+            let storage_keys = entry.get("storageKeys").and_then(|k| k.as_array()).map(|k| k.as_slice()).unwrap_or_default()
+                .iter()
+                .map(|key| parse_b256(key).context("Invalid access list storage key"))
+                .collect::<Result<_>>()?;
+            Ok(AccessListItem { address, storage_keys })
+        })
+        .collect()
+}
+
+/// EIP-4844 versioned hashes of the blobs a blob transaction carries
+pub fn blob_hashes(tx: &Value) -> Result<Vec<B256>> {
+    tx.get("blobVersionedHashes").and_then(|h| h.as_array()).context("Blob transaction has no blobVersionedHashes")?
+        .iter()
+        .map(|hash| parse_b256(hash).context("Invalid blob versioned hash"))
+        .collect()
+}
+
+/// EIP-7702 authorizations of a set-code transaction, with their signatures
+pub fn authorization_list(tx: &Value) -> Result<Vec<SignedAuthorization>> {
+    tx.get("authorizationList").and_then(|a| a.as_array()).context("Set-code transaction has no authorizationList")?
+        .iter()
+        .map(|auth| {
+            let authorization = Authorization {
+                chain_id: require_u256(auth, "chainId")?,
+                address: field_address(auth, "address").context("Authorization has no address")?,
+                nonce: require_u64(auth, "nonce")?,
+            };
+            let y_parity = require_u64(auth, "yParity")?;
+            if y_parity > 1 {
+                bail!("Invalid authorization yParity: {}", y_parity);
+            }
+            #[allow(deprecated)]
+            let signature = Signature::from_rs_and_parity(require_u256(auth, "r")?, require_u256(auth, "s")?, y_parity == 1)
+                .context("Invalid authorization signature")?;
+            Ok(authorization.into_signed(signature))
+        })
+        .collect()
+}
+
+fn parse_b256(value: &Value) -> Option<B256> {
+    let bytes = hex::decode(value.as_str()?.trim_start_matches("0x")).ok()?;
+    (bytes.len() == 32).then(|| B256::from_slice(&bytes))
+}
+
+fn require_u64(obj: &Value, key: &str) -> Result<u64> {
+    field_u64(obj, key).with_context(|| format!("Transaction field {} missing or invalid", key))
+}
+
+fn require_u256(obj: &Value, key: &str) -> Result<U256> {
+    field_u256(obj, key).with_context(|| format!("Transaction field {} missing or invalid", key))
+}
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use k256::ecdsa::SigningKey;
+    use revm::primitives::{address, Address};
+    use serde_json::json;
+
+    use crate::sender::recover_sender;
+
+    /// Signer of the EIP-155 example, private key 0x4646…46
+    pub(crate) const EXAMPLE_SIGNER: Address = address!("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+
+    /// The example transaction of EIP-155
+    pub(crate) fn eip155_example() -> Value {
+        json!({
+            "nonce": "0x9",
+            "gasPrice": "0x4a817c800",
+            "gas": "0x5208",
+            "to": "0x3535353535353535353535353535353535353535",
+            "value": "0xde0b6b3a7640000",
+            "input": "0x",
+            "v": "0x25",
+            "r": "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+            "s": "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        })
+    }
+
+    /// Mainnet block 46147: the first transaction, pre-EIP-155
+    pub(crate) fn first_mainnet_transaction() -> Value {
+        json!({
+            "type": "0x0",
+            "nonce": "0x0",
+            "gasPrice": "0x2d79883d2000",
+            "gas": "0x5208",
+            "to": "0x5df9b87991262f6ba471f09758cde1c0fc1de734",
+            "value": "0x7a69",
+            "input": "0x",
+            "v": "0x1c",
+            "r": "0x88ff6cf0fefd94db46111149ae4bfc179e9b94721fffd821d38d16464b3f71d0",
+            "s": "0x45e0aff800961cfce805daef7016b9b675c137a6a41a548f7b60a3484c06a33a",
+        })
+    }
+
+    /// One unsigned transaction of each typed envelope
+    pub(crate) fn typed_transactions() -> Vec<Value> {
+        let access_list = json!([{
+            "address": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            "storageKeys": ["0x0000000000000000000000000000000000000000000000000000000000000007"],
+        }]);
+        let base = json!({
+            "chainId": "0x1",
+            "nonce": "0x2a",
+            "gas": "0x186a0",
+            "to": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            "value": "0x0",
+            "input": "0xa9059cbb",
+            "accessList": access_list,
+        });
+        let with = |fields: Value| {
+            let mut tx = base.clone();
+            tx.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+            tx
+        };
+        vec![
+            with(json!({ "type": "0x1", "gasPrice": "0x4a817c800" })),
+            with(json!({ "type": "0x2", "maxPriorityFeePerGas": "0x3b9aca00", "maxFeePerGas": "0x6fc23ac00" })),
+            with(json!({
+                "type": "0x3",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "maxFeePerGas": "0x6fc23ac00",
+                "maxFeePerBlobGas": "0x3b9aca00",
+                "blobVersionedHashes": ["0x01a8c3e5b8b0e2b5b3e1d0f6c4f8e6f0a9b2d4c6e8f0a1b3c5d7e9f1a3b5c7d9"],
+            })),
+            with(json!({
+                "type": "0x4",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "maxFeePerGas": "0x6fc23ac00",
+                "authorizationList": [{
+                    "chainId": "0x1",
+                    "address": "0x00000000000000000000000000000000000000c0",
+                    "nonce": "0x2b",
+                    "yParity": "0x1",
+                    "r": "0x5",
+                    "s": "0x6",
+                }],
+            })),
+        ]
+    }
+
+    /// Sign with the EIP-155 example key
+    pub(crate) fn sign(mut tx: Value) -> Value {
+        let key = SigningKey::from_slice(&[0x46; 32]).unwrap();
+        let hash = signing_hash(&tx).unwrap();
+        let (signature, recovery_id) = key.sign_prehash_recoverable(hash.as_slice()).unwrap();
+        let (r, s) = signature.split_bytes();
+        tx["r"] = json!(format!("{:#x}", U256::from_be_slice(&r)));
+        tx["s"] = json!(format!("{:#x}", U256::from_be_slice(&s)));
+        tx["yParity"] = json!(format!("{:#x}", recovery_id.is_y_odd() as u8));
+        tx
+    }
+
+    #[test]
+    fn eip155_example_signing_hash_and_signer() {
+        let tx = eip155_example();
+        assert_eq!(
+            signing_hash(&tx).unwrap(),
+            "0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53".parse::<B256>().unwrap()
+        );
+        assert_eq!(tx_signature(&tx).unwrap().legacy_chain_id, Some(1));
+        assert_eq!(recover_sender(&tx).unwrap(), EXAMPLE_SIGNER);
+    }
+
+    #[test]
+    fn pre_eip155_signer() {
+        let tx = first_mainnet_transaction();
+        assert_eq!(tx_signature(&tx).unwrap().legacy_chain_id, None);
+        assert_eq!(recover_sender(&tx).unwrap(), address!("a1e4380a3b1f749673e270229993ee55f35663b4"));
+    }
+
+    #[test]
+    fn typed_transactions_recover_their_signer() {
+        for tx in typed_transactions() {
+            let ty = tx_type(&tx);
+            let tx = sign(tx);
+            assert_eq!(recover_sender(&tx).unwrap(), EXAMPLE_SIGNER, "type {}", ty);
+            assert_eq!(signing_payload(&tx).unwrap()[0], ty as u8);
+
+            // Any change to a signed field changes the signer
+            let mut tampered = tx.clone();
+            tampered["nonce"] = json!("0x2b");
+            assert_ne!(recover_sender(&tampered).unwrap(), EXAMPLE_SIGNER, "type {}", ty);
+        }
+    }
+
+    #[test]
+    fn invalid_signatures_are_rejected() {
+        let mut tx = eip155_example();
+        tx["v"] = json!("0x1d");
+        assert!(tx_signature(&tx).is_err());
+
+        let mut tx = sign(typed_transactions().remove(1));
+        tx["yParity"] = json!("0x2");
+        assert!(tx_signature(&tx).is_err());
+    }
+}